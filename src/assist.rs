@@ -0,0 +1,37 @@
+// Author: Eskil Nyberg
+
+/*!
+ * Warnings for beginner-assist UIs, such as throwing away a won game by stalemating the opponent.
+ */
+
+use super::Game;
+use super::GameOverReason;
+use super::Move;
+
+impl Game {
+    /// How many pawns of material the active colour must be ahead by for `stalemate_traps` to warn.
+    pub const CLEARLY_WINNING_MARGIN: i32 = 3;
+
+    /// Returns the legal moves of the active colour that stalemate the opponent, if the active colour is clearly winning
+    /// (ahead by at least `Game::CLEARLY_WINNING_MARGIN` pawns of material). Otherwise, returns an empty vector.
+    ///
+    /// A beginner-assist UI can warn before such a move is made, since it turns a won game into a draw.
+    pub fn stalemate_traps(&self) -> Vec<Move> {
+        if self.is_gameover()
+            || self.material_advantage(self.active_colour) < Game::CLEARLY_WINNING_MARGIN * 100
+        {
+            return vec![];
+        }
+
+        let mut traps = vec![];
+        for mv in self.get_all_possible_moves() {
+            let mut game = self.clone();
+            if game.play_move(mv).is_ok()
+                && game.game_over_reason == Some(GameOverReason::Stalemate)
+            {
+                traps.push(mv);
+            }
+        }
+        return traps;
+    }
+}
@@ -0,0 +1,457 @@
+// Author: Eskil Nyberg
+
+/*!
+ * Precomputed attack tables, used to find which pieces attack a position without trying any moves.
+ *
+ * Sets of positions are stored as bitboards: a `u64` where bit `idx` is set if the position with index `idx` is in the set.
+ */
+
+use super::Colour;
+use super::Game;
+use super::IllegalMoveReason;
+use super::Move;
+use super::Piece;
+use super::PieceType;
+use super::Position;
+
+/// The positions a knight attacks, indexed by the knight's position.
+const KNIGHT_ATTACKS: [u64; 64] = leaper_attacks(&[
+    (2, 1),
+    (2, -1),
+    (1, 2),
+    (1, -2),
+    (-1, 2),
+    (-1, -2),
+    (-2, 1),
+    (-2, -1),
+]);
+
+/// The positions a king attacks, indexed by the king's position.
+pub(crate) const KING_ATTACKS: [u64; 64] = leaper_attacks(&[
+    (1, 1),
+    (1, 0),
+    (1, -1),
+    (0, 1),
+    (0, -1),
+    (-1, 1),
+    (-1, 0),
+    (-1, -1),
+]);
+
+/// The positions a white pawn attacks, indexed by the pawn's position.
+pub(crate) const WHITE_PAWN_ATTACKS: [u64; 64] = leaper_attacks(&[(1, 1), (1, -1)]);
+
+/// The positions a black pawn attacks, indexed by the pawn's position.
+pub(crate) const BLACK_PAWN_ATTACKS: [u64; 64] = leaper_attacks(&[(-1, 1), (-1, -1)]);
+
+/// The positions a rook attacks on an empty board, indexed by the rook's position.
+const ROOK_RAYS: [u64; 64] = slider_rays(&[(1, 0), (0, 1), (0, -1), (-1, 0)]);
+
+/// The positions a bishop attacks on an empty board, indexed by the bishop's position.
+const BISHOP_RAYS: [u64; 64] = slider_rays(&[(1, 1), (1, -1), (-1, 1), (-1, -1)]);
+
+/// The positions strictly between two positions on the same rank, file or diagonal, indexed by both positions.
+///
+/// Is 0 if the positions are not on the same rank, file or diagonal.
+static BETWEEN: [[u64; 64]; 64] = between();
+
+/// Returns the bitboard of the position at `rank` and `file`, or 0 if it is outside the board.
+const fn bit(rank: i32, file: i32) -> u64 {
+    if rank < 0 || rank > 7 || file < 0 || file > 7 {
+        return 0;
+    }
+    return 1 << (rank * 8 + file);
+}
+
+/// Returns the attack table of a piece that jumps by one of `steps`.
+const fn leaper_attacks(steps: &[(i32, i32)]) -> [u64; 64] {
+    let mut table = [0; 64];
+    let mut idx = 0;
+    while idx < 64 {
+        let mut i = 0;
+        while i < steps.len() {
+            table[idx] |= bit(idx as i32 / 8 + steps[i].0, idx as i32 % 8 + steps[i].1);
+            i += 1;
+        }
+        idx += 1;
+    }
+    return table;
+}
+
+/// Returns the attack table on an empty board of a piece that slides in the directions `steps`.
+const fn slider_rays(steps: &[(i32, i32)]) -> [u64; 64] {
+    let mut table = [0; 64];
+    let mut idx = 0;
+    while idx < 64 {
+        let mut i = 0;
+        while i < steps.len() {
+            let mut distance = 1;
+            while distance < 8 {
+                table[idx] |= bit(
+                    idx as i32 / 8 + steps[i].0 * distance,
+                    idx as i32 % 8 + steps[i].1 * distance,
+                );
+                distance += 1;
+            }
+            i += 1;
+        }
+        idx += 1;
+    }
+    return table;
+}
+
+/// Returns the table `BETWEEN`.
+const fn between() -> [[u64; 64]; 64] {
+    let mut table = [[0; 64]; 64];
+    let mut from = 0;
+    while from < 64 {
+        let mut to = 0;
+        while to < 64 {
+            let rank_diff = to as i32 / 8 - from as i32 / 8;
+            let file_diff = to as i32 % 8 - from as i32 % 8;
+            if from != to
+                && (rank_diff == 0 || file_diff == 0 || rank_diff.abs() == file_diff.abs())
+            {
+                // On a line, the distance is the larger of the rank and file differences
+                let steps = if rank_diff == 0 {
+                    file_diff.abs()
+                } else {
+                    rank_diff.abs()
+                };
+                let mut distance = 1;
+                while distance < steps {
+                    table[from][to] |= bit(
+                        from as i32 / 8 + rank_diff.signum() * distance,
+                        from as i32 % 8 + file_diff.signum() * distance,
+                    );
+                    distance += 1;
+                }
+            }
+            to += 1;
+        }
+        from += 1;
+    }
+    return table;
+}
+
+/// Returns the bitboard of the positions on `board` that have a piece.
+fn occupancy(board: &[Option<Piece>; 8 * 8]) -> u64 {
+    let mut occupied = 0;
+    for (i, piece) in board.iter().enumerate() {
+        if piece.is_some() {
+            occupied |= 1 << i;
+        }
+    }
+    return occupied;
+}
+
+/// Returns the bitboard of the positions that `piece` at index `idx` attacks, where `occupied` is the occupancy of the board.
+fn piece_attacks(piece: Piece, idx: usize, occupied: u64) -> u64 {
+    let rays = match piece.piece_type {
+        PieceType::Pawn => {
+            return match piece.colour {
+                Colour::White => WHITE_PAWN_ATTACKS[idx],
+                Colour::Black => BLACK_PAWN_ATTACKS[idx],
+            }
+        }
+        PieceType::Knight => return KNIGHT_ATTACKS[idx],
+        PieceType::King => return KING_ATTACKS[idx],
+        PieceType::Rook => ROOK_RAYS[idx],
+        PieceType::Bishop => BISHOP_RAYS[idx],
+        PieceType::Queen => ROOK_RAYS[idx] | BISHOP_RAYS[idx],
+    };
+    // Sliding pieces attack along their rays up to and including the first piece.
+    let mut attacks = 0;
+    for (target, between) in BETWEEN[idx].iter().enumerate() {
+        if rays & (1 << target) != 0 && between & occupied == 0 {
+            attacks |= 1 << target;
+        }
+    }
+    return attacks;
+}
+
+impl Game {
+    /// Returns the positions of the pieces of colour `by_colour` that attack `pos` on `board`.
+    ///
+    /// A piece attacks a position if it could capture a piece there, whether or not the capture would put its own king in check.
+    /// This only looks at the board, so that legality can be checked on a board where a move has been tried.
+    pub(crate) fn attackers(
+        board: &[Option<Piece>; 8 * 8],
+        pos: Position,
+        by_colour: Colour,
+    ) -> Vec<Position> {
+        let occupied = occupancy(board);
+        let target = 1 << pos.idx;
+        let mut attackers = vec![];
+        for (i, piece) in board.iter().enumerate() {
+            let piece = match piece {
+                Some(piece) if piece.colour == by_colour => piece,
+                _ => continue,
+            };
+            // Sliding pieces are blocked by any piece between them and `pos`.
+            let unblocked = BETWEEN[i][pos.idx] & occupied == 0;
+            let attacks = match piece.piece_type {
+                PieceType::Pawn => match by_colour {
+                    Colour::White => WHITE_PAWN_ATTACKS[i] & target != 0,
+                    Colour::Black => BLACK_PAWN_ATTACKS[i] & target != 0,
+                },
+                PieceType::Knight => KNIGHT_ATTACKS[i] & target != 0,
+                PieceType::King => KING_ATTACKS[i] & target != 0,
+                PieceType::Rook => ROOK_RAYS[i] & target != 0 && unblocked,
+                PieceType::Bishop => BISHOP_RAYS[i] & target != 0 && unblocked,
+                PieceType::Queen => (ROOK_RAYS[i] | BISHOP_RAYS[i]) & target != 0 && unblocked,
+            };
+            if attacks {
+                attackers.push(Position::new_from_idx(i).expect("enumerated"));
+            }
+        }
+        return attackers;
+    }
+
+    /// Returns the bitboard of every position that some piece of colour `colour` attacks on `board`.
+    pub(crate) fn attacked_by(board: &[Option<Piece>; 8 * 8], colour: Colour) -> u64 {
+        let occupied = occupancy(board);
+        let mut attacked = 0;
+        for (i, piece) in board.iter().enumerate() {
+            if let Some(piece) = piece.filter(|p| p.colour == colour) {
+                attacked |= piece_attacks(piece, i, occupied);
+            }
+        }
+        return attacked;
+    }
+
+    /// Returns, for each position by index, how many pieces of colour `colour` attack it, e.g. to draw a heat map of threats.
+    ///
+    /// Works regardless of whose turn it is. As in `is_square_attacked`, a piece attacks a position if it could capture
+    /// a piece there, also when the capture would put its own king in check, or when a piece of its own colour stands there.
+    ///
+    /// # Example code
+    ///
+    /// ```rust
+    /// use chess_engine::*;
+    ///
+    /// let game = Game::new();
+    /// let map = game.attack_map(Colour::White);
+    /// assert_eq!(map[Position::parse_str("f3").unwrap().idx], 3); // the g1 knight and the e2 and g2 pawns
+    /// assert_eq!(map[Position::parse_str("e4").unwrap().idx], 0);
+    /// ```
+    pub fn attack_map(&self, colour: Colour) -> [u8; 64] {
+        let occupied = occupancy(&self.board);
+        let mut map = [0; 64];
+        for (i, piece) in self.board.iter().enumerate() {
+            if let Some(piece) = piece.filter(|p| p.colour == colour) {
+                let attacks = piece_attacks(piece, i, occupied);
+                for (target, count) in map.iter_mut().enumerate() {
+                    if attacks & (1 << target) != 0 {
+                        *count += 1;
+                    }
+                }
+            }
+        }
+        return map;
+    }
+
+    /// Returns why the piece at `from` cannot move to `to`, or None if `to` is one of its possible moves,
+    /// like `get_possible_moves(from)` without the restrictions of teaching mode, but without generating
+    /// the other moves of the piece. Neither the game state nor the active colour is checked.
+    pub(crate) fn move_obstacle(&self, from: Position, to: Position) -> Option<IllegalMoveReason> {
+        let piece = match self.board[from.idx] {
+            Some(piece) => piece,
+            None => return Some(IllegalMoveReason::NoPiece),
+        };
+        let own_piece_on_target = self.board[to.idx].is_some_and(|p| p.colour == piece.colour);
+        match piece.piece_type {
+            PieceType::King if from.rank == to.rank && from.file.abs_diff(to.file) == 2 => {
+                // Castling has too many conditions to repeat here, so the king's moves are generated
+                let mut found = false;
+                self._for_each_possible_move(from, &mut |pos| found |= pos == to)
+                    .expect("from is valid");
+                if !found {
+                    return Some(IllegalMoveReason::CannotCastle);
+                }
+                return None;
+            }
+            PieceType::Pawn if from.file == to.file => {
+                // Pawns move forward once, or twice from their first rank, onto empty squares only
+                let dir = piece.colour.pawn_dir();
+                let steps = (to.rank as i32 - from.rank as i32) * dir;
+                let first_rank = if piece.is_white() { 1 } else { 6 };
+                if !(steps == 1 || steps == 2 && from.rank == first_rank) {
+                    return Some(IllegalMoveReason::CannotMoveThatWay);
+                }
+                if own_piece_on_target {
+                    return Some(IllegalMoveReason::OwnPieceOnTarget);
+                }
+                let between = (from.idx as i32 + 8 * dir) as usize;
+                if self.board[to.idx].is_some() || steps == 2 && self.board[between].is_some() {
+                    return Some(IllegalMoveReason::PathBlocked);
+                }
+            }
+            PieceType::Pawn => {
+                // Pawns capture diagonally, including en passant
+                if piece_attacks(piece, from.idx, 0) & (1 << to.idx) == 0 {
+                    return Some(IllegalMoveReason::CannotMoveThatWay);
+                }
+                if own_piece_on_target {
+                    return Some(IllegalMoveReason::OwnPieceOnTarget);
+                }
+                if self.board[to.idx].is_none()
+                    && !(to == self.en_passant_target && piece.colour == self.active_colour)
+                {
+                    return Some(IllegalMoveReason::NothingToCapture);
+                }
+            }
+            _ => {
+                // With an empty board, the attacks are the ways the piece moves
+                if piece_attacks(piece, from.idx, 0) & (1 << to.idx) == 0 {
+                    return Some(IllegalMoveReason::CannotMoveThatWay);
+                }
+                if own_piece_on_target {
+                    return Some(IllegalMoveReason::OwnPieceOnTarget);
+                }
+                if piece_attacks(piece, from.idx, occupancy(&self.board)) & (1 << to.idx) == 0 {
+                    return Some(IllegalMoveReason::PathBlocked);
+                }
+            }
+        }
+        if !self.leaves_king_safe(from, to) {
+            return Some(IllegalMoveReason::LeavesKingInCheck);
+        }
+        if !self.rules.allows_move(self, from, to) {
+            return Some(IllegalMoveReason::ForbiddenByVariant);
+        }
+        return None;
+    }
+
+    /// Returns every possible move of the active colour that captures a piece, as a vector of `Move`-s with flags set.
+    ///
+    /// Gives the same moves as `get_all_possible_moves()` filtered on `is_capture`, en passant and promotions included,
+    /// but only tries the positions each piece attacks, so it is much faster. Used by the quiescence search.
+    /// In teaching mode, moves that violate the restrictions are left out.
+    ///
+    /// # Example code
+    ///
+    /// ```rust
+    /// use chess_engine::*;
+    ///
+    /// let game = Game::from_fen("4k3/8/8/3p4/4P3/8/8/4K3 w - - 0 1").unwrap();
+    /// let captures = game.get_capture_moves_all();
+    /// assert_eq!(captures.len(), 1);
+    /// assert_eq!(captures[0].to, Position::parse_str("d5").unwrap());
+    /// ```
+    pub fn get_capture_moves_all(&self) -> Vec<Move> {
+        let occupied = occupancy(&self.board);
+        let mut opponents = 0;
+        for (i, piece) in self.board.iter().enumerate() {
+            if piece.is_some_and(|p| p.colour != self.active_colour) {
+                opponents |= 1 << i;
+            }
+        }
+        let en_passant = if self.en_passant_target == Position::NULL {
+            0
+        } else {
+            1 << self.en_passant_target.idx
+        };
+
+        let mut moves = vec![];
+        for (i, piece) in self.board.iter().enumerate() {
+            let piece = match piece {
+                Some(piece) if piece.colour == self.active_colour => *piece,
+                _ => continue,
+            };
+            let from = Position::new_from_idx(i).expect("enumerated");
+            let mut targets = piece_attacks(piece, i, occupied)
+                & if piece.is_pawn() {
+                    opponents | en_passant
+                } else {
+                    opponents
+                };
+            while targets != 0 {
+                let to = Position::new_from_idx(targets.trailing_zeros() as usize)
+                    .expect("on the board");
+                targets &= targets - 1;
+                if let Some(restrictions) = &self.restrictions {
+                    if restrictions.check(piece, to).is_err() {
+                        continue;
+                    }
+                }
+                if !self.leaves_king_safe(from, to) || !self.rules.allows_move(self, from, to) {
+                    continue;
+                }
+                let mv = Move {
+                    is_capture: true,
+                    is_en_passant: piece.is_pawn() && to == self.en_passant_target,
+                    ..Move::new(from, to)
+                };
+                if piece.is_pawn() && (to.rank == 0 || to.rank == 7) {
+                    for promotion in self.rules.promotion_piece_types() {
+                        moves.push(Move {
+                            promotion: Some(promotion),
+                            ..mv
+                        });
+                    }
+                } else {
+                    moves.push(mv);
+                }
+            }
+        }
+        return moves;
+    }
+
+    /// Returns every piece of colour `colour` that is absolutely pinned, as pairs of the position of the pinned piece
+    /// and the position of the pinning attacker.
+    ///
+    /// A piece is absolutely pinned if it is the only piece between its king and an enemy rook, bishop or queen
+    /// that moves along that line, so that it may only move along the line (if at all). Is empty if `colour` has no king on the board.
+    ///
+    /// # Example code
+    ///
+    /// ```rust
+    /// use chess_engine::*;
+    ///
+    /// // The bishop on b4 pins the knight on c3 to the king on e1
+    /// let game = Game::from_fen("4k3/8/8/8/1b6/2N5/8/4K3 w - - 0 1").unwrap();
+    /// let pins = game.pinned_pieces(Colour::White);
+    /// assert_eq!(
+    ///     pins,
+    ///     vec![(Position::parse_str("c3").unwrap(), Position::parse_str("b4").unwrap())]
+    /// );
+    /// ```
+    pub fn pinned_pieces(&self, colour: Colour) -> Vec<(Position, Position)> {
+        let king_pos = match self.find_king(colour) {
+            Ok(pos) => pos,
+            Err(_) => return vec![],
+        };
+
+        let occupied = occupancy(&self.board);
+
+        let mut pins = vec![];
+        for (i, piece) in self.board.iter().enumerate() {
+            let piece = match piece {
+                Some(piece) if piece.colour != colour => piece,
+                _ => continue,
+            };
+            let rays = match piece.piece_type {
+                PieceType::Rook => ROOK_RAYS[i],
+                PieceType::Bishop => BISHOP_RAYS[i],
+                PieceType::Queen => ROOK_RAYS[i] | BISHOP_RAYS[i],
+                _ => continue,
+            };
+            if rays & (1 << king_pos.idx) == 0 {
+                continue;
+            }
+            // Exactly one piece between the attacker and the king, of the king's colour
+            let blockers = BETWEEN[i][king_pos.idx] & occupied;
+            if blockers.count_ones() == 1 {
+                let pinned_idx = blockers.trailing_zeros() as usize;
+                if self.board[pinned_idx].is_some_and(|p| p.colour == colour) {
+                    pins.push((
+                        Position::new_from_idx(pinned_idx).expect("on the board"),
+                        Position::new_from_idx(i).expect("enumerated"),
+                    ));
+                }
+            }
+        }
+        return pins;
+    }
+}
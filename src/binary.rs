@@ -0,0 +1,227 @@
+// Author: Eskil Nyberg
+
+/*!
+ * A compact binary encoding of positions, for storing many positions or sending them over a network.
+ *
+ * The encoding is, in order:
+ * * 8 bytes: the occupied positions as a little-endian bitboard, where bit `idx` is set if `idx` has a piece.
+ * * 1 byte: the active colour (bit 0 set for black) and the castling rights
+ *   (bits 1-4 for white kingside, white queenside, black kingside and black queenside).
+ * * 1 byte: the file of the en passant target plus one, or 0 if there is none.
+ * * 1 byte: the halfmove clock, at most 255.
+ * * 2 bytes: the fullmove number, little-endian.
+ * * A nibble per piece, in the order of the occupied positions, two to a byte with the first piece in the low nibble:
+ *   the piece type (0-5 for king, queen, rook, knight, bishop and pawn) plus 8 for black pieces.
+ *
+ * A position with 32 pieces thus takes 29 bytes.
+ */
+
+use std::convert::TryInto;
+
+use super::CastlingRights;
+use super::ChessError;
+use super::Colour;
+use super::Game;
+use super::Piece;
+use super::PieceType;
+use super::Position;
+
+/// The number of bytes before the pieces.
+const HEADER_LENGTH: usize = 13;
+
+const PIECE_TYPES: [PieceType; 6] = [
+    PieceType::King,
+    PieceType::Queen,
+    PieceType::Rook,
+    PieceType::Knight,
+    PieceType::Bishop,
+    PieceType::Pawn,
+];
+
+impl Game {
+    /// Returns the position encoded in at most 29 bytes (for positions with at most 32 pieces): the placement of
+    /// the pieces, the active colour, the castling rights, the en passant target and the clocks.
+    ///
+    /// The encoding is deterministic, so equal positions give equal bytes, and is read back with `from_bytes`.
+    /// Like `fen()`, the history, the game over reason of a finished game and the rules are not included.
+    /// A halfmove clock above 255 is stored as 255, which keeps the 50 and 75-move rules, and a fullmove number
+    /// above 65535 is stored as 65535.
+    ///
+    /// # Example code
+    ///
+    /// ```rust
+    /// use chess_engine::*;
+    ///
+    /// let mut game = Game::new();
+    /// game.make_move("e2", "e4").unwrap();
+    /// let bytes = game.to_bytes();
+    /// assert_eq!(bytes.len(), 29);
+    /// assert_eq!(Game::from_bytes(&bytes).unwrap().fen(), game.fen());
+    /// ```
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut occupied: u64 = 0;
+        let mut nibbles = vec![];
+        for (idx, piece) in self.board.iter().enumerate() {
+            if let Some(piece) = piece {
+                occupied |= 1 << idx;
+                let type_code = PIECE_TYPES
+                    .iter()
+                    .position(|piece_type| *piece_type == piece.piece_type)
+                    .expect("every piece type is listed") as u8;
+                nibbles.push(type_code | if piece.is_white() { 0 } else { 8 });
+            }
+        }
+
+        let mut bytes = Vec::with_capacity(HEADER_LENGTH + nibbles.len().div_ceil(2));
+        bytes.extend_from_slice(&occupied.to_le_bytes());
+        bytes.push(
+            if self.active_colour.is_white() { 0 } else { 1 } | self.castling_rights.bits() << 1,
+        );
+        bytes.push(if self.en_passant_target == Position::NULL {
+            0
+        } else {
+            self.en_passant_target.file as u8 + 1
+        });
+        bytes.push(self.halfmoves.min(u8::MAX as u16) as u8);
+        bytes.extend_from_slice(&(self.fullmoves.min(u16::MAX as u32) as u16).to_le_bytes());
+        for pair in nibbles.chunks(2) {
+            bytes.push(pair[0] | pair.get(1).map_or(0, |nibble| nibble << 4));
+        }
+        return bytes;
+    }
+
+    /// Constructor that reads a position encoded by `to_bytes`. The game starts without history, like with `from_fen`.
+    ///
+    /// Errors if `bytes` is not a valid encoding, or the position breaks the same rules as for `from_fen`:
+    /// one king per colour, castling rights with the king and rook in place, an en passant target with a pawn in front
+    /// of it, a fullmove number of at least 1 and no pawns on the first or last rank.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Game, ChessError> {
+        if bytes.len() < HEADER_LENGTH {
+            return Err(ChessError::InvalidBytes(format!(
+                "The encoding should have at least {} bytes, but has {}",
+                HEADER_LENGTH,
+                bytes.len()
+            )));
+        }
+        let occupied = u64::from_le_bytes(bytes[0..8].try_into().expect("8 bytes"));
+        let piece_count = occupied.count_ones() as usize;
+        if bytes.len() != HEADER_LENGTH + piece_count.div_ceil(2) {
+            return Err(ChessError::InvalidBytes(format!(
+                "The encoding of {} pieces should have {} bytes, but has {}",
+                piece_count,
+                HEADER_LENGTH + piece_count.div_ceil(2),
+                bytes.len()
+            )));
+        }
+
+        let mut game = Game::new();
+        game.board = [None; 8 * 8];
+        let mut piece = 0;
+        for idx in 0..64 {
+            if occupied & 1 << idx == 0 {
+                continue;
+            }
+            let nibble = (bytes[HEADER_LENGTH + piece / 2] >> (piece % 2 * 4)) & 0xf;
+            let piece_type = match PIECE_TYPES.get((nibble & 7) as usize) {
+                Some(piece_type) => *piece_type,
+                None => {
+                    return Err(ChessError::InvalidBytes(format!(
+                        "{} is not a valid piece",
+                        nibble
+                    )))
+                }
+            };
+            let colour = if nibble & 8 == 0 {
+                Colour::White
+            } else {
+                Colour::Black
+            };
+            game.board[idx] = Some(Piece { piece_type, colour });
+            piece += 1;
+        }
+        if piece_count % 2 == 1 && bytes[bytes.len() - 1] >> 4 != 0 {
+            return Err(ChessError::InvalidBytes(
+                "The unused last nibble is not 0".to_owned(),
+            ));
+        }
+
+        let flags = bytes[8];
+        if flags >> 5 != 0 {
+            return Err(ChessError::InvalidBytes(format!(
+                "{:#010b} has unused flags set",
+                flags
+            )));
+        }
+        game.active_colour = if flags & 1 == 0 {
+            Colour::White
+        } else {
+            Colour::Black
+        };
+        game.castling_rights = CastlingRights::from_bits(flags >> 1);
+
+        game.en_passant_target = match bytes[9] {
+            0 => Position::NULL,
+            file @ 1..=8 => {
+                // The target is behind a pawn of the inactive colour that just moved two squares.
+                let rank = match game.active_colour {
+                    Colour::White => 5,
+                    Colour::Black => 2,
+                };
+                Position::new(rank, file as usize - 1)?
+            }
+            file => {
+                return Err(ChessError::InvalidBytes(format!(
+                    "{} is not a valid en passant file",
+                    file
+                )))
+            }
+        };
+        if game.en_passant_target_without_pawn() {
+            return Err(ChessError::InvalidBytes(format!(
+                "There is no pawn of {:?} in front of the en passant target {}",
+                game.active_colour.invert(),
+                game.en_passant_target
+            )));
+        }
+        game.halfmoves = bytes[10] as u16;
+        game.fullmoves = u16::from_le_bytes([bytes[11], bytes[12]]) as u32;
+        if game.fullmoves == 0 {
+            return Err(ChessError::InvalidBytes(
+                "The fullmove number should be at least 1".to_owned(),
+            ));
+        }
+
+        for colour in [Colour::White, Colour::Black] {
+            let kings = game
+                .board
+                .iter()
+                .flatten()
+                .filter(|p| p.is_king() && p.colour == colour)
+                .count();
+            if kings != 1 {
+                return Err(ChessError::InvalidBytes(format!(
+                    "The {:?} side should have exactly one king, but has {}",
+                    colour, kings
+                )));
+            }
+        }
+        if !game.castling_rights_without_pieces().is_empty() {
+            return Err(ChessError::InvalidBytes(
+                "The castling rights do not match the placement of the kings and rooks".to_owned(),
+            ));
+        }
+        for file in 0..8 {
+            if game.board[Position::idx(0, file)].is_some_and(|p| p.is_pawn())
+                || game.board[Position::idx(7, file)].is_some_and(|p| p.is_pawn())
+            {
+                return Err(ChessError::InvalidBytes(
+                    "A pawn stands on the first or last rank".to_owned(),
+                ));
+            }
+        }
+
+        game.position_keys = vec![game.position_key()];
+        game.evaluate_game_state();
+        return Ok(game);
+    }
+}
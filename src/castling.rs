@@ -0,0 +1,191 @@
+// Author: Eskil Nyberg
+
+/*!
+ * The castling rights of both colours, kept as bit flags.
+ */
+
+use std::fmt;
+
+use super::ChessError;
+use super::Colour;
+
+/// A side of the board that the king can castle to.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum CastlingSide {
+    /// Towards the h-file, O-O.
+    Kingside,
+    /// Towards the a-file, O-O-O.
+    Queenside,
+}
+
+impl CastlingSide {
+    /// Returns the file (0-7) of the rook that the king castles with.
+    pub const fn rook_file(&self) -> usize {
+        return match self {
+            CastlingSide::Kingside => 7,
+            CastlingSide::Queenside => 0,
+        };
+    }
+
+    /// Returns the file (0-7) that the king castles to.
+    pub(crate) const fn king_file(&self) -> usize {
+        return match self {
+            CastlingSide::Kingside => 6,
+            CastlingSide::Queenside => 2,
+        };
+    }
+
+    /// Returns the file (0-7) that the rook castles to.
+    pub(crate) const fn rook_target_file(&self) -> usize {
+        return match self {
+            CastlingSide::Kingside => 5,
+            CastlingSide::Queenside => 3,
+        };
+    }
+}
+
+/// Returns the rank (0-7) that the king and rooks of `colour` start on and castle on.
+pub(crate) const fn home_rank(colour: Colour) -> usize {
+    return match colour {
+        Colour::White => 0,
+        Colour::Black => 7,
+    };
+}
+
+/// The castling rights of both colours, see `Game::castling_rights`.
+///
+/// A right is kept until the king or the rook moves (or the rook is captured), even while castling is blocked.
+/// It is written like in FEN, e.g. "KQkq", or "-" without rights.
+///
+/// # Example code
+///
+/// ```rust
+/// use chess_engine::*;
+///
+/// let mut rights = CastlingRights::from_fen("KQk").unwrap();
+/// assert!(rights.can_castle(Colour::Black, CastlingSide::Kingside));
+/// assert!(!rights.can_castle(Colour::Black, CastlingSide::Queenside));
+/// rights.remove(Colour::White, CastlingSide::Queenside);
+/// assert_eq!(rights.to_string(), "Kk");
+/// ```
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CastlingRights {
+    /// Bits 0-3 for white kingside, white queenside, black kingside and black queenside.
+    bits: u8,
+}
+
+/// Every castling right, in the order of the bits and of FEN.
+pub(crate) const RIGHTS: [(Colour, CastlingSide); 4] = [
+    (Colour::White, CastlingSide::Kingside),
+    (Colour::White, CastlingSide::Queenside),
+    (Colour::Black, CastlingSide::Kingside),
+    (Colour::Black, CastlingSide::Queenside),
+];
+
+impl CastlingRights {
+    /// No castling rights.
+    pub const NONE: CastlingRights = CastlingRights { bits: 0 };
+    /// Every castling right, as at the start of a game.
+    pub const ALL: CastlingRights = CastlingRights { bits: 0b1111 };
+
+    /// Returns the bit of the right of `colour` to castle to `side`.
+    const fn bit(colour: Colour, side: CastlingSide) -> u8 {
+        let colour_offset = match colour {
+            Colour::White => 0,
+            Colour::Black => 2,
+        };
+        let side_offset = match side {
+            CastlingSide::Kingside => 0,
+            CastlingSide::Queenside => 1,
+        };
+        return 1 << (colour_offset + side_offset);
+    }
+
+    /// Returns true if `colour` has the right to castle to `side`.
+    pub const fn can_castle(&self, colour: Colour, side: CastlingSide) -> bool {
+        return self.bits & CastlingRights::bit(colour, side) != 0;
+    }
+
+    /// Gives `colour` the right to castle to `side`.
+    pub fn insert(&mut self, colour: Colour, side: CastlingSide) {
+        self.bits |= CastlingRights::bit(colour, side);
+    }
+
+    /// Takes away the right of `colour` to castle to `side`.
+    pub fn remove(&mut self, colour: Colour, side: CastlingSide) {
+        self.bits &= !CastlingRights::bit(colour, side);
+    }
+
+    /// Takes away both rights of `colour`, e.g. when its king moves.
+    pub fn remove_colour(&mut self, colour: Colour) {
+        self.remove(colour, CastlingSide::Kingside);
+        self.remove(colour, CastlingSide::Queenside);
+    }
+
+    /// Returns true if neither colour can castle.
+    pub const fn is_empty(&self) -> bool {
+        return self.bits == 0;
+    }
+
+    /// Returns the rights as bits 0-3 for white kingside, white queenside, black kingside and black queenside.
+    pub const fn bits(&self) -> u8 {
+        return self.bits;
+    }
+
+    /// Constructor for the rights of `bits`, see `bits`. Bits above bit 3 are ignored.
+    pub const fn from_bits(bits: u8) -> CastlingRights {
+        return CastlingRights {
+            bits: bits & CastlingRights::ALL.bits,
+        };
+    }
+
+    /// Returns the rights with the colours swapped, e.g. for a board with flipped colours.
+    pub const fn mirrored(&self) -> CastlingRights {
+        return CastlingRights {
+            bits: (self.bits >> 2) | ((self.bits & 0b11) << 2),
+        };
+    }
+
+    /// Parses the castling field of a FEN, e.g. "KQkq", or "-" without rights.
+    ///
+    /// Errors if a character is not a castling right.
+    pub fn from_fen(field: &str) -> Result<CastlingRights, ChessError> {
+        let mut rights = CastlingRights::NONE;
+        if field == "-" {
+            return Ok(rights);
+        }
+        for ch in field.chars() {
+            let (colour, side) = match ch {
+                'K' => RIGHTS[0],
+                'Q' => RIGHTS[1],
+                'k' => RIGHTS[2],
+                'q' => RIGHTS[3],
+                _ => {
+                    return Err(ChessError::InvalidFen(format!(
+                        "'{}' is not a valid castling right",
+                        ch
+                    )))
+                }
+            };
+            rights.insert(colour, side);
+        }
+        return Ok(rights);
+    }
+}
+
+/// Writes the rights as the castling field of a FEN.
+impl fmt::Display for CastlingRights {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.is_empty() {
+            return write!(f, "-");
+        }
+        for (ch, (colour, side)) in ['K', 'Q', 'k', 'q'].iter().zip(RIGHTS) {
+            if self.can_castle(colour, side) {
+                write!(f, "{}", ch)?;
+            }
+        }
+        return Ok(());
+    }
+}
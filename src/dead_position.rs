@@ -0,0 +1,185 @@
+// Author: Eskil Nyberg
+
+/*!
+ * Detection of dead positions (FIDE Laws of Chess 5.2.2), where no sequence of legal moves can lead to checkmate.
+ *
+ * Two kinds are detected: insufficient material, and walls of blocked pawns that neither king can break through.
+ * Other dead positions exist, so a position that is not detected may still be dead.
+ */
+
+use super::attacks::{BLACK_PAWN_ATTACKS, KING_ATTACKS, WHITE_PAWN_ATTACKS};
+use super::Colour;
+use super::Game;
+use super::PieceType;
+
+impl Game {
+    /// Returns true if neither colour has the material to checkmate, whatever the other colour plays.
+    ///
+    /// That is the case when only kings and bishops remain and every bishop stands on the same colour of square,
+    /// or when only kings and a single knight remain.
+    pub fn is_insufficient_material(&self) -> bool {
+        let mut bishop_square_colours = [false; 2];
+        let mut knights = 0;
+        for (idx, piece) in self.board.iter().enumerate() {
+            match piece.map(|p| p.piece_type) {
+                None | Some(PieceType::King) => {}
+                Some(PieceType::Bishop) => bishop_square_colours[(idx / 8 + idx % 8) % 2] = true,
+                Some(PieceType::Knight) => knights += 1,
+                Some(_) => return false,
+            }
+        }
+        return match knights {
+            0 => !(bishop_square_colours[0] && bishop_square_colours[1]),
+            1 => !bishop_square_colours[0] && !bishop_square_colours[1],
+            _ => false,
+        };
+    }
+
+    /// Returns true if `colour` has the material to checkmate by some sequence of legal moves, even with the help
+    /// of the other colour. A colour without it cannot win, so when the other colour runs out of time the game is
+    /// drawn rather than lost, see `submit_timeout`.
+    ///
+    /// A lone king has no mating material. Neither has a king with a single knight if the other colour has only
+    /// queens to block its king with, nor a king with bishops if every bishop on the board stands on the same
+    /// colour of square and the other colour has no knights or pawns. Any other material is enough.
+    ///
+    /// # Example code
+    ///
+    /// ```rust
+    /// use chess_engine::*;
+    ///
+    /// // White has a lone king, black a king and a knight
+    /// let game = Game::from_fen("4k3/8/8/8/8/8/8/2n1K3 w - - 0 1").unwrap();
+    /// assert!(!game.has_mating_material(Colour::White));
+    /// assert!(!game.has_mating_material(Colour::Black));
+    /// assert!(Game::new().has_mating_material(Colour::White));
+    /// ```
+    pub fn has_mating_material(&self, colour: Colour) -> bool {
+        let mut own_knights = 0;
+        let mut own_bishops = 0;
+        let mut bishop_square_colours = [false; 2];
+        let mut other_blockers = false;
+        let mut other_knights_or_pawns = false;
+        for (idx, piece) in self.board.iter().enumerate() {
+            let piece = match piece {
+                Some(piece) => piece,
+                None => continue,
+            };
+            if piece.piece_type == PieceType::Bishop {
+                bishop_square_colours[(idx / 8 + idx % 8) % 2] = true;
+            }
+            if piece.colour != colour {
+                match piece.piece_type {
+                    PieceType::King | PieceType::Queen => {}
+                    PieceType::Knight | PieceType::Pawn => {
+                        other_blockers = true;
+                        other_knights_or_pawns = true;
+                    }
+                    _ => other_blockers = true,
+                }
+                continue;
+            }
+            match piece.piece_type {
+                PieceType::King => {}
+                PieceType::Knight => own_knights += 1,
+                PieceType::Bishop => own_bishops += 1,
+                _ => return true,
+            }
+        }
+        return match (own_knights, own_bishops) {
+            (0, 0) => false,
+            (1, 0) => other_blockers,
+            (0, _) => {
+                (bishop_square_colours[0] && bishop_square_colours[1]) || other_knights_or_pawns
+            }
+            _ => true,
+        };
+    }
+
+    /// Returns true if the position is dead, so that the game is drawn: no sequence of legal moves
+    /// can lead to checkmate, see `is_insufficient_material`.
+    ///
+    /// Besides insufficient material, this detects positions where only kings and pawns remain, every pawn
+    /// is blocked by another pawn and has nothing to capture, and neither king can reach an enemy pawn that
+    /// it could capture. Then no pawn will move again, so no check can be given.
+    ///
+    /// # Example code
+    ///
+    /// ```rust
+    /// use chess_engine::*;
+    ///
+    /// // The kings cannot cross the wall of pawns
+    /// let game = Game::from_fen("4k3/8/1p1p1p1p/pPpPpPpP/P1P1P1P1/8/8/4K3 w - - 0 1").unwrap();
+    /// assert!(game.is_dead_position());
+    /// assert!(!Game::new().is_dead_position());
+    /// ```
+    pub fn is_dead_position(&self) -> bool {
+        return self.is_insufficient_material() || self.is_blocked_pawn_wall();
+    }
+
+    /// Returns true if only kings and pawns remain, and the pawns can never move again, see `is_dead_position`.
+    pub(crate) fn is_blocked_pawn_wall(&self) -> bool {
+        // Bitboards of the pawns, indexed white then black
+        let mut pawns = [0u64; 2];
+        for (idx, piece) in self.board.iter().enumerate() {
+            match piece {
+                None => {}
+                Some(piece) if piece.is_king() => {}
+                Some(piece) if piece.is_pawn() => pawns[piece.colour as usize] |= 1 << idx,
+                Some(_) => return false,
+            }
+        }
+        let all_pawns = pawns[0] | pawns[1];
+        if all_pawns == 0 || self.is_in_check(self.active_colour) {
+            return false;
+        }
+
+        // Every pawn must be blocked by a pawn in front of it, and have no pawn to capture
+        let mut pawn_attacks = [0u64; 2];
+        for colour in [Colour::White, Colour::Black] {
+            let own = pawns[colour as usize];
+            let enemy = pawns[colour.invert() as usize];
+            for idx in (0..64).filter(|idx| own & (1 << idx) != 0) {
+                let attacks = match colour {
+                    Colour::White => WHITE_PAWN_ATTACKS[idx],
+                    Colour::Black => BLACK_PAWN_ATTACKS[idx],
+                };
+                let front = idx as i32 + 8 * colour.pawn_dir();
+                if !(0..64).contains(&front)
+                    || all_pawns & (1 << front) == 0
+                    || attacks & enemy != 0
+                {
+                    return false;
+                }
+                pawn_attacks[colour as usize] |= attacks;
+            }
+        }
+
+        // Neither king may reach a pawn that it can capture, walking on squares free of pawns and pawn attacks
+        for colour in [Colour::White, Colour::Black] {
+            let king = match self.find_king(colour) {
+                Ok(king) => king,
+                Err(_) => return false,
+            };
+            let enemy_pawns = pawns[colour.invert() as usize];
+            let enemy_attacks = pawn_attacks[colour.invert() as usize];
+            let mut region: u64 = 1 << king.idx;
+            loop {
+                let mut next = region;
+                for idx in (0..64).filter(|idx| region & (1 << idx) != 0) {
+                    next |= KING_ATTACKS[idx] & !all_pawns & !enemy_attacks;
+                }
+                if next == region {
+                    break;
+                }
+                region = next;
+            }
+            for idx in (0..64).filter(|idx| region & (1 << idx) != 0) {
+                if KING_ATTACKS[idx] & enemy_pawns & !enemy_attacks != 0 {
+                    return false;
+                }
+            }
+        }
+        return true;
+    }
+}
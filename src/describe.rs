@@ -0,0 +1,185 @@
+// Author: Eskil Nyberg
+
+/*!
+ * Verbose natural-language descriptions of moves, e.g. "knight from g1 to f3".
+ *
+ * Intended for accessibility (screen readers) and blindfold training.
+ * Descriptions are produced through the `MoveLanguage` trait so that they can be localized.
+ */
+
+use super::ChessError;
+use super::Game;
+use super::GameOverReason;
+use super::GameState;
+use super::Move;
+use super::PieceType;
+
+/// The facts about a move that a description is built from.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct MoveDescription {
+    /// The type of the moved piece.
+    pub piece_type: PieceType,
+    /// The position moved from, on the format XF, e.g. "g1".
+    pub from: &'static str,
+    /// The position moved to, on the format XF, e.g. "f3".
+    pub to: &'static str,
+    /// The type of the captured piece, if any.
+    pub captured: Option<PieceType>,
+    /// True if the move captures en passant.
+    pub en_passant: bool,
+    /// Some(true) if the move castles kingside, Some(false) if it castles queenside, otherwise None.
+    pub castles_kingside: Option<bool>,
+    /// True if the move results in a pawn waiting for promotion.
+    pub promotes: bool,
+    /// True if the move checks the opponent's king.
+    pub check: bool,
+    /// True if the move checkmates the opponent.
+    pub checkmate: bool,
+}
+
+/// A language that moves can be described in. Implement this trait to localize move descriptions.
+pub trait MoveLanguage {
+    /// Returns the full sentence describing the move.
+    fn describe(&self, description: &MoveDescription) -> String;
+}
+
+/// The default `MoveLanguage`, producing descriptions like "pawn from e5 takes pawn on d6, en passant".
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Default)]
+pub struct English;
+
+impl English {
+    /// Returns the lowercase English name of `piece_type`.
+    pub fn piece_name(piece_type: PieceType) -> &'static str {
+        return match piece_type {
+            PieceType::King => "king",
+            PieceType::Queen => "queen",
+            PieceType::Rook => "rook",
+            PieceType::Knight => "knight",
+            PieceType::Bishop => "bishop",
+            PieceType::Pawn => "pawn",
+        };
+    }
+}
+
+impl MoveLanguage for English {
+    fn describe(&self, d: &MoveDescription) -> String {
+        let mut res = match (d.castles_kingside, d.captured) {
+            (Some(true), _) => "king castles kingside".to_owned(),
+            (Some(false), _) => "king castles queenside".to_owned(),
+            (None, Some(captured)) => format!(
+                "{} from {} takes {} on {}",
+                English::piece_name(d.piece_type),
+                d.from,
+                English::piece_name(captured),
+                d.to
+            ),
+            (None, None) => format!(
+                "{} from {} to {}",
+                English::piece_name(d.piece_type),
+                d.from,
+                d.to
+            ),
+        };
+        if d.en_passant {
+            res.push_str(", en passant");
+        }
+        if d.promotes {
+            res.push_str(", promotes");
+        }
+        if d.checkmate {
+            res.push_str(", checkmate");
+        } else if d.check {
+            res.push_str(", check");
+        }
+        return res;
+    }
+}
+
+/// Returns the name (XF) of the position with index `idx` as a static string.
+fn square_name(idx: usize) -> &'static str {
+    const NAMES: [&str; 64] = [
+        "a1", "b1", "c1", "d1", "e1", "f1", "g1", "h1", "a2", "b2", "c2", "d2", "e2", "f2", "g2",
+        "h2", "a3", "b3", "c3", "d3", "e3", "f3", "g3", "h3", "a4", "b4", "c4", "d4", "e4", "f4",
+        "g4", "h4", "a5", "b5", "c5", "d5", "e5", "f5", "g5", "h5", "a6", "b6", "c6", "d6", "e6",
+        "f6", "g6", "h6", "a7", "b7", "c7", "d7", "e7", "f7", "g7", "h7", "a8", "b8", "c8", "d8",
+        "e8", "f8", "g8", "h8",
+    ];
+    return NAMES[idx];
+}
+
+impl Move {
+    /// Returns the facts about this move when made in `game`, before it is made.
+    ///
+    /// Check and checkmate are only detected if the move is legal.
+    ///
+    /// Errors if the positions are invalid or there is no piece at `from`.
+    pub fn description(&self, game: &Game) -> Result<MoveDescription, ChessError> {
+        self.from.valid()?;
+        self.to.valid()?;
+        let piece = match game.get(self.from)? {
+            Some(piece) => piece,
+            None => {
+                return Err(ChessError::IllegalMove(
+                    "There is no piece on the square moved from".to_owned(),
+                ))
+            }
+        };
+
+        let en_passant = piece.is_pawn()
+            && self.to == game.en_passant_target
+            && self.from.file != self.to.file
+            && game.get(self.to)?.is_none();
+        let captured = if en_passant {
+            Some(PieceType::Pawn)
+        } else {
+            game.get(self.to)?
+                .filter(|p| p.colour != piece.colour)
+                .map(|p| p.piece_type)
+        };
+        let castles_kingside = if piece.is_king() && self.from.file.abs_diff(self.to.file) == 2 {
+            Some(self.to.file > self.from.file)
+        } else {
+            None
+        };
+
+        // Play the move on a copy of the game to find out what it results in.
+        let mut game_clone = game.clone();
+        game_clone.set_restrictions(None);
+        let state = game_clone
+            .make_move_pos(self.from, self.to)
+            .ok()
+            .map(|result| result.state);
+
+        return Ok(MoveDescription {
+            piece_type: piece.piece_type,
+            from: square_name(self.from.idx),
+            to: square_name(self.to.idx),
+            captured,
+            en_passant,
+            castles_kingside,
+            promotes: state == Some(GameState::WaitingOnPromotionChoice),
+            check: state == Some(GameState::Check),
+            checkmate: state == Some(GameState::GameOver)
+                && game_clone.get_game_over_reason() == Some(GameOverReason::Checkmate),
+        });
+    }
+
+    /// Returns a verbose English description of this move when made in `game`,
+    /// e.g. "knight from g1 to f3" or "pawn from e5 takes pawn on d6, en passant".
+    ///
+    /// Errors if the positions are invalid or there is no piece at `from`.
+    pub fn describe(&self, game: &Game) -> Result<String, ChessError> {
+        return self.describe_in(game, &English);
+    }
+
+    /// Returns a description of this move when made in `game`, in the given `language`.
+    ///
+    /// Errors if the positions are invalid or there is no piece at `from`.
+    pub fn describe_in(
+        &self,
+        game: &Game,
+        language: &dyn MoveLanguage,
+    ) -> Result<String, ChessError> {
+        return Ok(language.describe(&self.description(game)?));
+    }
+}
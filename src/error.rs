@@ -0,0 +1,105 @@
+// Author: Eskil Nyberg
+
+/*!
+ * The error type returned by the fallible methods of the engine.
+ */
+
+use std::error::Error;
+use std::fmt;
+
+use super::Colour;
+
+/// An error returned by the engine.
+///
+/// Variants carrying a `String` contain a human-readable explanation, which is also what `Display` prints.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum ChessError {
+    /// The move is not legal, e.g. the piece cannot move that way, the move leaves the king in check,
+    /// or the move breaks the restrictions of teaching mode. See `Game::illegal_move_reason` for the reason.
+    IllegalMove(String),
+    /// The piece moved is not of the active colour.
+    WrongColour,
+    /// Some input does not represent a position on the board (or is not a valid position).
+    InvalidPosition(String),
+    /// Some input does not represent a piece.
+    InvalidPiece(String),
+    /// The game is over, so no moves can be made.
+    GameOver,
+    /// The game is waiting for a promotion choice (see `Game::set_promotion`), so no moves can be made.
+    WaitingOnPromotion,
+    /// The promotion is not valid, e.g. promoting to a king, or promoting when no pawn can be promoted.
+    InvalidPromotion(String),
+    /// The king of the colour is not on the board.
+    MissingKing(Colour),
+    /// The FEN string is not valid.
+    InvalidFen(String),
+    /// The SAN move is not valid, not legal or ambiguous.
+    InvalidSan(String),
+    /// The PGN is not valid.
+    InvalidPgn(String),
+    /// The board grid is not valid, see `Game::parse_grid`.
+    InvalidGrid(String),
+    /// The binary encoding of a position is not valid, see `Game::from_bytes`.
+    InvalidBytes(String),
+    /// The opening book is not valid or cannot be read, see `OpeningBook::from_bytes`.
+    InvalidBook(String),
+    /// The line is not a message of the network protocol, see `net::NetMessage`.
+    InvalidMessage(String),
+    /// There is no move to undo.
+    NothingToUndo,
+    /// The game has not reached the half-move with this number, see `Game::at_ply`.
+    NoSuchPly(usize),
+    /// The move has not been played from the current position, see `Game::variations`.
+    NoSuchVariation(String),
+    /// There is no game with this id, see `GameManager`.
+    NoSuchGame(u64),
+    /// There is no pending draw offer to accept or decline.
+    NoDrawOffer,
+    /// A draw was claimed but neither the threefold repetition rule nor the 50-move rule applies.
+    NoDrawClaim,
+    /// No pairing for the next round of a tournament satisfies the rules.
+    PairingFailed(String),
+}
+
+impl fmt::Display for ChessError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        return match self {
+            ChessError::IllegalMove(msg)
+            | ChessError::InvalidPosition(msg)
+            | ChessError::InvalidPiece(msg)
+            | ChessError::InvalidPromotion(msg)
+            | ChessError::InvalidFen(msg)
+            | ChessError::InvalidSan(msg)
+            | ChessError::InvalidPgn(msg)
+            | ChessError::InvalidGrid(msg)
+            | ChessError::InvalidBytes(msg)
+            | ChessError::InvalidBook(msg)
+            | ChessError::InvalidMessage(msg)
+            | ChessError::PairingFailed(msg) => write!(f, "{}", msg),
+            ChessError::NoSuchVariation(mv) => {
+                write!(f, "The move {} has not been played from this position", mv)
+            }
+            ChessError::WrongColour => write!(f, "It is not this colour's turn!"),
+            ChessError::GameOver => write!(f, "The game is over, no more moves can be made."),
+            ChessError::WaitingOnPromotion => write!(
+                f,
+                "The game is waiting on a promotion choice, choose a piece with set_promotion first."
+            ),
+            ChessError::MissingKing(colour) => {
+                write!(f, "The {:?} king is not on the board", colour)
+            }
+            ChessError::NothingToUndo => write!(f, "There is no move to undo"),
+            ChessError::NoSuchPly(ply) => {
+                write!(f, "The game has not reached half-move {}", ply)
+            }
+            ChessError::NoSuchGame(id) => write!(f, "There is no game with id {}", id),
+            ChessError::NoDrawOffer => write!(f, "There is no pending draw offer"),
+            ChessError::NoDrawClaim => write!(
+                f,
+                "A draw can only be claimed by threefold repetition or the 50-move rule"
+            ),
+        };
+    }
+}
+
+impl Error for ChessError {}
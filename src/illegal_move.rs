@@ -0,0 +1,148 @@
+// Author: Eskil Nyberg
+
+/*!
+ * Explanations of why a move is not legal, e.g. for a GUI to tell the user what went wrong.
+ */
+
+use std::fmt;
+
+use super::ChessError;
+use super::Game;
+use super::GameState;
+use super::Position;
+
+/// The reason that a move is not legal, see `Game::illegal_move_reason`.
+///
+/// `Display` prints a human-readable explanation, which is also the message of the `ChessError` that
+/// the move methods return for the move.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum IllegalMoveReason {
+    /// The game is over, so no moves can be made.
+    GameOver,
+    /// The game is waiting for a promotion choice (see `Game::set_promotion`), so no moves can be made.
+    WaitingOnPromotion,
+    /// The position moved from or to is not on the board.
+    InvalidPosition,
+    /// There is no piece on the position moved from.
+    NoPiece,
+    /// The piece moved is not of the active colour.
+    WrongColour,
+    /// The move breaks the restrictions of teaching mode, explained by the string.
+    Restricted(String),
+    /// The piece does not move that way, e.g. a rook moving diagonally.
+    CannotMoveThatWay,
+    /// A piece of the same colour stands on the position moved to.
+    OwnPieceOnTarget,
+    /// A piece stands in the way, or in front of a pawn moving forward.
+    PathBlocked,
+    /// A pawn moves diagonally, but there is nothing to capture there, not even en passant.
+    NothingToCapture,
+    /// The king cannot castle that way: the right to castle is lost, a piece stands between the king and the rook,
+    /// or the king is in check or would pass or land on an attacked position.
+    CannotCastle,
+    /// The move leaves the king of the moving colour in check, e.g. because the piece is pinned
+    /// or because the move does not answer a check.
+    LeavesKingInCheck,
+    /// The move is legal in standard chess, but the rules of the variant do not allow it, see `Rules::allows_move`.
+    ForbiddenByVariant,
+}
+
+impl fmt::Display for IllegalMoveReason {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        return match self {
+            IllegalMoveReason::GameOver
+            | IllegalMoveReason::WaitingOnPromotion
+            | IllegalMoveReason::WrongColour => write!(f, "{}", ChessError::from(self.clone())),
+            IllegalMoveReason::InvalidPosition => {
+                write!(f, "The move is from or to a position that is not on the board.")
+            }
+            IllegalMoveReason::NoPiece => {
+                write!(f, "There is no piece on the square you are trying to move from")
+            }
+            IllegalMoveReason::Restricted(msg) => write!(f, "{}", msg),
+            IllegalMoveReason::CannotMoveThatWay => write!(f, "This piece cannot move that way."),
+            IllegalMoveReason::OwnPieceOnTarget => {
+                write!(f, "A piece of your own stands on the square you are trying to move to.")
+            }
+            IllegalMoveReason::PathBlocked => {
+                write!(f, "Another piece stands in the way of this move.")
+            }
+            IllegalMoveReason::NothingToCapture => {
+                write!(f, "Pawns only move diagonally when capturing, but there is nothing to capture there.")
+            }
+            IllegalMoveReason::CannotCastle => write!(
+                f,
+                "The king cannot castle that way, since the right is lost, the way is blocked or the king would pass through check."
+            ),
+            IllegalMoveReason::LeavesKingInCheck => {
+                write!(f, "This move would leave your king in check!")
+            }
+            IllegalMoveReason::ForbiddenByVariant => {
+                write!(f, "The rules of this variant do not allow this move.")
+            }
+        };
+    }
+}
+
+impl From<IllegalMoveReason> for ChessError {
+    /// Returns the error that the move methods return for a move that is not legal for the reason.
+    fn from(reason: IllegalMoveReason) -> ChessError {
+        return match reason {
+            IllegalMoveReason::GameOver => ChessError::GameOver,
+            IllegalMoveReason::WaitingOnPromotion => ChessError::WaitingOnPromotion,
+            IllegalMoveReason::WrongColour => ChessError::WrongColour,
+            IllegalMoveReason::InvalidPosition => ChessError::InvalidPosition(reason.to_string()),
+            _ => ChessError::IllegalMove(reason.to_string()),
+        };
+    }
+}
+
+impl Game {
+    /// Returns why moving the piece at `from_pos` to `to_pos` is not legal, or None if the move is legal.
+    ///
+    /// The reasons are checked in the order `make_move_pos` checks them, so the first that applies is returned.
+    /// See `check_move` for the error that making the move would return.
+    ///
+    /// # Example code
+    ///
+    /// ```rust
+    /// use chess_engine::*;
+    ///
+    /// let game = Game::new();
+    /// let pos = |str: &str| Position::parse_str(str).unwrap();
+    /// assert_eq!(game.illegal_move_reason(pos("e2"), pos("e4")), None);
+    /// assert_eq!(game.illegal_move_reason(pos("a1"), pos("a3")), Some(IllegalMoveReason::PathBlocked));
+    /// assert_eq!(game.illegal_move_reason(pos("b1"), pos("b3")), Some(IllegalMoveReason::CannotMoveThatWay));
+    /// assert_eq!(game.illegal_move_reason(pos("e7"), pos("e5")), Some(IllegalMoveReason::WrongColour));
+    /// ```
+    pub fn illegal_move_reason(
+        &self,
+        from_pos: Position,
+        to_pos: Position,
+    ) -> Option<IllegalMoveReason> {
+        match self.state {
+            GameState::InProgress | GameState::Check => {}
+            GameState::WaitingOnPromotionChoice => {
+                return Some(IllegalMoveReason::WaitingOnPromotion)
+            }
+            _ => return Some(IllegalMoveReason::GameOver),
+        }
+        if from_pos.valid().is_err() || to_pos.valid().is_err() {
+            return Some(IllegalMoveReason::InvalidPosition);
+        }
+        let piece = match self.board[from_pos.idx] {
+            Some(piece) => piece,
+            None => return Some(IllegalMoveReason::NoPiece),
+        };
+        if piece.colour != self.active_colour {
+            return Some(IllegalMoveReason::WrongColour);
+        }
+        // In teaching mode, explain why a restricted move is not allowed.
+        if let Some(restrictions) = &self.restrictions {
+            if let Err(err) = restrictions.check(piece, to_pos) {
+                return Some(IllegalMoveReason::Restricted(err.to_string()));
+            }
+        }
+        return self.move_obstacle(from_pos, to_pos);
+    }
+}
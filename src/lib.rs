@@ -6,9 +6,71 @@
 */
 
 use std::fmt;
+use std::ops::Index;
+use std::str::FromStr;
+use std::sync::Arc;
+
+mod assist;
+mod attacks;
+mod binary;
+mod book;
+mod castling;
+mod dead_position;
+mod describe;
+mod error;
+mod illegal_move;
+mod manager;
+mod material;
+mod mirror;
+pub mod net;
+mod opening_tree;
+pub mod pairing;
+mod pgn;
+#[cfg(feature = "rand")]
+mod playout;
+mod puzzle;
+mod render;
+mod retro;
+mod san;
+mod search;
+mod selfplay;
+mod simulation;
+#[cfg(feature = "websocket")]
+pub mod server;
+#[cfg(feature = "serde")]
+mod serde_board;
+mod square;
+pub mod standings;
+pub mod testing;
+mod transposition;
+mod validate;
+pub mod variant;
+mod variation;
+
+pub use book::{BookMove, OpeningBook};
+pub use castling::{CastlingRights, CastlingSide};
+pub use describe::{English, MoveDescription, MoveLanguage};
+pub use error::ChessError;
+pub use illegal_move::IllegalMoveReason;
+pub use manager::{GameId, GameManager};
+pub use material::MaterialCount;
+pub use opening_tree::{BookExit, OpeningTree};
+#[cfg(feature = "rand")]
+pub use playout::PlayoutResult;
+pub use puzzle::LineError;
+pub use render::RenderOptions;
+pub use search::{Evaluation, Personality};
+pub use selfplay::{SelfPlay, SelfPlayOptions, SelfPlayRecord};
+pub use simulation::{Player, SimulationOptions, SimulationStats};
+pub use square::{File, Rank, Square};
+pub use transposition::TranspositionTable;
+pub use validate::PositionIssue;
+use variant::Rules;
+use variation::VariationTree;
 
 /// The current state of the game.
 #[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum GameState {
     /// The game is initialized and playable. The game starts in this state.
     /// This is the general state of the game unless the game is in check.
@@ -19,7 +81,8 @@ pub enum GameState {
     Check,
     /// The game is waiting for the user to choose which piece the recently moved pawn should be promoted to.
     ///
-    /// Pieces are promoted through the method `Game::set_promotion(PieceType)`.
+    /// Pieces are promoted through the method `Game::set_promotion(PieceType)`,
+    /// and `Game::promotion_pending()` tells which pawn is waiting.
     WaitingOnPromotionChoice, // TODO fix history in relation to state
     /// The game is over. All state-altering functions will not work in this state.
     ///
@@ -29,6 +92,7 @@ pub enum GameState {
 
 /// The reason the game game-overed.
 #[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum GameOverReason {
     /// This variant is reached automatically when one player is checked and cannot by any means escape the check.
     Checkmate,
@@ -41,12 +105,31 @@ pub enum GameOverReason {
     /// This variant is reached automatically when what remains on the board is a case of insufficient material.
     /// (That is, a case when no move can put the game in checkmate or stalemate.)
     InsufficientMaterial,
+    /// This variant is reached automatically when the position is dead for another reason than insufficient material,
+    /// e.g. a wall of blocked pawns that neither king can break through, see `Game::is_dead_position`.
+    DeadPosition,
     /// This variant is reached manually through the method `submit_draw()`
     ManualDraw,
+    /// This variant is reached when a draw offered through `offer_draw()` is accepted through `accept_draw()`.
+    DrawByAgreement,
+    /// This variant is reached through the method `claim_draw()` when no move that captures a piece or moves a pawn has been made in 50 moves.
+    FiftyMoveRule,
+    /// This variant is reached through the method `claim_draw()` when the same position has been reached three times.
+    ThreefoldRepetition,
+    /// This variant is reached automatically when the colour wins by a rule of the game's variant, see `variant::Rules::winner`.
+    VariantWin(Colour),
+    /// This variant is reached through the method `resign()`, by the colour that resigned.
+    Resignation(Colour),
+    /// This variant is reached through the method `submit_timeout()`, by the colour that ran out of time.
+    Timeout(Colour),
+    /// This variant is reached through the method `submit_timeout()`, by the colour that ran out of time, when the
+    /// other colour has no material to checkmate with, see `Game::has_mating_material`. The game is drawn.
+    TimeoutDraw(Colour),
 }
 
 /// The colour of some `Piece` or player.
 #[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Colour {
     White,
     Black,
@@ -54,17 +137,17 @@ pub enum Colour {
 
 impl Colour {
     /// Returns true if self is white
-    pub fn is_white(&self) -> bool {
-        return self == &Colour::White;
+    pub const fn is_white(&self) -> bool {
+        return matches!(self, Colour::White);
     }
 
     /// Returns true if self is black
-    pub fn is_black(&self) -> bool {
-        return self == &Colour::Black;
+    pub const fn is_black(&self) -> bool {
+        return matches!(self, Colour::Black);
     }
 
     /// Returns the opposite colour
-    pub fn invert(&self) -> Colour {
+    pub const fn invert(&self) -> Colour {
         return match self {
             Colour::White => Colour::Black,
             Colour::Black => Colour::White,
@@ -72,7 +155,7 @@ impl Colour {
     }
 
     /// Returns a lowercase character representation of the colour
-    pub fn to_char(&self) -> char {
+    pub const fn to_char(&self) -> char {
         return match self {
             Colour::White => 'w',
             Colour::Black => 'b',
@@ -92,6 +175,7 @@ impl Colour {
 
 /// The type of piece referenced.
 #[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum PieceType {
     King,
     Queen,
@@ -103,37 +187,37 @@ pub enum PieceType {
 
 impl PieceType {
     /// Returns true if the piece is a king
-    pub fn is_king(&self) -> bool {
-        return self == &PieceType::King;
+    pub const fn is_king(&self) -> bool {
+        return matches!(self, PieceType::King);
     }
 
     /// Returns true if the piece is a queen
-    pub fn is_queen(&self) -> bool {
-        return self == &PieceType::Queen;
+    pub const fn is_queen(&self) -> bool {
+        return matches!(self, PieceType::Queen);
     }
 
     /// Returns true if the piece is a rook
-    pub fn is_rook(&self) -> bool {
-        return self == &PieceType::Rook;
+    pub const fn is_rook(&self) -> bool {
+        return matches!(self, PieceType::Rook);
     }
 
     /// Returns true if the piece is a bishop
-    pub fn is_bishop(&self) -> bool {
-        return self == &PieceType::Bishop;
+    pub const fn is_bishop(&self) -> bool {
+        return matches!(self, PieceType::Bishop);
     }
 
     /// Returns true if the piece is a knight
-    pub fn is_knight(&self) -> bool {
-        return self == &PieceType::Knight;
+    pub const fn is_knight(&self) -> bool {
+        return matches!(self, PieceType::Knight);
     }
 
     /// Returns true if the piece is a pawn
-    pub fn is_pawn(&self) -> bool {
-        return self == &PieceType::Pawn;
+    pub const fn is_pawn(&self) -> bool {
+        return matches!(self, PieceType::Pawn);
     }
 
     /// Returns an uppercase character that represents the piece type
-    pub fn char(&self) -> char {
+    pub const fn char(&self) -> char {
         return match self {
             PieceType::King => 'K',
             PieceType::Queen => 'Q',
@@ -147,14 +231,14 @@ impl PieceType {
     /// Returns the piece type represented by the char `ch`.
     ///
     /// Supports lowercase, uppercase, and unicode miscellaneous symbols.
-    pub fn from_char(ch: char) -> Result<PieceType, String> {
+    pub fn from_char(ch: char) -> Result<PieceType, ChessError> {
         return Ok(match ch.to_ascii_uppercase() {
             'K' => PieceType::King,
             'Q' => PieceType::Queen,
             'R' => PieceType::Rook,
             'B' => PieceType::Bishop,
-            'N' => PieceType::Rook,
-            'P' => PieceType::Bishop,
+            'N' => PieceType::Knight,
+            'P' => PieceType::Pawn,
             '♔' => PieceType::King,
             '♕' => PieceType::Queen,
             '♖' => PieceType::Rook,
@@ -167,32 +251,18 @@ impl PieceType {
             '♞' => PieceType::Knight,
             '♝' => PieceType::Bishop,
             '♟' => PieceType::Pawn,
-            _ => return Err(format!("'{}' does not represent a piece", ch)),
-        });
-    }
-
-    /// Returns the piece type represented by the string `str`.
-    ///
-    /// Supports lower-, upper- and mixed case English written words, single characters, and unicode miscellaneous symbols.
-    pub fn from_str(str: &str) -> Result<PieceType, String> {
-        let mut chars = str.trim().chars();
-        let c1 = chars.next();
-        if c1.is_some() && chars.next() == None {
-            return PieceType::from_char(c1.expect("is not none"));
-        }
-        return Ok(match str.trim().to_ascii_lowercase().as_str() {
-            "king" => PieceType::King,
-            "queen" => PieceType::Queen,
-            "rook" => PieceType::Rook,
-            "bishop" => PieceType::Bishop,
-            "knight" => PieceType::Knight,
-            "pawn" => PieceType::Pawn,
-            _ => return Err(format!("'{}' does not represent a piece", str)),
+            _ => {
+                return Err(ChessError::InvalidPiece(format!(
+                    "'{}' does not represent a piece",
+                    ch
+                )))
+            }
         });
     }
 }
 
 #[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 /// Some piece, containing the type of piece and the colour of the piece.
 pub struct Piece {
     pub piece_type: PieceType,
@@ -200,48 +270,66 @@ pub struct Piece {
 }
 
 impl Piece {
+    pub const WHITE_KING: Piece = Piece::new(PieceType::King, Colour::White);
+    pub const WHITE_QUEEN: Piece = Piece::new(PieceType::Queen, Colour::White);
+    pub const WHITE_ROOK: Piece = Piece::new(PieceType::Rook, Colour::White);
+    pub const WHITE_KNIGHT: Piece = Piece::new(PieceType::Knight, Colour::White);
+    pub const WHITE_BISHOP: Piece = Piece::new(PieceType::Bishop, Colour::White);
+    pub const WHITE_PAWN: Piece = Piece::new(PieceType::Pawn, Colour::White);
+    pub const BLACK_KING: Piece = Piece::new(PieceType::King, Colour::Black);
+    pub const BLACK_QUEEN: Piece = Piece::new(PieceType::Queen, Colour::Black);
+    pub const BLACK_ROOK: Piece = Piece::new(PieceType::Rook, Colour::Black);
+    pub const BLACK_KNIGHT: Piece = Piece::new(PieceType::Knight, Colour::Black);
+    pub const BLACK_BISHOP: Piece = Piece::new(PieceType::Bishop, Colour::Black);
+    pub const BLACK_PAWN: Piece = Piece::new(PieceType::Pawn, Colour::Black);
+
+    /// Constructor for a piece of `piece_type` and `colour`, which can be used in constants.
+    pub const fn new(piece_type: PieceType, colour: Colour) -> Piece {
+        return Piece { piece_type, colour };
+    }
+
     /// Returns true if the piece is a king
-    pub fn is_king(&self) -> bool {
+    pub const fn is_king(&self) -> bool {
         return self.piece_type.is_king();
     }
 
     /// Returns true if the piece is a queen
-    pub fn is_queen(&self) -> bool {
+    pub const fn is_queen(&self) -> bool {
         return self.piece_type.is_queen();
     }
 
     /// Returns true if the piece is a rook
-    pub fn is_rook(&self) -> bool {
+    pub const fn is_rook(&self) -> bool {
         return self.piece_type.is_rook();
     }
 
     /// Returns true if the piece is a bishop
-    pub fn is_bishop(&self) -> bool {
+    pub const fn is_bishop(&self) -> bool {
         return self.piece_type.is_bishop();
     }
 
     /// Returns true if the piece is a knight
-    pub fn is_knight(&self) -> bool {
-        return self.piece_type.is_pawn();
+    pub const fn is_knight(&self) -> bool {
+        return self.piece_type.is_knight();
     }
 
     /// Returns true if the piece is a pawn
-    pub fn is_pawn(&self) -> bool {
+    pub const fn is_pawn(&self) -> bool {
         return self.piece_type.is_pawn();
     }
 
     /// Returns true if the piece is white
-    pub fn is_white(&self) -> bool {
+    pub const fn is_white(&self) -> bool {
         return self.colour.is_white();
     }
 
     /// Returns true if the piece is white
-    pub fn is_black(&self) -> bool {
+    pub const fn is_black(&self) -> bool {
         return self.colour.is_black();
     }
 
     /// Returns an uppercase character that represents the piece
-    pub fn to_char(&self) -> char {
+    pub const fn to_char(&self) -> char {
         return self.piece_type.char();
     }
 
@@ -283,6 +371,7 @@ impl Piece {
 }
 
 #[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 /// Some Position on the chessboard.
 ///
 /// Contains the `rank` (row) and `file` (column) on the board,
@@ -313,13 +402,13 @@ impl Position {
     /// Constructor that parses some position on the chessboard from the corresponding rank and file as indices 0-7.
     ///
     /// Returns an `Ok(Position)`,
-    /// or an `Err(&str)` describing the error if the input does not represent some part of the chess board.
-    pub fn new(rank: usize, file: usize) -> Result<Position, String> {
+    /// or an `Err(ChessError::InvalidPosition)` describing the error if the input does not represent some part of the chess board.
+    pub fn new(rank: usize, file: usize) -> Result<Position, ChessError> {
         if rank >= 8 || file >= 8 {
-            return Err(format!(
+            return Err(ChessError::InvalidPosition(format!(
                 "Invalid rank: {} or file: {}; input should be between 0-7",
                 rank, file
-            ));
+            )));
         }
 
         return Ok(Position {
@@ -332,13 +421,13 @@ impl Position {
     /// Constructor that parses some position on the chessboard from the corresponding array index 0-63.
     ///
     /// Returns an `Ok(Position)`,
-    /// or an `Err(&str)` describing the error if the input does not represent some part of the chess board.
-    pub fn new_from_idx(idx: usize) -> Result<Position, String> {
+    /// or an `Err(ChessError::InvalidPosition(&str))` describing the error if the input does not represent some part of the chess board.
+    pub fn new_from_idx(idx: usize) -> Result<Position, ChessError> {
         if idx > 63 {
-            return Err(format!(
+            return Err(ChessError::InvalidPosition(format!(
                 "Invalid idx: {}; input should be between 0-63",
                 idx
-            ));
+            )));
         }
 
         return Ok(Position {
@@ -352,8 +441,8 @@ impl Position {
     /// where `X` is a character a-h and `F` is a number 0-7. Performs trimming and caps-handling.
     ///
     /// Returns an `Ok(Position)`,
-    /// or an `Err(&str)` describing the error if the input does not represent some part of the chess board.
-    pub fn parse_str(str: &str) -> Result<Position, String> {
+    /// or an `Err(ChessError::InvalidPosition(&str))` describing the error if the input does not represent some part of the chess board.
+    pub fn parse_str(str: &str) -> Result<Position, ChessError> {
         let str_lowercase = str.to_lowercase(); // Permit uppercase inputs
         let chars: Vec<char> = str_lowercase
             .trim() // Removes potential whitespaces passed to the function
@@ -361,7 +450,10 @@ impl Position {
             .collect(); // Creates the vector
 
         if chars.len() != 2 {
-            return Err(format!("Input {} is of invalid length.", str));
+            return Err(ChessError::InvalidPosition(format!(
+                "Input {} is of invalid length.",
+                str
+            )));
         }
 
         // Parses the first character: the file; throws an error if the character is not a character between a-h
@@ -375,10 +467,10 @@ impl Position {
             'g' => 6,
             'h' => 7,
             _ => {
-                return Err(format!(
+                return Err(ChessError::InvalidPosition(format!(
                     "First character '{}' of string invalid, should be some character between a-h",
                     chars[0]
-                ));
+                )));
             }
         };
 
@@ -393,10 +485,10 @@ impl Position {
             '7' => 6,
             '8' => 7,
             _ => {
-                return Err(format!(
+                return Err(ChessError::InvalidPosition(format!(
                     "Second character '{}' of string invalid, should be some number between 1-8",
                     chars[1]
-                ));
+                )));
             }
         };
 
@@ -404,15 +496,15 @@ impl Position {
     }
 
     /// Returns the index for some rank (0-7) and file (0-7)
-    fn idx(rank: usize, file: usize) -> usize {
+    const fn idx(rank: usize, file: usize) -> usize {
         return rank * 8 + file;
     }
 
     /// Returns a clone of self modified by offset.
     ///
     /// Errors if the result is outside the chess board.
-    fn offset(&self, rank_offset: i32, file_offset: i32) -> Result<Position, String> {
-        let mut res = self.clone();
+    fn offset(&self, rank_offset: i32, file_offset: i32) -> Result<Position, ChessError> {
+        let mut res = *self;
         res.offset_self(rank_offset, file_offset)?;
         return Ok(res);
     }
@@ -420,15 +512,15 @@ impl Position {
     /// Modifies self by offset.
     ///
     /// Errors if the result is outside the chess board and does not update self in that case.
-    fn offset_self(&mut self, rank_offset: i32, file_offset: i32) -> Result<(), String> {
+    fn offset_self(&mut self, rank_offset: i32, file_offset: i32) -> Result<(), ChessError> {
         let rank_result: i32 = self.rank as i32 + rank_offset;
         let file_result: i32 = self.file as i32 + file_offset;
 
-        if rank_result < 0 || rank_result > 7 || file_result < 0 || file_result > 7 {
-            return Err(format!(
+        if !(0..=7).contains(&rank_result) || !(0..=7).contains(&file_result) {
+            return Err(ChessError::InvalidPosition(format!(
                 "New position rank: {} file: {} is not on the board",
                 rank_result, file_result
-            ));
+            )));
         }
 
         // Result is within the chess board
@@ -438,59 +530,419 @@ impl Position {
         return Ok(());
     }
 
-    /// Converts the given position to a String
-    /// 
-    /// Position::NULL is displayed as a single hyphen (-)
-    /// 
-    /// # Panics
-    /// 
-    /// Panics if self does not represent some position on the chessboard
-    /// and is not Position::NULL.
-    pub fn to_string(&self) -> String {
-        if self == &Position::NULL {
-            return "-".to_owned();
-        }
-        return format!(
-            "{}{}",
-            match self.file {
-                0 => "a",
-                1 => "b",
-                2 => "c",
-                3 => "d",
-                4 => "e",
-                5 => "f",
-                6 => "g",
-                7 => "h",
-                _default => panic!("Method called on a position outside the chess board"),
-            },
-            self.rank + 1
-        );
-    }
-
     /// Validates self. Errors if self is not valid.
     ///
     /// Position::NULL is not a valid position.
-    pub fn valid(&self) -> Result<(), String> {
-        if self.rank < 8 && self.rank < 8 && self.idx == self.rank * 8 + self.file {
+    pub fn valid(&self) -> Result<(), ChessError> {
+        if self.rank < 8 && self.file < 8 && self.idx == self.rank * 8 + self.file {
             return Ok(());
         } else {
-            return Err(format!("Invalid position {:?}", self));
+            return Err(ChessError::InvalidPosition(format!(
+                "Invalid position {:?}",
+                self
+            )));
         }
     }
 }
 
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// Some move on the chessboard, from one position to another.
+///
+/// Moves generated by the engine (see `Game::get_moves`) have their flags set.
+/// The flags are ignored when a move is made with `Game::play_move`, only `from`, `to` and `promotion` matter.
+pub struct Move {
+    /// The position moved from.
+    pub from: Position,
+    /// The position moved to.
+    pub to: Position,
+    /// The piece type a pawn is promoted to. Is None if the move is not a promotion.
+    pub promotion: Option<PieceType>,
+    /// True if the move captures a piece (including en passant).
+    pub is_capture: bool,
+    /// True if the move captures en passant.
+    pub is_en_passant: bool,
+    /// True if the move is a king castling.
+    pub is_castle: bool,
+}
+
+impl Move {
+    /// Constructor for the move from `from` to `to`, without promotion or flags.
+    pub fn new(from: Position, to: Position) -> Move {
+        return Move {
+            from,
+            to,
+            promotion: None,
+            is_capture: false,
+            is_en_passant: false,
+            is_castle: false,
+        };
+    }
+
+    /// Constructor for the move from `from` to `to` that promotes the moved pawn to `promotion`.
+    pub fn new_promotion(from: Position, to: Position, promotion: PieceType) -> Move {
+        return Move {
+            promotion: Some(promotion),
+            ..Move::new(from, to)
+        };
+    }
+
+    /// Constructor that parses a move in coordinate notation (as in UCI), e.g. "e2e4", or "e7e8q" for a promotion.
+    ///
+    /// The flags of the parsed move are not set. Errors if `uci` is not a move in coordinate notation.
+    pub fn from_uci(uci: &str) -> Result<Move, ChessError> {
+        return uci.parse();
+    }
+
+    /// Returns the move in coordinate notation (as in UCI), e.g. "e2e4", or "e7e8q" for a promotion.
+    pub fn to_uci(&self) -> String {
+        return self.to_string();
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+/// A position that some piece can legally move to, see `Game::destinations`.
+pub struct Destination {
+    /// The position moved to.
+    pub to: Position,
+    /// True if moving here captures a piece (including en passant).
+    pub is_capture: bool,
+    /// True if moving here promotes the pawn.
+    pub is_promotion: bool,
+    /// True if moving here is a king castling.
+    pub is_castle: bool,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+/// What happened in a move, returned by `Game::make_move` and the other methods that make moves.
+pub struct MoveResult {
+    /// The game state after the move, see `Game::get_game_state`.
+    pub state: GameState,
+    /// The position moved from.
+    pub from: Position,
+    /// The position moved to.
+    pub to: Position,
+    /// The piece that was moved. A promoted pawn is a pawn.
+    pub piece_moved: Piece,
+    /// The piece that was captured. Is None if no piece was captured. Includes pawns captured en passant.
+    pub piece_captured: Option<Piece>,
+    /// The position of the captured piece, which is not `to` for en passant. Is None if no piece was captured.
+    pub captured_at: Option<Position>,
+    /// True if the move is a king castling. The rook has been moved too.
+    pub is_castle: bool,
+    /// True if the other colour's king is now in check (including checkmate).
+    pub is_check: bool,
+    /// True if the game is waiting for the promotion choice, see `Game::set_promotion`.
+    pub promotion_pending: bool,
+    /// The piece type the pawn was promoted to. Is None if the move is not a promotion or the promotion is pending.
+    pub promotion: Option<PieceType>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+/// A pawn waiting to be promoted, see `Game::promotion_pending`.
+pub struct PendingPromotion {
+    /// The position of the pawn, on the last rank.
+    pub position: Position,
+    /// The colour of the pawn.
+    pub colour: Colour,
+    /// The piece types the pawn may be promoted to, any of which is accepted by `Game::set_promotion`.
+    pub piece_types: Vec<PieceType>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Default)]
+/// The pieces each colour has captured, see `Game::captured_pieces`.
+pub struct CapturedPieces {
+    /// The black pieces captured by white, in the order they were captured.
+    pub by_white: Vec<Piece>,
+    /// The white pieces captured by black, in the order they were captured.
+    pub by_black: Vec<Piece>,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// A summary of the game's status, see `Game::summary`.
+pub struct GameSummary {
+    /// The game state, see `Game::get_game_state`.
+    pub state: GameState,
+    /// The colour to move.
+    pub active_colour: Colour,
+    /// True if the active colour's king is checked, also when it is checkmated.
+    pub is_check: bool,
+    /// The game over reason. Is None if the game is not over.
+    pub game_over_reason: Option<GameOverReason>,
+    /// The colour that won the game. Is None if the game is not over or was drawn.
+    pub winner: Option<Colour>,
+    /// The number of the full move, starting at 1 and incremented after black's move.
+    pub fullmove_number: u32,
+    /// The number of halfmoves since the last capture or pawn move, used for the 50 and 75-move rules.
+    pub halfmove_clock: u16,
+    /// How many times the current position has occurred, including now.
+    pub repetitions: usize,
+    /// True if a draw can be claimed by the threefold repetition rule.
+    pub can_claim_threefold_repetition: bool,
+    /// True if a draw can be claimed by the 50-move rule.
+    pub can_claim_50_move_rule: bool,
+}
+
 #[derive(Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 /// An entry in the chess engine's move history.
 pub struct HistoryEntry {
     /// The Forsyth-Edwards Notation (FEN) for the game state.
     fen: String,
-    /// Position (XF) moved from.
-    from: String,
-    /// Position (XF) moved to.
-    to: String,
+    /// Position moved from.
+    from: Position,
+    /// Position moved to.
+    to: Position,
     piece_moved: Piece,
-    /// None if no piece was captured.
+    /// None if no piece was captured. Includes pawns captured en passant.
     piece_captured: Option<Piece>,
+    /// None if the move is not a promotion, or the promotion has not been chosen yet.
+    promotion: Option<PieceType>,
+    /// True if the move put the other colour's king in check (including checkmate).
+    is_check: bool,
+    /// A comment on the move, see `Game::set_comment`.
+    comment: Option<String>,
+    /// The numeric annotation glyphs of the move, see `Game::set_nags`.
+    nags: Vec<u8>,
+    /// The game as it was before the move, used by `Game::undo_move`.
+    before: GameSnapshot,
+}
+
+impl HistoryEntry {
+    /// Returns the Forsyth-Edwards Notation (FEN) for the game before the move.
+    pub fn fen(&self) -> &str {
+        return &self.fen;
+    }
+
+    /// Returns the position moved from.
+    pub fn from(&self) -> Position {
+        self.from
+    }
+
+    /// Returns the position moved to.
+    pub fn to(&self) -> Position {
+        self.to
+    }
+
+    /// Returns the piece that was moved. A promoted pawn is returned as a pawn.
+    pub fn piece_moved(&self) -> Piece {
+        self.piece_moved
+    }
+
+    /// Returns the piece that was captured. Is None if no piece was captured. Includes pawns captured en passant.
+    pub fn piece_captured(&self) -> Option<Piece> {
+        self.piece_captured
+    }
+
+    /// Returns the piece type the pawn was promoted to. Is None if the move is not a promotion,
+    /// or the game is still waiting on the promotion choice.
+    pub fn promotion(&self) -> Option<PieceType> {
+        self.promotion
+    }
+
+    /// Returns true if the move put the other colour's king in check (including checkmate), otherwise false.
+    pub fn is_check(&self) -> bool {
+        self.is_check
+    }
+
+    /// Returns the comment on the move, if any, see `Game::set_comment`.
+    pub fn comment(&self) -> Option<&str> {
+        return self.comment.as_deref();
+    }
+
+    /// Returns the numeric annotation glyphs of the move, e.g. 1 for a good move (!), see `Game::set_nags`.
+    pub fn nags(&self) -> &[u8] {
+        return &self.nags;
+    }
+
+    /// Returns the number of the full move the move was made in, starting at 1. White's and black's moves share a number.
+    pub fn move_number(&self) -> u32 {
+        self.before.fullmoves
+    }
+
+    /// Returns the move as a `Move`, with the promotion set if it has been chosen.
+    pub fn as_move(&self) -> Move {
+        return Move {
+            promotion: self.promotion,
+            ..Move::new(self.from, self.to)
+        };
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// The parts of a `Game` that a move changes, saved in the history so that the move can be undone.
+struct GameSnapshot {
+    state: GameState,
+    game_over_reason: Option<GameOverReason>,
+    active_colour: Colour,
+    #[cfg_attr(feature = "serde", serde(with = "serde_board"))]
+    board: [Option<Piece>; 8 * 8],
+    halfmoves: u16,
+    fullmoves: u32,
+    en_passant_target: Position,
+    castling_rights: CastlingRights,
+    draw_offer: Option<Colour>,
+    /// The length of `Game::position_keys`.
+    position_keys: usize,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// What identifies a position for the repetition rules, see `Game::position_key`.
+struct PositionKey {
+    #[cfg_attr(feature = "serde", serde(with = "serde_board"))]
+    board: [Option<Piece>; 8 * 8],
+    active_colour: Colour,
+    /// Whether white can castle kingside and queenside, and black can castle kingside and queenside, in that order.
+    castling: [bool; 4],
+    /// Position::NULL unless some pawn can capture en passant.
+    en_passant_target: Position,
+}
+
+/// Restrictions on which moves the engine accepts, used for teaching drills.
+///
+/// For example, a "pawns and kings only" drill sets `piece_types` to `[PieceType::Pawn, PieceType::King]`,
+/// and a knight tour exercise sets `piece_types` to `[PieceType::Knight]`.
+///
+/// Restrictions only limit which moves are offered and accepted by the public move methods,
+/// the rules of the game (check, checkmate, stalemate etc.) are not affected.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MoveRestrictions {
+    /// The piece types that may be moved. Is None if every piece type may be moved.
+    pub piece_types: Option<Vec<PieceType>>,
+    /// The squares that pieces may move to. Is None if every square may be moved to.
+    pub squares: Option<Vec<Position>>,
+}
+
+impl MoveRestrictions {
+    /// Returns Ok if `piece` may move to `to_pos` under these restrictions.
+    ///
+    /// Errors with an explanation of the violated restriction otherwise.
+    pub fn check(&self, piece: Piece, to_pos: Position) -> Result<(), ChessError> {
+        if let Some(piece_types) = &self.piece_types {
+            if !piece_types.contains(&piece.piece_type) {
+                return Err(ChessError::IllegalMove(format!(
+                    "Only the piece types {:?} may be moved in this exercise, but this piece is a {:?}.",
+                    piece_types, piece.piece_type
+                )));
+            }
+        }
+        if let Some(squares) = &self.squares {
+            if !squares.contains(&to_pos) {
+                return Err(ChessError::IllegalMove(format!(
+                    "Only the squares {:?} may be moved to in this exercise, but the move goes to {}.",
+                    squares
+                        .iter()
+                        .map(|pos| pos.to_string())
+                        .collect::<Vec<String>>(),
+                    to_pos
+                )));
+            }
+        }
+        return Ok(());
+    }
+}
+
+/// Options for `Game::get_possible_moves_with`.
+///
+/// The default only generates moves for the active colour, includes castling and expands promotions,
+/// which is what is needed to play the game. Use e.g. `MoveGenOptions { include_inactive_colour: true, ..Default::default() }`
+/// to change single options.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct MoveGenOptions {
+    /// If true, moves are also generated for pieces of the colour that is not to move (for analysis or premoves).
+    pub include_inactive_colour: bool,
+    /// If true, castling moves are included.
+    pub include_castling: bool,
+    /// If true, a promotion is returned as one move per piece type that the pawn can be promoted to,
+    /// otherwise as a single move with `promotion` set to None.
+    pub expand_promotions: bool,
+}
+
+impl Default for MoveGenOptions {
+    fn default() -> Self {
+        MoveGenOptions {
+            include_inactive_colour: false,
+            include_castling: true,
+            expand_promotions: true,
+        }
+    }
+}
+
+/// The iterator returned by `Game::legal_moves`.
+///
+/// The moves of one piece at a time are generated into `buffer`, which fits the at most 27 moves of a queen.
+struct LegalMoves<'a> {
+    game: &'a Game,
+    next_idx: usize, // the index of the next position to generate moves for
+    buffer: [Move; 27],
+    len: usize,      // the number of moves in the buffer
+    returned: usize, // the number of moves in the buffer that have been returned
+}
+
+impl Iterator for LegalMoves<'_> {
+    type Item = Move;
+
+    fn next(&mut self) -> Option<Move> {
+        while self.returned == self.len {
+            if self.next_idx == 64 {
+                return None;
+            }
+            let idx = self.next_idx;
+            self.next_idx += 1;
+            self.len = 0;
+            self.returned = 0;
+            if !self.game.board[idx].is_some_and(|p| p.colour == self.game.active_colour) {
+                continue;
+            }
+            let (buffer, len) = (&mut self.buffer, &mut self.len);
+            self.game
+                .for_each_move_with(
+                    Position::new_from_idx(idx).expect("on the board"),
+                    MoveGenOptions::default(),
+                    &mut |mv| {
+                        buffer[*len] = mv;
+                        *len += 1;
+                    },
+                )
+                .expect("on the board");
+        }
+        self.returned += 1;
+        return Some(self.buffer[self.returned - 1]);
+    }
+}
+
+/// The FEN of the starting position, which `Game::new()` sets up.
+pub const STARTING_FEN: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+
+/// The board of the starting position, indexed like `Game::get_board()`, e.g. to build boards at compile time.
+pub const STARTING_BOARD: [Option<Piece>; 8 * 8] = starting_board();
+
+/// Returns `STARTING_BOARD`.
+const fn starting_board() -> [Option<Piece>; 8 * 8] {
+    const BACK_RANK: [PieceType; 8] = [
+        PieceType::Rook,
+        PieceType::Knight,
+        PieceType::Bishop,
+        PieceType::Queen,
+        PieceType::King,
+        PieceType::Bishop,
+        PieceType::Knight,
+        PieceType::Rook,
+    ];
+    let mut board = [None; 8 * 8];
+    let mut file = 0;
+    while file < 8 {
+        board[Position::idx(0, file)] = Some(Piece::new(BACK_RANK[file], Colour::White));
+        board[Position::idx(1, file)] = Some(Piece::WHITE_PAWN);
+        board[Position::idx(6, file)] = Some(Piece::BLACK_PAWN);
+        board[Position::idx(7, file)] = Some(Piece::new(BACK_RANK[file], Colour::Black));
+        file += 1;
+    }
+    return board;
 }
 
 /// An engine that runs a game of chess. 
@@ -517,6 +969,9 @@ pub struct HistoryEntry {
 /// let from_pos = Position::parse_str("e5").unwrap();
 /// let result = game.make_move_pos(to_pos, from_pos); // moves from e7 to e5
 /// assert!(result.is_ok());
+///
+/// let result = game.make_move_san("Nf3"); // moves the knight from g1 to f3
+/// assert!(result.is_ok());
 /// 
 /// match game.get_game_state() {
 ///     GameState::InProgress => {
@@ -551,115 +1006,130 @@ pub struct HistoryEntry {
 /// 
 /// The following methods may be of use if you want to work with the board in any way.
 /// * `get_board()` returns the board as an array of `Option<Piece>`-s.
+/// * `format_board_from(BoardStyle, Colour)` prints the board from either side, as does `{:#}` for black.
+/// * `to_string_unicode()` and `to_string_ansi(Colour)` draw the board for a terminal, and `render(&RenderOptions)` highlights moves.
+/// * `game[Position]` returns the `Option<Piece>` at a position, and `pieces()` and `pieces_of(Colour)` iterate over the pieces.
+/// * `to_bytes()` encodes the position in at most 29 bytes, e.g. to store many positions, and `from_bytes(&[u8])` reads it.
+/// * `king_position(Colour)` and `find_pieces(PieceType, Colour)` find where pieces stand.
+/// * `mirrored()` flips the board and swaps the colours, e.g. to test that an evaluation is symmetric.
 /// * `get_possible_moves(Position)` returns a list of all possible moves for the piece at position.
+/// * `is_legal_move(Position, Position)` checks a single move, and `illegal_move_reason(Position, Position)` tells why it is not legal.
+/// * `get_all_possible_moves()` returns every possible move of the active colour as `Move`-s.
+/// * `random_legal_move(&mut Rng)` picks one of them at random, and `simulate_random_playout(max_plies, &mut Rng)`
+///   plays random moves until the game is over, with the `rand` feature.
+/// * `get_capture_moves_all()` returns only the captures among them, which is much faster.
+/// * `legal_moves()` iterates over the same moves lazily, and `generate_moves_into(&mut Vec<Move>)` reuses a vector.
+/// * `get_moves(Position)` returns the same moves as `Move`-s, flagged as captures, en passant, castling or promotions.
+///   These can be made with `play_move(Move)`.
+/// * `perft(depth)` counts the move sequences from the position, and `bench_movegen(iterations)` measures the speed of
+///   the move generation. See also `testing::run_perft_suite(depth)`.
+/// * `perft_parallel(depth)` and `best_move_parallel(depth)` spread the counting and the search over all cores,
+///   with the `parallel` feature.
+/// * `get_possible_moves_for(Position, Colour)` returns the possible moves as if it were some colour's turn, for analysis.
 /// * `get_possible_capture_moves(Position)` returns the possible moves which capture.
 /// * `get_possible_non_capture_moves(Position)` returns the possible moves which do not capture.
+/// * `destinations(Position)` returns the possible moves flagged as captures, promotions or castling in one call.
+/// * `is_square_attacked(Position, Colour)` checks if some piece of a colour attacks a position, e.g. to highlight threats.
+/// * `attack_map(Colour)` counts the pieces of a colour that attack each position, e.g. for a heat map.
+/// * `material(Colour)` counts the pieces of a colour, and `material_balance()` compares the colours in centipawns.
+/// * `get_checkers()` returns the pieces giving check, and `is_in_check_for(Colour)` checks if either colour's king is attacked.
+/// * `is_checkmate_position()` and `is_stalemate_position()` examine the board as it is, e.g. after `put(...)` or `remove(...)`.
+/// * `pinned_pieces(Colour)` returns the pieces that are pinned to their king, e.g. to explain why a piece cannot move.
+/// * `undo_move()` and `undo_moves(n)` take back moves, and `at_ply(n)` returns the game as it was after `n` half-moves.
+/// * `snapshot()` copies the position without the history, e.g. to analyse it in another thread.
+/// * `set_comment(ply, Option<&str>)` and `set_nags(ply, &[u8])` annotate moves of the history, which `to_pgn()`
+///   writes and `from_pgn(&str)` reads.
+/// * `variations()` lists the moves played from the position, also those taken back, which can be ordered with
+///   `promote_variation(Move)` and removed with `delete_variation(Move)`. `main_line()` follows the first of them.
+/// * `validate_position()` lists what makes a position set up with `put(...)` or `from_fen(...)` illegal.
+/// * `refresh_state()` updates the castling rights, the en passant target and the game state after `put(...)` or `remove(...)`.
+/// * `set_active_colour(Colour)` and `skip_turn()` choose the colour to move, e.g. in a position editor.
+/// * `verify_line(&[&str])` checks that a sequence of moves can be played, e.g. the solution of a puzzle.
+/// * `is_dead_position()` checks if no sequence of moves can lead to checkmate, which ends the game as a draw.
+/// * `has_mating_material(Colour)` checks if a colour could checkmate at all, e.g. to draw a game lost on time.
+/// * `plies()` counts the moves of the history, `move_number()` returns the number of the current move,
+///   and `halfmove_clock()` and `fullmove_number()` return the clocks of the FEN.
+/// * `summary()` returns the state, check, winner, clocks and draw claims in one `GameSummary`.
+/// * `promotion_pending()` returns the position, colour and possible promotions of the pawn waiting for `set_promotion(PieceType)`.
+/// * `evaluate_batch(&[Game], depth, &mut TranspositionTable)` returns the best move and score of many positions,
+///   e.g. to label a dataset.
+/// * `self_play(SelfPlayOptions, seed)` plays games of the engine against itself and returns a record per move, e.g. as training data.
+/// * `simulate(Player, Player, SimulationOptions, seed, pgn)` plays many games between two players and adds up
+///   the results, e.g. for statistics.
 ///
 /// If you want to implement manual draws, the following methods might be helpful:
 ///
 /// * `submit_draw()` lets you set the game as manually drawn.
-/// * `can_enact_threefold_repetition_rule()` checks if the threefold repetition rule is applicable.
-/// * `can_enact_50_move_rule()` checks if the 50 move rule is applicable.
-#[derive(Clone, Debug)] // The clone derivation is necessary as it is used by try_move
+/// * `offer_draw(Colour)`, `accept_draw(Colour)` and `decline_draw()` let the players agree to a draw.
+/// * `is_threefold_repetition()` checks if the threefold repetition rule is applicable.
+/// * `is_50_move_rule()` checks if the 50 move rule is applicable.
+/// * `claim_draw()` ends the game by whichever of these rules applies.
+///
+/// Likewise, `resign(Colour)` and `submit_timeout(Colour)` end the game as lost for a colour, though running out of time
+/// against a colour without mating material is a draw.
+/// `to_pgn()` writes the game with its result and how it ended.
+///
+/// For teaching drills, `set_restrictions(...)` limits which pieces may move and where (see `MoveRestrictions`).
+/// For chess variants, `set_rules(...)` adds the rules of the variant (see `variant::Rules`).
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Game {
     state: GameState,
     game_over_reason: Option<GameOverReason>,
     active_colour: Colour,
+    #[cfg_attr(feature = "serde", serde(with = "serde_board"))]
     board: [Option<Piece>; 8 * 8],
     history: Vec<HistoryEntry>,
-    halfmoves: u8, // used for implementing the 50 and 75-move rules
+    halfmoves: u16, // used for implementing the 50 and 75-move rules
     fullmoves: u32,
     en_passant_target: Position, // Is set to a targetable position for en passant, when relevant, otherwise Position::NULL
-    white_has_right_to_castle_queenside: bool,
-    white_has_right_to_castle_kingside: bool,
-    black_has_right_to_castle_queenside: bool,
-    black_has_right_to_castle_kingside: bool,
+    castling_rights: CastlingRights,
+    restrictions: Option<MoveRestrictions>, // set when the game is used in teaching mode
+    position_keys: Vec<PositionKey>, // the keys of every position reached, used for the repetition rules
+    draw_offer: Option<Colour>, // the colour that offered a draw, while the offer is pending
+    #[cfg_attr(feature = "serde", serde(skip, default = "variant::standard_rules"))]
+    rules: Arc<dyn Rules>, // the rules of the variant played, on top of the standard rules
+    variations: VariationTree, // every move played, also those taken back, while history is the line to the current position
+}
+
+impl Default for Game {
+    fn default() -> Self {
+        Game::new()
+    }
 }
 
+// Games are shared with and moved to other threads, e.g. by `Game::snapshot` and `GameManager`,
+// so a field that is not `Send` or `Sync` must not be added.
+const _: () = {
+    const fn assert_send_sync<T: Send + Sync>() {}
+    assert_send_sync::<Game>();
+};
+
 /// Here we implement the main functions of our game.
 impl Game {
-    /// This is a constant used in the function `try_move` that specifies how far the engine should check for Check-states.
-    /// The value 1 should do since after 1 recursions, we have checked the current and the next move. In this time, we should discover all relevant Check-states.
-    const MAX_RECURSIONS: i32 = 2;
-
-    /// Initialises a new board with pieces.
+    /// Initialises a new board with pieces, see `STARTING_BOARD` and `STARTING_FEN`.
+    ///
+    /// This is not a `const fn`, since the game keeps its history and rules on the heap.
     pub fn new() -> Game {
-        // generate the pieces
-        let w_king = Some(Piece {
-            colour: Colour::White,
-            piece_type: PieceType::King,
-        });
-        let w_queen = Some(Piece {
-            colour: Colour::White,
-            piece_type: PieceType::Queen,
-        });
-        let w_rook = Some(Piece {
-            colour: Colour::White,
-            piece_type: PieceType::Rook,
-        });
-        let w_knight = Some(Piece {
-            colour: Colour::White,
-            piece_type: PieceType::Knight,
-        });
-        let w_bishop = Some(Piece {
-            colour: Colour::White,
-            piece_type: PieceType::Bishop,
-        });
-        let w_pawn = Some(Piece {
-            colour: Colour::White,
-            piece_type: PieceType::Pawn,
-        });
-
-        let b_king = Some(Piece {
-            colour: Colour::Black,
-            piece_type: PieceType::King,
-        });
-        let b_queen = Some(Piece {
-            colour: Colour::Black,
-            piece_type: PieceType::Queen,
-        });
-        let b_rook = Some(Piece {
-            colour: Colour::Black,
-            piece_type: PieceType::Rook,
-        });
-        let b_knight = Some(Piece {
-            colour: Colour::Black,
-            piece_type: PieceType::Knight,
-        });
-        let b_bishop = Some(Piece {
-            colour: Colour::Black,
-            piece_type: PieceType::Bishop,
-        });
-        let b_pawn = Some(Piece {
-            colour: Colour::Black,
-            piece_type: PieceType::Pawn,
-        });
-
-        // initializing board array
-        let board_init = [
-            w_rook, w_knight, w_bishop, w_queen, w_king, w_bishop, w_knight, w_rook, w_pawn,
-            w_pawn, w_pawn, w_pawn, w_pawn, w_pawn, w_pawn, w_pawn, None, None, None, None, None,
-            None, None, None, None, None, None, None, None, None, None, None, None, None, None,
-            None, None, None, None, None, None, None, None, None, None, None, None, None, b_pawn,
-            b_pawn, b_pawn, b_pawn, b_pawn, b_pawn, b_pawn, b_pawn, b_rook, b_knight, b_bishop,
-            b_queen, b_king, b_bishop, b_knight, b_rook,
-        ];
-
-        Game {
+        let mut game = Game {
             /* initialise board, set active colour to white and state to in progress */
             state: GameState::InProgress,
             game_over_reason: None,
             active_colour: Colour::White,
-            board: board_init,
+            board: STARTING_BOARD,
             history: vec![],
             halfmoves: 0,
-            fullmoves: 0,
+            fullmoves: 1,
             en_passant_target: Position::NULL,
-            white_has_right_to_castle_queenside: true,
-            white_has_right_to_castle_kingside: true,
-            black_has_right_to_castle_queenside: true,
-            black_has_right_to_castle_kingside: true,
-        }
+            castling_rights: CastlingRights::ALL,
+            restrictions: None,
+            position_keys: vec![],
+            draw_offer: None,
+            rules: Arc::new(variant::Standard),
+            variations: VariationTree::new(),
+        };
+        game.position_keys.push(game.position_key());
+        return game;
     }
 
     /// Returns the Forsyth-Edwards Notation (FEN) of the current position.
@@ -706,47 +1176,12 @@ impl Game {
         fen.push(' ');
 
         // 3rd field: castling rights
-        if self.white_has_right_to_castle_kingside {
-            fen.push('K')
-        }
-        if self.white_has_right_to_castle_queenside {
-            fen.push('Q')
-        }
-        if self.black_has_right_to_castle_kingside {
-            fen.push('k')
-        }
-        if self.black_has_right_to_castle_queenside {
-            fen.push('Q')
-        }
-        if fen.ends_with(' ') {
-            // no castling rights
-            fen.push('-');
-        }
+        fen.push_str(&self.castling_rights.to_string());
 
         fen.push(' ');
 
         // 4th field: possible en passant target
-        if self.en_passant_target != Position::NULL {
-            // Check if this position is threatened by some pawn, otherwise do not include this
-            let dir = self.active_colour.pawn_dir() * -1;
-            let pos1 = self.en_passant_target.offset(dir, 1);
-            let piece1 = match pos1 {
-                Ok(pos) => self.get(pos).expect("validated"),
-                Err(_) => None,
-            };
-            let pos2 = self.en_passant_target.offset(dir, 1);
-            let piece2 = match pos2 {
-                Ok(pos) => self.get(pos).expect("validated"),
-                Err(_) => None,
-            };
-            if piece1.is_some_and(|p| p.is_pawn()) || piece2.is_some_and(|p| p.is_pawn()) {
-                fen.push_str(&self.en_passant_target.to_string());
-            } else {
-                fen.push('-');
-            }
-        } else {
-            fen.push('-');
-        }
+        fen.push_str(&self.capturable_en_passant_target().to_string());
 
         fen.push(' ');
 
@@ -761,95 +1196,478 @@ impl Game {
         return fen;
     }
 
-    /// Returns the `Option<Piece>` at position `pos`.
+    /// Constructor that loads a game from its Forsyth-Edwards Notation (FEN).
     ///
-    /// Is None if there is no piece at `pos`.
+    /// See https://www.chess.com/terms/fen-chess for a detailed explanation on the notation.
     ///
-    /// Errors if `pos` is invalid.
-    pub fn get(&self, pos: Position) -> Result<Option<Piece>, String> {
-        pos.valid()?;
-        return Ok(self.board[pos.idx]);
-    }
-
-    /// Puts `piece` at position `pos`.
+    /// All six fields are parsed. The halfmove and fullmove fields may be left out,
+    /// in which case they are set to 0 and 1 respectively.
+    /// The game state (check, checkmate, stalemate etc.) is evaluated for the loaded position.
     ///
-    /// Errors if `pos` is invalid or the placement results in a board with multiple kings.
-    /// (The engine does not support placing multiple kings of the same color).
-    pub fn put(&mut self, pos: Position, piece: Piece) -> Result<(), String> {
-        pos.valid()?;
-        if piece.piece_type == PieceType::King {
-            match self.find_king(piece.colour) {
-                Ok(_) => {
-                    return Err(format!(
-                        "The {:?} king is already on the board, a second one cannot be placed",
-                        piece.colour
-                    ))
+    /// Errors if `fen` is not valid FEN, or if either colour does not have exactly one king, the fullmove number
+    /// is 0 or no pawn of the colour that just moved stands in front of the en passant target.
+    pub fn from_fen(fen: &str) -> Result<Game, ChessError> {
+        let fields: Vec<&str> = fen.split_whitespace().collect();
+        if fields.len() != 6 && fields.len() != 4 {
+            return Err(ChessError::InvalidFen(format!(
+                "FEN '{}' should have 6 (or 4) fields, but has {}",
+                fen,
+                fields.len()
+            )));
+        }
+
+        let mut game = Game::new();
+
+        // 1st field: piece placement
+        game.board = [None; 8 * 8];
+        let ranks: Vec<&str> = fields[0].split('/').collect();
+        if ranks.len() != 8 {
+            return Err(ChessError::InvalidFen(format!(
+                "FEN piece placement '{}' should have 8 ranks, but has {}",
+                fields[0],
+                ranks.len()
+            )));
+        }
+        for (i, rank_str) in ranks.iter().enumerate() {
+            let rank = 7 - i; // the placement starts at rank 8
+            let mut file = 0;
+            for ch in rank_str.chars() {
+                if file >= 8 {
+                    return Err(ChessError::InvalidFen(format!(
+                        "FEN rank '{}' is longer than 8 squares",
+                        rank_str
+                    )));
+                }
+                match ch.to_digit(10) {
+                    Some(empty @ 1..=8) => file += empty as usize,
+                    Some(_) => {
+                        return Err(ChessError::InvalidFen(format!(
+                            "FEN rank '{}' contains an invalid number",
+                            rank_str
+                        )))
+                    }
+                    None => {
+                        if !ch.is_ascii_alphabetic() {
+                            return Err(ChessError::InvalidFen(format!(
+                                "'{}' does not represent a piece",
+                                ch
+                            )));
+                        }
+                        game.board[Position::idx(rank, file)] = Some(Piece {
+                            piece_type: PieceType::from_char(ch)
+                                .map_err(|err| ChessError::InvalidFen(err.to_string()))?,
+                            colour: if ch.is_ascii_uppercase() {
+                                Colour::White
+                            } else {
+                                Colour::Black
+                            },
+                        });
+                        file += 1;
+                    }
                 }
-                Err(_) => {}
+            }
+            if file != 8 {
+                return Err(ChessError::InvalidFen(format!(
+                    "FEN rank '{}' does not have 8 squares",
+                    rank_str
+                )));
+            }
+        }
+        for colour in [Colour::White, Colour::Black] {
+            let kings = game
+                .board
+                .iter()
+                .flatten()
+                .filter(|p| p.is_king() && p.colour == colour)
+                .count();
+            if kings != 1 {
+                return Err(ChessError::InvalidFen(format!(
+                    "The {:?} side should have exactly one king, but has {}",
+                    colour, kings
+                )));
             }
         }
-        self.board[pos.idx] = Some(piece);
-        // TODO update state appropriately if this upsets en passant, castling, check, checkmate or promotions
-        return Ok(());
-    }
 
-    /// Removes the piece at position `pos` and returns it.
-    ///
-    /// Returns None if there is no piece at `pos`.
-    ///
-    /// Errors if `pos` is invalid.
-    pub fn remove(&mut self, pos: Position) -> Result<Option<Piece>, String> {
-        pos.valid()?;
-        let removed_piece = self.board[pos.idx];
-        self.board[pos.idx] = None;
-        return Ok(removed_piece);
-    }
+        // 2nd field: active colour
+        game.active_colour = match fields[1] {
+            "w" => Colour::White,
+            "b" => Colour::Black,
+            _ => {
+                return Err(ChessError::InvalidFen(format!(
+                    "'{}' is not a valid active colour",
+                    fields[1]
+                )))
+            }
+        };
 
-    /// Returns true if the threefold repetition rule can be enacted, otherwise false.
-    pub fn is_threefold_repetition(&self) -> bool {
-        let mut count = 0;
-        let fen = self.fen();
-        'o: for entry in self.history.clone() {
-            let mut f1 = entry.fen.split(" ");
-            let mut f2 = fen.split(" ");
-            for _ in 0..4 {
-                if f1.next().expect("fen") != f2.next().expect("fen") {
-                    eprintln!("{:?},{:?}", fen, entry);
-                    continue 'o;
-                }
+        // 3rd field: castling rights
+        game.castling_rights = CastlingRights::from_fen(fields[2])?;
+        // The engine assumes that the king and rook are in place when the right to castle remains.
+        if !game.castling_rights_without_pieces().is_empty() {
+            return Err(ChessError::InvalidFen(format!(
+                "The castling rights '{}' do not match the placement of the kings and rooks",
+                fields[2]
+            )));
+        }
+
+        // 4th field: possible en passant target
+        game.en_passant_target = if fields[3] == "-" {
+            Position::NULL
+        } else {
+            let target = Position::parse_str(fields[3])
+                .map_err(|err| ChessError::InvalidFen(err.to_string()))?;
+            // The target is behind a pawn of the inactive colour that just moved two squares.
+            let expected_rank = match game.active_colour {
+                Colour::White => 5,
+                Colour::Black => 2,
+            };
+            if target.rank != expected_rank {
+                return Err(ChessError::InvalidFen(format!(
+                    "'{}' is not a valid en passant target when it is {:?}'s turn",
+                    fields[3], game.active_colour
+                )));
             }
-            count += 1;
+            target
+        };
+        if game.en_passant_target_without_pawn() {
+            return Err(ChessError::InvalidFen(format!(
+                "There is no pawn of {:?} in front of the en passant target '{}'",
+                game.active_colour.invert(),
+                fields[3]
+            )));
         }
-        return count >= 2;
-    }
 
-    /// Returns true if the fivefold repetition rule has been enacted, otherwise false.
-    pub fn is_fivefold_repetition(&self) -> bool {
-        let mut count = 0;
-        let fen = self.fen();
-        'o: for entry in self.history.clone() {
-            let mut f1 = entry.fen.split(" ");
-            let mut f2 = fen.split(" ");
-            for _ in 0..4 {
-                if f1.next().expect("fen") != f2.next().expect("fen") {
-                    eprintln!("{:?},{:?}", fen, entry);
-                    continue 'o;
+        // 5th and 6th field: halfmoves and fullmoves
+        if fields.len() == 6 {
+            game.halfmoves = match fields[4].parse() {
+                Ok(halfmoves) => halfmoves,
+                Err(_) => {
+                    return Err(ChessError::InvalidFen(format!(
+                        "'{}' is not a valid halfmove clock",
+                        fields[4]
+                    )))
                 }
+            };
+            // The fullmove number starts at 1
+            game.fullmoves = match fields[5].parse() {
+                Ok(fullmoves) if fullmoves > 0 => fullmoves,
+                _ => {
+                    return Err(ChessError::InvalidFen(format!(
+                        "'{}' is not a valid fullmove number",
+                        fields[5]
+                    )))
+                }
+            };
+        } else {
+            game.halfmoves = 0;
+            game.fullmoves = 1;
+        }
+
+        // Pawns can never stand on the first or last rank.
+        for file in 0..8 {
+            if game.board[Position::idx(0, file)].is_some_and(|p| p.is_pawn())
+                || game.board[Position::idx(7, file)].is_some_and(|p| p.is_pawn())
+            {
+                return Err(ChessError::InvalidFen(
+                    "FEN places a pawn on the first or last rank".to_owned(),
+                ));
             }
-            count += 1;
         }
-        return count >= 4;
-    }
 
-    /// Returns true if the 50-move rule can be enacted, otherwise false.
-    pub fn is_50_move_rule(&self) -> bool {
-        return self.halfmoves >= 100;
+        game.position_keys = vec![game.position_key()];
+        game.evaluate_game_state();
+        return Ok(game);
     }
 
-    /// Returns true if the 75-move rule has been enacted, otherwise false.
-    pub fn is_75_move_rule(&self) -> bool {
-        return self.halfmoves >= 150;
-    }
+    /// Returns the `Option<Piece>` at position `pos`.
+    ///
+    /// Is None if there is no piece at `pos`.
+    ///
+    /// Errors if `pos` is invalid.
+    pub fn get(&self, pos: Position) -> Result<Option<Piece>, ChessError> {
+        pos.valid()?;
+        return Ok(self.board[pos.idx]);
+    }
+
+    /// Returns an iterator over every piece on the board together with its position, from a1 to h8.
+    ///
+    /// # Example code
+    ///
+    /// ```rust
+    /// use chess_engine::*;
+    ///
+    /// let game = Game::new();
+    /// assert_eq!(game.pieces().count(), 32);
+    /// let (pos, piece) = game.pieces().next().unwrap();
+    /// assert_eq!(pos, Position::parse_str("a1").unwrap());
+    /// assert!(piece.is_rook());
+    /// ```
+    pub fn pieces(&self) -> impl Iterator<Item = (Position, Piece)> + '_ {
+        return self.board.iter().enumerate().filter_map(|(idx, piece)| {
+            piece.map(|piece| (Position::new_from_idx(idx).expect("enumerated"), piece))
+        });
+    }
+
+    /// Returns an iterator over every piece of colour `colour` together with its position, from a1 to h8.
+    pub fn pieces_of(&self, colour: Colour) -> impl Iterator<Item = (Position, Piece)> + '_ {
+        return self.pieces().filter(move |(_, piece)| piece.colour == colour);
+    }
+
+    /// Returns the position of the king of colour `colour`, or None if it is not on the board.
+    pub fn king_position(&self, colour: Colour) -> Option<Position> {
+        return self.find_king(colour).ok();
+    }
+
+    /// Returns the positions of every piece of type `piece_type` and colour `colour`, from a1 to h8.
+    ///
+    /// # Example code
+    ///
+    /// ```rust
+    /// use chess_engine::*;
+    ///
+    /// let game = Game::new();
+    /// let knights = game.find_pieces(PieceType::Knight, Colour::Black);
+    /// assert_eq!(knights, vec![Position::parse_str("b8").unwrap(), Position::parse_str("g8").unwrap()]);
+    /// assert_eq!(game.king_position(Colour::White), Position::parse_str("e1").ok());
+    /// ```
+    pub fn find_pieces(&self, piece_type: PieceType, colour: Colour) -> Vec<Position> {
+        return self
+            .pieces_of(colour)
+            .filter(|(_, piece)| piece.piece_type == piece_type)
+            .map(|(pos, _)| pos)
+            .collect();
+    }
+
+    /// Puts `piece` at position `pos`.
+    ///
+    /// The game state, castling rights and en passant target are not updated, so that a position can be
+    /// set up piece by piece. Call `refresh_state()` when the position is done.
+    ///
+    /// Errors if `pos` is invalid or the placement results in a board with multiple kings.
+    /// (The engine does not support placing multiple kings of the same color).
+    pub fn put(&mut self, pos: Position, piece: Piece) -> Result<(), ChessError> {
+        pos.valid()?;
+        if piece.piece_type == PieceType::King && self.find_king(piece.colour).is_ok() {
+            return Err(ChessError::InvalidPosition(format!(
+                "The {:?} king is already on the board, a second one cannot be placed",
+                piece.colour
+            )));
+        }
+        self.board[pos.idx] = Some(piece);
+        return Ok(());
+    }
+
+    /// Removes the piece at position `pos` and returns it.
+    ///
+    /// Returns None if there is no piece at `pos`. Like `put`, the game state is not updated, see `refresh_state()`.
+    ///
+    /// Errors if `pos` is invalid.
+    pub fn remove(&mut self, pos: Position) -> Result<Option<Piece>, ChessError> {
+        pos.valid()?;
+        let removed_piece = self.board[pos.idx];
+        self.board[pos.idx] = None;
+        return Ok(removed_piece);
+    }
+
+    /// Updates the game after the board has been edited with `put` or `remove`, e.g. in a position editor.
+    ///
+    /// Castling rights are dropped where the king or rook is no longer on its starting position, and the
+    /// en passant target is dropped unless a pawn that just moved two positions stands in front of it.
+    /// Then check, checkmate, stalemate, dead positions and the other rules are evaluated for the position.
+    ///
+    /// Results that do not follow from the position, i.e. resignations, timeouts and agreed or claimed draws, are kept,
+    /// as is a promotion choice that the game is still waiting on. Pawns on the first or last rank are left as they are,
+    /// see `validate_position`.
+    ///
+    /// # Example code
+    ///
+    /// ```rust
+    /// use chess_engine::*;
+    ///
+    /// let mut game = Game::new();
+    /// game.remove(Position::parse_str("h1").unwrap()).unwrap();
+    /// game.put(Position::parse_str("e2").unwrap(), "r".parse().unwrap()).unwrap();
+    /// game.refresh_state();
+    /// assert_eq!(game.get_game_state(), GameState::Check);
+    /// assert_eq!(game.fen(), "rnbqkbnr/pppppppp/8/8/8/8/PPPPrPPP/RNBQKBN1 w Qkq - 0 1");
+    /// ```
+    pub fn refresh_state(&mut self) {
+        // The engine assumes that the king and rook are in place when the right to castle remains.
+        for (colour, side) in self.castling_rights_without_pieces() {
+            self.castling_rights.remove(colour, side);
+        }
+
+        // The en passant target is behind a pawn of the inactive colour that just moved two positions.
+        if self.en_passant_target != Position::NULL {
+            let dir = self.active_colour.pawn_dir();
+            let moved = self.en_passant_target.offset(-dir, 0);
+            let start = self.en_passant_target.offset(dir, 0);
+            let valid = match (moved, start) {
+                (Ok(moved), Ok(start)) => {
+                    self.board[moved.idx]
+                        .is_some_and(|p| p.is_pawn() && p.colour != self.active_colour)
+                        && self.board[self.en_passant_target.idx].is_none()
+                        && self.board[start.idx].is_none()
+                }
+                _ => false,
+            };
+            if !valid {
+                self.en_passant_target = Position::NULL;
+            }
+        }
+
+        // The current position replaces the one from before the edits for the repetition rules.
+        let key = self.position_key();
+        if let Some(last) = self.position_keys.last_mut() {
+            *last = key;
+        }
+
+        match (self.state, self.game_over_reason) {
+            (
+                GameState::GameOver,
+                Some(GameOverReason::ManualDraw)
+                | Some(GameOverReason::DrawByAgreement)
+                | Some(GameOverReason::FiftyMoveRule)
+                | Some(GameOverReason::ThreefoldRepetition)
+                | Some(GameOverReason::Resignation(_))
+                | Some(GameOverReason::Timeout(_))
+                | Some(GameOverReason::TimeoutDraw(_)),
+            ) => {}
+            (GameState::WaitingOnPromotionChoice, _) if self.find_pawn_to_promote().is_ok() => {}
+            _ => {
+                self.game_over_reason = None;
+                self.evaluate_game_state();
+            }
+        }
+    }
+
+    /// Sets the colour to move, e.g. when choosing who moves first in a position editor, and returns the new game state.
+    ///
+    /// If the turn changes, the en passant target is dropped, since it belonged to the last move of the other colour.
+    /// Then the game state is updated like with `refresh_state()`.
+    ///
+    /// Errors if the other colour's king would be in check while it is not its turn, or if the game is waiting on
+    /// a promotion choice. The game is not changed if an error is returned.
+    ///
+    /// # Example code
+    ///
+    /// ```rust
+    /// use chess_engine::*;
+    ///
+    /// let mut game = Game::from_fen("4k3/8/8/8/8/8/4r3/4K3 b - - 0 1").unwrap();
+    /// assert!(game.set_active_colour(Colour::White).is_ok());
+    /// assert_eq!(game.get_game_state(), GameState::Check);
+    /// // The black king cannot be in check while white is to move
+    /// let mut game = Game::from_fen("4k3/8/8/8/8/8/4R3/4K3 b - - 0 1").unwrap();
+    /// assert!(game.set_active_colour(Colour::White).is_err());
+    /// ```
+    pub fn set_active_colour(&mut self, colour: Colour) -> Result<GameState, ChessError> {
+        if self.state == GameState::WaitingOnPromotionChoice {
+            return Err(ChessError::WaitingOnPromotion);
+        }
+        if self.is_in_check(colour.invert()) {
+            return Err(ChessError::InvalidPosition(
+                PositionIssue::OpponentInCheck(colour.invert()).to_string(),
+            ));
+        }
+        if colour != self.active_colour {
+            self.active_colour = colour;
+            self.en_passant_target = Position::NULL;
+        }
+        self.refresh_state();
+        return Ok(self.state);
+    }
+
+    /// Passes the turn to the other colour without making a move, e.g. to let the same colour move twice
+    /// while setting up a position, and returns the new game state. See `set_active_colour`.
+    ///
+    /// Errors if the colour to move is in check, since its king would be in check on the other colour's turn,
+    /// or if the game is waiting on a promotion choice.
+    pub fn skip_turn(&mut self) -> Result<GameState, ChessError> {
+        return self.set_active_colour(self.active_colour.invert());
+    }
+
+    /// Returns true if the threefold repetition rule can be enacted, otherwise false.
+    ///
+    /// Positions are the same if the pieces, the active colour, and the castling and en passant captures
+    /// that can be made are the same. Castling rights that cannot be used right now do not make positions different.
+    pub fn is_threefold_repetition(&self) -> bool {
+        return self.repetitions() >= 3;
+    }
+
+    /// Returns true if the fivefold repetition rule has been enacted, otherwise false.
+    ///
+    /// See `is_threefold_repetition` for when positions are the same.
+    pub fn is_fivefold_repetition(&self) -> bool {
+        return self.repetitions() >= 5;
+    }
+
+    /// Returns how many times the current position has occurred, including now.
+    fn repetitions(&self) -> usize {
+        let key = match self.position_keys.last() {
+            None => return 0,
+            Some(key) => key,
+        };
+        return self.position_keys.iter().filter(|k| *k == key).count();
+    }
+
+    /// Returns the key that identifies the current position for the repetition rules.
+    ///
+    /// Castling and en passant are only included if the castling or en passant capture can be made right now,
+    /// so that positions do not differ by rights that cannot be used.
+    fn position_key(&self) -> PositionKey {
+        let mut castling = [false; 4];
+        for (i, (colour, rank)) in [(Colour::White, 0), (Colour::Black, 7)].iter().enumerate() {
+            let king_pos = match self.find_king(*colour) {
+                Ok(pos) if pos.rank == *rank && pos.file == 4 => pos,
+                _ => continue,
+            };
+            // The king can only move two files by castling.
+            let moves = self._get_possible_moves(king_pos).expect("found");
+            castling[2 * i] = moves.iter().any(|pos| pos.file == 6);
+            castling[2 * i + 1] = moves.iter().any(|pos| pos.file == 2);
+        }
+
+        return PositionKey {
+            board: self.board,
+            active_colour: self.active_colour,
+            castling,
+            en_passant_target: self.capturable_en_passant_target(),
+        };
+    }
+
+    /// Returns the en passant target if some pawn of the active colour can legally capture en passant there,
+    /// otherwise Position::NULL.
+    fn capturable_en_passant_target(&self) -> Position {
+        if self.en_passant_target == Position::NULL {
+            return Position::NULL;
+        }
+        let dir = self.active_colour.pawn_dir();
+        for file_offset in [-1, 1] {
+            if let Ok(pos) = self.en_passant_target.offset(-dir, file_offset) {
+                if self.board[pos.idx]
+                    .is_some_and(|p| p.is_pawn() && p.colour == self.active_colour)
+                    && self
+                        ._get_possible_moves(pos)
+                        .expect("valid")
+                        .contains(&self.en_passant_target)
+                {
+                    return self.en_passant_target;
+                }
+            }
+        }
+        return Position::NULL;
+    }
+
+    /// Returns true if the 50-move rule can be enacted, otherwise false.
+    pub fn is_50_move_rule(&self) -> bool {
+        return self.halfmoves >= 100;
+    }
+
+    /// Returns true if the 75-move rule has been enacted, otherwise false.
+    pub fn is_75_move_rule(&self) -> bool {
+        return self.halfmoves >= 150;
+    }
 
     /// Returns true if the game is over, otherwise false.
     pub fn is_gameover(&self) -> bool {
@@ -861,6 +1679,20 @@ impl Game {
         return self.state == GameState::Check;
     }
 
+    /// Returns true if the `colour`'s king is attacked, otherwise false. Works for either colour, regardless of whose turn it is.
+    ///
+    /// Returns false if `colour` has no king on the board.
+    pub fn is_in_check_for(&self, colour: Colour) -> bool {
+        return self.is_in_check(colour);
+    }
+
+    /// Returns the positions of the pieces that give check to the active colour's king.
+    ///
+    /// Is empty if the king is not in check, and has two positions in the case of a double check.
+    pub fn get_checkers(&self) -> Vec<Position> {
+        return self.checkers(self.active_colour);
+    }
+
     /// Returns true if the active colour's king is checkmated, otherwise false.
     pub fn is_checkmate(&self) -> bool {
         return self
@@ -868,106 +1700,471 @@ impl Game {
             .is_some_and(|r| r == GameOverReason::Checkmate);
     }
 
+    /// Returns true if the active colour's king is checkmated in the position on the board, otherwise false.
+    ///
+    /// Unlike `is_checkmate`, which tells how the game ended, the position itself is examined, so this also works
+    /// right after the board has been edited with `put` or `remove`, before the game state has been updated.
+    ///
+    /// # Example code
+    ///
+    /// ```rust
+    /// use chess_engine::*;
+    ///
+    /// let mut game = Game::from_fen("k7/8/1K6/8/8/8/8/8 b - - 0 1").unwrap();
+    /// assert!(!game.is_checkmate_position());
+    /// game.put(Position::parse_str("b7").unwrap(), "Q".parse().unwrap()).unwrap();
+    /// assert!(game.is_checkmate_position());
+    /// ```
+    pub fn is_checkmate_position(&self) -> bool {
+        return self.is_in_check(self.active_colour) && !self._can_make_legal_move();
+    }
+
+    /// Returns true if the active colour is stalemated in the position on the board: its king is not in check,
+    /// but it has no possible move. Otherwise false, also if the active colour has no king on the board.
+    ///
+    /// Like `is_checkmate_position`, the position itself is examined, regardless of the game state.
+    pub fn is_stalemate_position(&self) -> bool {
+        return self.find_king(self.active_colour).is_ok()
+            && !self.is_in_check(self.active_colour)
+            && !self._can_make_legal_move();
+    }
+
+    /// Returns true if some piece of colour `by_colour` attacks position `pos`, otherwise false.
+    ///
+    /// Works regardless of whose turn it is. A piece attacks a position if it could capture a piece there,
+    /// also when the capture would put its own king in check, or when a piece of its own colour stands there.
+    ///
+    /// Errors if `pos` is invalid.
+    pub fn is_square_attacked(&self, pos: Position, by_colour: Colour) -> Result<bool, ChessError> {
+        pos.valid()?;
+        return Ok(!Game::attackers(&self.board, pos, by_colour).is_empty());
+    }
+
     /// Submits a manual draw and puts the game in game over
     pub fn submit_draw(&mut self) {
         self.state = GameState::GameOver;
         self.game_over_reason = Some(GameOverReason::ManualDraw);
     }
 
+    /// Resigns the game on behalf of `colour` and puts the game in game over, see `GameOverReason::Resignation`.
+    ///
+    /// Errors if the game is over.
+    pub fn resign(&mut self, colour: Colour) -> Result<GameState, ChessError> {
+        if self.is_gameover() {
+            return Err(ChessError::GameOver);
+        }
+        self.state = GameState::GameOver;
+        self.game_over_reason = Some(GameOverReason::Resignation(colour));
+        return Ok(self.state);
+    }
+
+    /// Submits that `colour` has run out of time and puts the game in game over, see `GameOverReason::Timeout`.
+    /// If the other colour has no mating material, see `has_mating_material`, the game is drawn instead,
+    /// see `GameOverReason::TimeoutDraw`.
+    ///
+    /// The engine keeps no clocks, so this is up to the front-end. Errors if the game is over.
+    pub fn submit_timeout(&mut self, colour: Colour) -> Result<GameState, ChessError> {
+        if self.is_gameover() {
+            return Err(ChessError::GameOver);
+        }
+        self.state = GameState::GameOver;
+        self.game_over_reason = Some(if self.has_mating_material(colour.invert()) {
+            GameOverReason::Timeout(colour)
+        } else {
+            GameOverReason::TimeoutDraw(colour)
+        });
+        return Ok(self.state);
+    }
+
+    /// Claims a draw by the threefold repetition rule or the 50-move rule and puts the game in game over.
+    ///
+    /// The game over reason is `GameOverReason::ThreefoldRepetition` if the current position has occurred three times,
+    /// otherwise `GameOverReason::FiftyMoveRule`.
+    ///
+    /// Errors if the game is over or neither rule applies.
+    pub fn claim_draw(&mut self) -> Result<GameState, ChessError> {
+        if self.is_gameover() {
+            return Err(ChessError::GameOver);
+        }
+        let reason = if self.is_threefold_repetition() {
+            GameOverReason::ThreefoldRepetition
+        } else if self.is_50_move_rule() {
+            GameOverReason::FiftyMoveRule
+        } else {
+            return Err(ChessError::NoDrawClaim);
+        };
+        self.state = GameState::GameOver;
+        self.game_over_reason = Some(reason);
+        return Ok(self.state);
+    }
+
+    /// Offers a draw on behalf of `colour`, replacing any pending offer.
+    ///
+    /// The offer is pending until it is accepted with `accept_draw()`, declined with `decline_draw()`,
+    /// or the other colour makes a move.
+    ///
+    /// Errors if the game is over.
+    pub fn offer_draw(&mut self, colour: Colour) -> Result<(), ChessError> {
+        if self.is_gameover() {
+            return Err(ChessError::GameOver);
+        }
+        self.draw_offer = Some(colour);
+        return Ok(());
+    }
+
+    /// Accepts the draw offer of the other colour than `colour` and puts the game in game over,
+    /// see `GameOverReason::DrawByAgreement`.
+    ///
+    /// Errors if the game is over or no draw offer of the other colour is pending, so a colour cannot accept
+    /// its own offer.
+    pub fn accept_draw(&mut self, colour: Colour) -> Result<GameState, ChessError> {
+        if self.is_gameover() {
+            return Err(ChessError::GameOver);
+        }
+        if self.draw_offer != Some(colour.invert()) {
+            return Err(ChessError::NoDrawOffer);
+        }
+        self.draw_offer = None;
+        self.state = GameState::GameOver;
+        self.game_over_reason = Some(GameOverReason::DrawByAgreement);
+        return Ok(self.state);
+    }
+
+    /// Declines the pending draw offer. The game goes on.
+    ///
+    /// Errors if no draw offer is pending.
+    pub fn decline_draw(&mut self) -> Result<(), ChessError> {
+        if self.draw_offer.take().is_none() {
+            return Err(ChessError::NoDrawOffer);
+        }
+        return Ok(());
+    }
+
+    /// Get the colour that offered a draw. Is None if no draw offer is pending.
+    pub fn get_draw_offer(&self) -> Option<Colour> {
+        self.draw_offer
+    }
+
     /// If the game is not over, try to perform the move `from_str` to `to_str`.
     ///
     /// `from_str` and `to_str` are parsed as XF where X is a character a-h and F is a number 1-8.
     ///
     /// Errors if the move is not legal, the game is over or the input is invalid.
-    pub fn make_move(&mut self, from_str: &str, to_str: &str) -> Result<GameState, String> {
+    pub fn make_move(&mut self, from_str: &str, to_str: &str) -> Result<MoveResult, ChessError> {
         // parse from_str
-        let from_pos = match Position::parse_str(&from_str) {
-            Ok(result) => result,
-            Err(string) => return Err(string),
-        };
+        let from_pos = Position::parse_str(from_str)?;
 
         // parse to_str
-        let to_pos = match Position::parse_str(&to_str) {
-            Ok(result) => result,
-            Err(string) => return Err(string),
-        };
+        let to_pos = Position::parse_str(to_str)?;
 
         return self.make_move_pos(from_pos, to_pos);
     }
 
     /// If the game is not over, try to perform a move between Positions `from_pos` to `to_pos`.
     ///
+    /// Returns what happened in the move, e.g. what was captured and if the game now waits on a promotion choice,
+    /// see `MoveResult`. The other methods that make moves return the same.
+    ///
     /// Errors if the move is not legal, the game is over or the input is invalid.
     pub fn make_move_pos(
         &mut self,
         from_pos: Position,
         to_pos: Position,
-    ) -> Result<GameState, String> {
-        // Checks that the game state is InProgress or Check, else throws an error.
-        if !(self.state == GameState::InProgress || self.state == GameState::Check) {
-            let error = format!("The game is not in a state where a move can be made. Currently, the state is {:?}.", self.state);
-            return Err(error);
-        }
+    ) -> Result<MoveResult, ChessError> {
+        self.check_move(from_pos, to_pos)?;
+        self.make_legal_move(from_pos, to_pos)?;
+        return Ok(self.last_move_result());
+    }
 
-        from_pos.valid()?;
-        to_pos.valid()?;
+    /// Makes the move from `from_pos` to `to_pos`, which `check_move` has found legal.
+    fn make_legal_move(&mut self, from_pos: Position, to_pos: Position) -> Result<(), ChessError> {
+        // We move the piece!
+        self._perfom_move(from_pos, to_pos)?;
+        // and update the game state (and maybe active colour)
+        self.update_game_state();
+        #[cfg(feature = "debug-validate")]
+        self.assert_valid();
+        return Ok(());
+    }
 
-        // check that the the piece is not None and is of the right colour
-        match self.board[from_pos.idx] {
-            None => {
-                return Err(
-                    "There is no piece on the square you are trying to move from".to_owned(),
-                )
+    /// Returns true if moving the piece at `from_pos` to `to_pos` is legal, i.e. if `make_move_pos` would make the move.
+    ///
+    /// Only this move is checked, which is much faster than looking for `to_pos` in `get_possible_moves(from_pos)`,
+    /// e.g. to validate the target of a drag-and-drop. See `check_move` for why a move is not legal.
+    pub fn is_legal_move(&self, from_pos: Position, to_pos: Position) -> bool {
+        return self.check_move(from_pos, to_pos).is_ok();
+    }
+
+    /// Returns Ok if moving the piece at `from_pos` to `to_pos` is legal, see `is_legal_move`.
+    ///
+    /// Errors with the error that `make_move_pos` would return for the move: if the move is not legal,
+    /// the game is over or waiting on a promotion choice, or the input is invalid.
+    /// See `illegal_move_reason` for the reason as an `IllegalMoveReason`.
+    pub fn check_move(&self, from_pos: Position, to_pos: Position) -> Result<(), ChessError> {
+        return match self.illegal_move_reason(from_pos, to_pos) {
+            None => Ok(()),
+            Some(reason) => Err(reason.into()),
+        };
+    }
+
+    /// If the game is not over, try to perform the move `mv`, including the promotion if the move is one.
+    ///
+    /// Unlike `make_move_pos`, a promotion is made in one step, without passing through `GameState::WaitingOnPromotionChoice`.
+    ///
+    /// Errors if the move is not legal, the game is over, or `mv.promotion` is missing for a promotion or set for another move.
+    /// The game is not changed if an error is returned.
+    pub fn play_move(&mut self, mv: Move) -> Result<MoveResult, ChessError> {
+        self.check_move(mv.from, mv.to)?;
+        let is_promotion = self.board[mv.from.idx].is_some_and(|p| p.is_pawn())
+            && (mv.to.rank == 0 || mv.to.rank == 7);
+        match (is_promotion, mv.promotion) {
+            (true, None) => {
+                return Err(ChessError::InvalidPromotion(
+                    "The move is a promotion but no piece to promote to was given".to_owned(),
+                ));
             }
-            Some(piece) => {
-                if piece.colour != self.active_colour {
-                    return Err("It is not this colour's turn!".to_owned());
-                }
+            (false, Some(_)) => {
+                return Err(ChessError::InvalidPromotion(
+                    "The move is not a promotion".to_owned(),
+                ))
             }
+            _ => {}
         }
 
-        // Generates a list of all the legal moves that the piece in question can perform.
-        let possible_moves = self.get_possible_moves(from_pos)?;
+        self.make_legal_move(mv.from, mv.to)?;
+        if let Some(piece_type) = mv.promotion {
+            if let Err(err) = self.set_promotion(piece_type) {
+                self.undo_move().expect("the move was just made");
+                return Err(err);
+            }
+        }
+        return Ok(self.last_move_result());
+    }
 
-        // Check if our position is a possible move for this piece.
-        if !possible_moves
-            .iter() // Creates an iterable of positions.
-            .any(|pos| pos == &to_pos)
-        {
-            return Err("Illegal move. (This might mean that this piece cannot move this way, or that it puts your king in check!)".to_owned());
-        } else {
-            // We move the piece!
-            self._perfom_move(from_pos, to_pos)?;
-            // and update the game state (and maybe active colour)
-            self.update_game_state();
+    /// Takes back the last move like `undo_move`, and also drops it from the variations if it was new there.
+    ///
+    /// Used by searches that make and take back moves on a game from `search_copy`, so that the variation tree
+    /// does not grow with every position searched.
+    pub(crate) fn unmake_move(&mut self) {
+        // A move waiting on the promotion choice has not been recorded in the variations yet
+        let recorded = self.state != GameState::WaitingOnPromotionChoice;
+        self.undo_move().expect("a move was made");
+        if recorded {
+            self.variations.forget_last();
+        }
+    }
+
+    /// Returns a copy of the game to search by making and taking back moves with `play_move` and `unmake_move`.
+    ///
+    /// The history is kept for the repetition rules, but the variations start empty.
+    pub(crate) fn search_copy(&self) -> Game {
+        return Game {
+            variations: VariationTree::new(),
+            ..self.clone()
+        };
+    }
 
-            return Ok(self.state);
+    /// Returns what happened in the most recent move, read from the history.
+    fn last_move_result(&self) -> MoveResult {
+        let entry = self.last_move().expect("is called after a move");
+        let en_passant = entry.piece_moved.is_pawn()
+            && entry.from.file != entry.to.file
+            && entry.before.board[entry.to.idx].is_none();
+        let captured_at = match entry.piece_captured {
+            None => None,
+            Some(_) if en_passant => Position::new(entry.from.rank, entry.to.file).ok(),
+            Some(_) => Some(entry.to),
+        };
+        return MoveResult {
+            state: self.state,
+            from: entry.from,
+            to: entry.to,
+            piece_moved: entry.piece_moved,
+            piece_captured: entry.piece_captured,
+            captured_at,
+            is_castle: entry.piece_moved.is_king() && entry.from.file.abs_diff(entry.to.file) == 2,
+            is_check: entry.is_check,
+            promotion_pending: self.state == GameState::WaitingOnPromotionChoice,
+            promotion: entry.promotion,
+        };
+    }
+
+    /// If the game is not over, try to perform the move `uci` written in coordinate notation (as in UCI),
+    /// e.g. "e2e4", "e1g1" for castling or "e7e8q" for a promotion, see `play_move`.
+    ///
+    /// Errors if `uci` is not a move in coordinate notation, the move is not legal or the game is over.
+    /// The game is not changed if an error is returned.
+    pub fn make_move_uci(&mut self, uci: &str) -> Result<MoveResult, ChessError> {
+        return self.play_move(Move::from_uci(uci)?);
+    }
+
+    /// Takes back the last move, restoring the board, castling rights, en passant target, clocks and game state
+    /// to what they were before it. A move waiting on a promotion choice is taken back as a whole.
+    ///
+    /// Works also when the game is over, so that e.g. a mating move can be taken back.
+    ///
+    /// Errors if no move has been made.
+    pub fn undo_move(&mut self) -> Result<GameState, ChessError> {
+        let entry = match self.history.pop() {
+            None => return Err(ChessError::NothingToUndo),
+            Some(entry) => entry,
+        };
+        // A move waiting on the promotion choice has not been recorded in the variations yet
+        if self.state != GameState::WaitingOnPromotionChoice {
+            self.variations.back();
         }
+        let before = entry.before;
+        self.state = before.state;
+        self.game_over_reason = before.game_over_reason;
+        self.active_colour = before.active_colour;
+        self.board = before.board;
+        self.halfmoves = before.halfmoves;
+        self.fullmoves = before.fullmoves;
+        self.en_passant_target = before.en_passant_target;
+        self.castling_rights = before.castling_rights;
+        self.draw_offer = before.draw_offer;
+        self.position_keys.truncate(before.position_keys);
+        #[cfg(feature = "debug-validate")]
+        self.assert_valid();
+        return Ok(self.state);
+    }
+
+    /// Takes back the last `n` moves, see `undo_move`.
+    ///
+    /// Errors if fewer than `n` moves have been made. The game is not changed if an error is returned.
+    pub fn undo_moves(&mut self, n: usize) -> Result<GameState, ChessError> {
+        if n > self.history.len() {
+            return Err(ChessError::NothingToUndo);
+        }
+        for _ in 0..n {
+            self.undo_move()?;
+        }
+        return Ok(self.state);
+    }
+
+    /// Returns the game as it was after the first `n` half-moves of the history, e.g. to jump to a move in a move list.
+    /// With 0, the game is returned as it was before the first move.
+    ///
+    /// The game is rebuilt from the history like with `undo_moves`, so the clocks, castling rights, en passant target,
+    /// draw offer and game state are exact, and moves can be made from it. The game itself is left untouched.
+    ///
+    /// Errors if fewer than `n` half-moves have been made.
+    ///
+    /// # Example code
+    ///
+    /// ```rust
+    /// use chess_engine::*;
+    ///
+    /// let mut game = Game::new();
+    /// for mv in ["e2e4", "e7e5", "g1f3"] {
+    ///     game.make_move_uci(mv).unwrap();
+    /// }
+    /// let game_after_e4 = game.at_ply(1).unwrap();
+    /// assert_eq!(game_after_e4.fen(), "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq - 0 1");
+    /// assert_eq!(game_after_e4.get_history().len(), 1);
+    /// assert_eq!(game.at_ply(4).unwrap_err(), ChessError::NoSuchPly(4));
+    /// ```
+    pub fn at_ply(&self, n: usize) -> Result<Game, ChessError> {
+        if n > self.history.len() {
+            return Err(ChessError::NoSuchPly(n));
+        }
+        let mut game = self.clone();
+        game.undo_moves(self.history.len() - n)?;
+        return Ok(game);
+    }
+
+    /// Returns a copy of the position without the history, the variations and the earlier positions of the
+    /// repetition rules, which is cheap to make however long the game is. The state, the rules and the restrictions
+    /// are kept, so the snapshot has the same moves and evaluation as the game.
+    ///
+    /// `Game` is `Send` and `Sync`, so a snapshot can be moved to another thread to be analysed, e.g. by a search,
+    /// while the game goes on in the thread that took it.
+    ///
+    /// # Example code
+    ///
+    /// ```rust
+    /// use chess_engine::*;
+    /// use std::thread;
+    ///
+    /// let mut game = Game::new();
+    /// game.make_move_uci("e2e4").unwrap();
+    /// let snapshot = game.snapshot();
+    /// let search = thread::spawn(move || snapshot.best_move(2));
+    ///
+    /// game.make_move_uci("e7e5").unwrap();
+    /// assert!(search.join().unwrap().is_some());
+    /// ```
+    pub fn snapshot(&self) -> Game {
+        return Game {
+            state: self.state,
+            game_over_reason: self.game_over_reason,
+            active_colour: self.active_colour,
+            board: self.board,
+            history: vec![],
+            halfmoves: self.halfmoves,
+            fullmoves: self.fullmoves,
+            en_passant_target: self.en_passant_target,
+            castling_rights: self.castling_rights,
+            restrictions: self.restrictions.clone(),
+            position_keys: vec![self.position_key()],
+            draw_offer: self.draw_offer,
+            rules: Arc::clone(&self.rules),
+            variations: VariationTree::new(),
+        };
     }
 
     /// Once a move is deemed okay, this method performs the move between from_pos and to_pos.
     ///
-    /// Also updates the fields `en_passant_target`, `halfmoves`, `fullmoves`, `white_has_right_to_castle_kingside` etc.
+    /// Also updates the fields `en_passant_target`, `halfmoves`, `fullmoves` and `castling_rights`.
     /// Removes an en passant-ed pawn, and moves the rook in the event of a castle.
     ///
-    /// Updating the castling fields when the king is checked is handled by `update_game_state()`.
     /// This function should be called after the move has been performed but before the active colour is updated.
-    fn _perfom_move(&mut self, from_pos: Position, to_pos: Position) -> Result<(), String> {
+    fn _perfom_move(&mut self, from_pos: Position, to_pos: Position) -> Result<(), ChessError> {
         // We move the piece!
-        let captured_piece: Option<Piece> = self.get(to_pos)?; // is None if none were captured
         let moved_piece = self
             .get(from_pos)?
             .expect("is never called trying to move an empty piece");
+        // The captured piece is None if none were captured.
+        // A pawn captured en passant stands beside the moved pawn, not at to_pos.
+        let captured_piece: Option<Piece> = if moved_piece.is_pawn()
+            && to_pos == self.en_passant_target
+            && from_pos.file != to_pos.file
+        {
+            self.board[Position::idx(from_pos.rank, to_pos.file)]
+        } else {
+            self.get(to_pos)?
+        };
 
         // Save game state in history vector
         self.history.push(HistoryEntry {
             fen: self.fen(),
-            from: from_pos.to_string(),
-            to: to_pos.to_string(),
+            from: from_pos,
+            to: to_pos,
             piece_moved: moved_piece,
             piece_captured: captured_piece,
+            promotion: None,
+            is_check: false, // set by update_game_state()
+            comment: None,
+            nags: vec![],
+            before: GameSnapshot {
+                state: self.state,
+                game_over_reason: self.game_over_reason,
+                active_colour: self.active_colour,
+                board: self.board,
+                halfmoves: self.halfmoves,
+                fullmoves: self.fullmoves,
+                en_passant_target: self.en_passant_target,
+                castling_rights: self.castling_rights,
+                draw_offer: self.draw_offer,
+                position_keys: self.position_keys.len(),
+            },
         });
 
+        // A pending draw offer lapses when the other colour moves instead of accepting it
+        if self.draw_offer.is_some_and(|colour| colour != moved_piece.colour) {
+            self.draw_offer = None;
+        }
+
         self.remove(from_pos)?;
         self.put(to_pos, moved_piece)?;
 
@@ -975,7 +2172,7 @@ impl Game {
         if moved_piece.is_pawn() || captured_piece.is_some() {
             self.halfmoves = 0;
         } else {
-            self.halfmoves += 1;
+            self.halfmoves = self.halfmoves.saturating_add(1);
         }
         // Fullmoves are incremented everytime black moves
         if self.active_colour.is_black() {
@@ -1008,93 +2205,43 @@ impl Game {
         match moved_piece.piece_type {
             PieceType::King => {
                 // If the king performs a castling move, we need to move the rook as well.
+                // The king keeps its right to castle only while at e1 or e8, so moving to c1, g1, c8 or g8 is castling.
                 // If the king moves, we need to disable future castling for the colour that moved.
-                match to_pos.idx {
-                    // Move rook if castling: 2 = c1, 6 = g1, 58 = c8, 62 = g8
-                    2 => {
-                        if self.white_has_right_to_castle_queenside {
-                            self.board[3] = self.board[0];
-                            self.board[0] = None;
-                        }
-                    }
-                    6 => {
-                        if self.white_has_right_to_castle_kingside {
-                            self.board[5] = self.board[7];
-                            self.board[7] = None;
-                        }
-                    }
-                    58 => {
-                        if self.black_has_right_to_castle_queenside {
-                            self.board[59] = self.board[56];
-                            self.board[56] = None;
-                        }
-                    }
-                    62 => {
-                        if self.black_has_right_to_castle_queenside {
-                            self.board[61] = self.board[63];
-                            self.board[63] = None;
-                        }
-                    }
-                    _ => {}
-                }
-
-                // Disable castling if the king moves.
-                match self.active_colour {
-                    Colour::White => {
-                        self.white_has_right_to_castle_queenside = false;
-                        self.white_has_right_to_castle_kingside = false;
-                    }
-                    Colour::Black => {
-                        self.black_has_right_to_castle_queenside = false;
-                        self.black_has_right_to_castle_kingside = false;
+                for side in [CastlingSide::Kingside, CastlingSide::Queenside] {
+                    let rank = castling::home_rank(self.active_colour);
+                    if self.castling_rights.can_castle(self.active_colour, side)
+                        && to_pos.idx == Position::idx(rank, side.king_file())
+                    {
+                        let rook_idx = Position::idx(rank, side.rook_file());
+                        self.board[Position::idx(rank, side.rook_target_file())] = self.board[rook_idx];
+                        self.board[rook_idx] = None;
                     }
                 }
+                self.castling_rights.remove_colour(self.active_colour);
             }
             PieceType::Rook => {
                 // If the rook moves, we need to disable castling for the correct colour and rook.
-                match from_pos.idx {
-                    // indices 0 = a1, 7 = h1, 56 = a8 and 63 = h8
-                    0 => {
-                        self.white_has_right_to_castle_queenside = false;
-                    }
-                    7 => {
-                        self.white_has_right_to_castle_kingside = false;
-                    }
-                    56 => {
-                        self.black_has_right_to_castle_queenside = false;
-                    }
-                    63 => {
-                        self.black_has_right_to_castle_kingside = false;
-                    }
-                    _ => {}
-                }
-            }
-            _default => {
-                // We also need to check if we capture either of the rooks at a1/h1/a8/h8,
-                // in which case we can no longer castle with them.
-                if captured_piece.is_some_and(|p| p.is_rook()) {
-                    match to_pos.idx {
-                        // indices 0 = a1, 7 = h1, 56 = a8 and 63 = h8
-                        0 => {
-                            self.white_has_right_to_castle_queenside = false;
-                        }
-                        7 => {
-                            self.white_has_right_to_castle_kingside = false;
-                        }
-                        56 => {
-                            self.black_has_right_to_castle_queenside = false;
-                        }
-                        63 => {
-                            self.black_has_right_to_castle_kingside = false;
-                        }
-                        _ => {}
-                    }
-                }
+                self.remove_castling_right_of_rook_at(from_pos);
             }
+            _default => {}
+        }
+        // We also need to check if we capture either of the rooks at a1/h1/a8/h8 (with any piece),
+        // in which case we can no longer castle with them.
+        if captured_piece.is_some_and(|p| p.is_rook()) {
+            self.remove_castling_right_of_rook_at(to_pos);
         }
         return Ok(());
     }
 
+    /// Disables the castling right of the rook that starts at `pos` (a1, h1, a8 or h8), if any.
+    fn remove_castling_right_of_rook_at(&mut self, pos: Position) {
+        for (colour, side) in castling::RIGHTS {
+            if pos.idx == Position::idx(castling::home_rank(colour), side.rook_file()) {
+                self.castling_rights.remove(colour, side);
+            }
+        }
+    }
+
     /// Updates the active colour and updates the game state for newly active colour.
     ///
     /// Is called when make_move is done.
@@ -1113,10 +2260,25 @@ impl Game {
 
         // Otherwise it is the next colour's turn
         self.active_colour = self.active_colour.invert();
+        self.position_keys.push(self.position_key());
+        // Record in the history whether the move gave check
+        let is_check = self.is_in_check(self.active_colour);
+        if let Some(entry) = self.history.last_mut() {
+            entry.is_check = is_check;
+            self.variations.record(entry.as_move());
+        }
+
+        self.evaluate_game_state();
+    }
 
+    /// Updates the game state for the active colour, without changing the active colour.
+    ///
+    /// Is called by `update_game_state` and when a game is loaded with `from_fen`.
+    fn evaluate_game_state(&mut self) {
         /* If the next thing to happen is not a promotion:
+        If a colour has won by the rules of the variant, it wins (GameOver).
         If the current game state has occurred 4 times before, enact the fivefold repetition rule (GameOver).
-        If the current game state is a case of insufficient material, declare the game a draw (GameOver).
+        If the current game state is a case of insufficient material or otherwise dead, declare the game a draw (GameOver).
         If the king is in check and no correcting move can be made, the game is in checkmate with (GameOver).
         If the king is in check and a correcting move can be made, the game is in check.
         If the king is not in check yet no move can be made, the game is in stalemate (GameOver).
@@ -1128,6 +2290,13 @@ impl Game {
         can be performed without putting the king at risk.
         */
 
+        // Wins by the rules of the variant.
+        if let Some(winner) = self.rules.winner(self) {
+            self.state = GameState::GameOver;
+            self.game_over_reason = Some(GameOverReason::VariantWin(winner));
+            return;
+        }
+
         // Fivefold repetition rule.
         if self.is_fivefold_repetition() {
             self.state = GameState::GameOver;
@@ -1135,57 +2304,23 @@ impl Game {
             return;
         }
 
-        // Insufficient material.
-        let remaining_pieces = self.board.iter().flatten();
-        let remaining_pieces_count = remaining_pieces.clone().count();
-        if remaining_pieces_count < 5 {
-            let mut king_count = 0;
-            let mut bishop_count = 0;
-            let mut knight_count = 0;
-            for piece in remaining_pieces {
-                match piece.piece_type {
-                    PieceType::King => king_count += 1,
-                    PieceType::Bishop => bishop_count += 1,
-                    PieceType::Knight => knight_count += 1,
-                    _ => {}
-                }
-            }
-            if remaining_pieces_count == 2 && king_count == 2 || // 2 kings (+ 1 bishop or 1 knight)
-                remaining_pieces_count == 3 && king_count == 2 && (bishop_count == 1 || knight_count == 1)
-            {
+        // Dead positions, where no sequence of moves can lead to checkmate.
+        if self.rules.insufficient_material_is_draw() {
+            if self.is_insufficient_material() {
                 self.state = GameState::GameOver;
                 self.game_over_reason = Some(GameOverReason::InsufficientMaterial);
                 return;
-            } else if remaining_pieces_count == 4 && king_count == 2 && bishop_count == 2 {
-                // 2 kings + 2 bishops on the same colour
-                let mut bishop_loc = 64;
-                for idx in 0..63 {
-                    if self.board[idx].is_some_and(|p| p.is_bishop()) {
-                        if bishop_loc == 64 {
-                            bishop_loc = idx;
-                        } else if bishop_loc % 2 == idx % 2 {
-                            self.state = GameState::GameOver;
-                            self.game_over_reason = Some(GameOverReason::InsufficientMaterial);
-                            return;
-                        }
-                    }
-                }
+            } else if self.is_blocked_pawn_wall() {
+                self.state = GameState::GameOver;
+                self.game_over_reason = Some(GameOverReason::DeadPosition);
+                return;
             }
         }
 
         // Check, checkmate, stalemate and in progress.
-        if self.is_in_check(self.active_colour, 1) {
-            // TODO why 1?
+        if self.is_in_check(self.active_colour) {
             if self._can_make_legal_move() {
                 self.state = GameState::Check;
-                // Also disable castling for active_colour.
-                if self.active_colour.is_white() {
-                    self.white_has_right_to_castle_queenside = false;
-                    self.white_has_right_to_castle_kingside = false;
-                } else {
-                    self.black_has_right_to_castle_queenside = false;
-                    self.black_has_right_to_castle_kingside = false;
-                }
             } else {
                 self.state = GameState::GameOver;
                 self.game_over_reason = Some(GameOverReason::Checkmate);
@@ -1209,32 +2344,18 @@ impl Game {
     /// Returns true if the `colour`'s king is checked, otherwise false.
     ///
     /// If `colour` has no king on the board, returns false.
+    fn is_in_check(&self, colour: Colour) -> bool {
+        return !self.checkers(colour).is_empty();
+    }
+
+    /// Returns the positions of the pieces that attack the `colour`'s king.
     ///
-    /// Note that this function calls `get_possible_moves()` again which calls this function.
-    /// To avoid infinite recursion, we pass the variable `recursion_order` which is incremented by `get_possible_moves`.
-    fn is_in_check(&self, colour: Colour, recursion_order: i32) -> bool {
-        let king_pos = match self.find_king(colour) {
-            Ok(pos) => pos,
-            Err(_) => return false,
+    /// Is empty if `colour` has no king on the board.
+    fn checkers(&self, colour: Colour) -> Vec<Position> {
+        return match self.find_king(colour) {
+            Ok(king_pos) => Game::attackers(&self.board, king_pos, colour.invert()),
+            Err(_) => vec![],
         };
-
-        // Iterate over pieces of the opposite colour and see if any attack the king.
-        for (i, piece) in self.board.iter().enumerate() {
-            if piece.is_some_and(|p| p.colour != colour) {
-                let possible_moves = self
-                    ._get_possible_moves(
-                        Position::new_from_idx(i).expect("enumerated"),
-                        recursion_order,
-                    )
-                    .expect("enumerated");
-                if possible_moves.iter().any(|pos| pos == &king_pos) {
-                    return true;
-                }
-            }
-        }
-
-        // If we have found no cases where the king is in check, the king is not in check.
-        return false;
     }
 
     /// Returns true if active colour can make any move, otherwise false.
@@ -1244,10 +2365,13 @@ impl Game {
     fn _can_make_legal_move(&self) -> bool {
         for (i, piece) in self.board.iter().enumerate() {
             if piece.is_some_and(|p| p.colour == self.active_colour) {
-                let possible_moves = self
-                    ._get_possible_moves(Position::new_from_idx(i).expect("enumerated"), 0)
-                    .expect("enumerated");
-                if possible_moves.len() > 0 {
+                let mut found = false;
+                self._for_each_possible_move(
+                    Position::new_from_idx(i).expect("enumerated"),
+                    &mut |_| found = true,
+                )
+                .expect("enumerated");
+                if found {
                     // We have found at least one possible move and return true
                     return true;
                 }
@@ -1261,41 +2385,76 @@ impl Game {
     /// Finds the king of `colour`'s position and returns it
     ///
     /// Errors if the king is not on the board
-    fn find_king(&self, colour: Colour) -> Result<Position, String> {
+    fn find_king(&self, colour: Colour) -> Result<Position, ChessError> {
         for (i, piece) in self.board.iter().enumerate() {
             if piece.is_some_and(|p| p.is_king() && p.colour == colour) {
-                return Ok(Position::new_from_idx(i)?);
+                return Position::new_from_idx(i);
             }
         }
-        return Err(format!("The {:?} king is not on the board", colour));
+        return Err(ChessError::MissingKing(colour));
     }
 
     /// Returns the position of the active colour's pawn that should be promoted.
     ///
     /// Errors if there is no pawn to promote.
-    fn find_pawn_to_promote(&self) -> Result<Position, String> {
+    fn find_pawn_to_promote(&self) -> Result<Position, ChessError> {
         let rank = match self.active_colour {
             // last rank for the pawn colour
             Colour::White => 7,
             Colour::Black => 0,
         };
-        for file in 0..7 {
+        for file in 0..8 {
             // all files for the rank
             if self
                 .get(Position::new(rank, file)?)?
                 .is_some_and(|p| p.is_pawn())
             {
                 // This engine will never end up in a situation where there are two panws on the last rank.
-                return Ok(Position::new(rank, file)?);
+                return Position::new(rank, file);
             }
         }
         // Otherwise there is none
-        return Err("There is no pawn to promote".to_owned());
+        return Err(ChessError::InvalidPromotion(
+            "There is no pawn to promote".to_owned(),
+        ));
+    }
+
+    /// Returns the pawn waiting to be promoted, with its position, its colour and the piece types it may be
+    /// promoted to, if the game is in the state `GameState::WaitingOnPromotionChoice`. Otherwise, returns None.
+    ///
+    /// The piece types are given by the rules of the game, see `variant::Rules::promotion_piece_types`,
+    /// and any of them is accepted by `set_promotion`.
+    ///
+    /// # Example code
+    ///
+    /// ```rust
+    /// use chess_engine::*;
+    ///
+    /// let mut game = Game::from_fen("4k3/1P6/8/8/8/8/8/4K3 w - - 0 1").unwrap();
+    /// assert_eq!(game.promotion_pending(), None);
+    /// game.make_move("b7", "b8").unwrap();
+    /// let pending = game.promotion_pending().unwrap();
+    /// assert_eq!(pending.position, Position::parse_str("b8").unwrap());
+    /// assert_eq!(pending.colour, Colour::White);
+    /// assert!(pending.piece_types.contains(&PieceType::Queen));
+    /// ```
+    pub fn promotion_pending(&self) -> Option<PendingPromotion> {
+        if self.state != GameState::WaitingOnPromotionChoice {
+            return None;
+        }
+        // The turn passes once the promotion is chosen, so the pawn is of the active colour
+        let position = self.find_pawn_to_promote().ok()?;
+        return Some(PendingPromotion {
+            position,
+            colour: self.active_colour,
+            piece_types: self.rules.promotion_piece_types(),
+        });
     }
 
     /// Set the piece type that a pawn becames following a promotion.
     ///
-    /// Errors if the type is a king or pawn, or if the game is not waiting for a promotion choice.
+    /// Errors if the type is a king or pawn or not allowed by the rules of the game (see `promotion_pending`),
+    /// or if the game is not waiting for a promotion choice.
     /// 
     /// # Example code
     /// 
@@ -1306,7 +2465,7 @@ impl Game {
     ///     /// ...
     ///     GameState::WaitingOnPromotionChoice => {
     ///         let input = /* text input */ "queen";
-    ///         let choice = PieceType::from_str(input);
+    ///         let choice = input.parse::<PieceType>();
     ///         /* or determine the choice in some other way */
     ///         assert!(choice.is_ok());
     ///         let result = game.set_promotion(choice.unwrap());
@@ -1315,19 +2474,34 @@ impl Game {
     ///     # _ => {}
     /// }
     /// ```
-    pub fn set_promotion(&mut self, piece_type: PieceType) -> Result<GameState, String> {
+    pub fn set_promotion(&mut self, piece_type: PieceType) -> Result<GameState, ChessError> {
         if self.state != GameState::WaitingOnPromotionChoice {
-            return Err(format!(
+            return Err(ChessError::InvalidPromotion(format!(
                 "The game is not currently waiting for a promotion. Currently, the state is {:?}.",
                 self.state
-            ));
+            )));
         }
 
         match piece_type {
-            PieceType::King => return Err("You can't promote a pawn to a king!".to_owned()),
-            PieceType::Pawn => return Err("You can't promote a pawn to a pawn!".to_owned()),
+            PieceType::King => {
+                return Err(ChessError::InvalidPromotion(
+                    "You can't promote a pawn to a king!".to_owned(),
+                ))
+            }
+            PieceType::Pawn => {
+                return Err(ChessError::InvalidPromotion(
+                    "You can't promote a pawn to a pawn!".to_owned(),
+                ))
+            }
             _ => {}
         };
+        if !self.rules.promotion_piece_types().contains(&piece_type) {
+            return Err(ChessError::InvalidPromotion(format!(
+                "You can't promote a pawn to a {:?} in {}!",
+                piece_type,
+                self.rules.name()
+            )));
+        }
 
         self.put(
             self.find_pawn_to_promote()?,
@@ -1336,58 +2510,507 @@ impl Game {
                 colour: self.active_colour,
             },
         )?;
+        if let Some(entry) = self.history.last_mut() {
+            entry.promotion = Some(piece_type);
+        }
 
-        self.active_colour = self.active_colour.invert();
-
+        // update_game_state() passes the turn to the next colour now that the pawn is promoted
         self.update_game_state();
+        #[cfg(feature = "debug-validate")]
+        self.assert_valid();
         return Ok(self.state);
     }
 
-    /// Get the current game state.
-    pub fn get_game_state(&self) -> GameState {
-        self.state
+    /// Get the current game state.
+    pub fn get_game_state(&self) -> GameState {
+        self.state
+    }
+
+    /// Get the game over reason. Is None if the game is not over.
+    pub fn get_game_over_reason(&self) -> Option<GameOverReason> {
+        self.game_over_reason
+    }
+
+    /// Get the colour that won the game, i.e. the colour that delivered checkmate.
+    ///
+    /// Is None if the game is not over or was drawn.
+    pub fn get_winner(&self) -> Option<Colour> {
+        // The checkmated colour is left as the active colour
+        return match self.game_over_reason {
+            Some(GameOverReason::Checkmate) => Some(self.active_colour.invert()),
+            Some(GameOverReason::VariantWin(winner)) => Some(winner),
+            Some(GameOverReason::Resignation(colour)) | Some(GameOverReason::Timeout(colour)) => {
+                Some(colour.invert())
+            }
+            _ => None,
+        };
+    }
+
+    /// Get the result of the game as written in PGN: `"1-0"` if white won, `"0-1"` if black won and `"1/2-1/2"` if drawn.
+    ///
+    /// Is None if the game is not over.
+    pub fn result_string(&self) -> Option<&'static str> {
+        if !self.is_gameover() {
+            return None;
+        }
+        return Some(match self.get_winner() {
+            Some(Colour::White) => "1-0",
+            Some(Colour::Black) => "0-1",
+            None => "1/2-1/2",
+        });
+    }
+
+    /// Get the castling rights of both colours.
+    pub fn castling_rights(&self) -> CastlingRights {
+        return self.castling_rights;
+    }
+
+    /// Get the position a pawn passed over by moving two steps in the last move.
+    ///
+    /// Is None if the last move was not a pawn moving two steps. Unlike in `fen()`, the position is given
+    /// even if no pawn can capture en passant there.
+    pub fn en_passant_square(&self) -> Option<Position> {
+        if self.en_passant_target == Position::NULL {
+            return None;
+        }
+        return Some(self.en_passant_target);
+    }
+
+    /// Get the number of halfmoves since the last capture or pawn move, used for the 50 and 75-move rules.
+    pub fn halfmove_clock(&self) -> u16 {
+        self.halfmoves
+    }
+
+    /// Get the number of the full move, starting at 1 and incremented after black's move.
+    pub fn fullmove_number(&self) -> u32 {
+        self.fullmoves
+    }
+
+    /// Get the number of halfmoves (plies) in the history, i.e. made since the start of the game or the FEN it was loaded from.
+    pub fn plies(&self) -> usize {
+        self.history.len()
+    }
+
+    /// Get the number of the current full move, e.g. for numbering the next move in a move list.
+    ///
+    /// Like `fullmove_number()`, this continues from the fullmove number of a FEN the game was loaded from,
+    /// and goes up after each move of black, whichever colour moved first.
+    pub fn move_number(&self) -> usize {
+        self.fullmoves as usize
+    }
+
+    /// Get the active colour.
+    pub fn get_active_colour(&self) -> Colour {
+        self.active_colour
+    }
+
+    /// Get a summary of the game's status in one call, e.g. to update a front-end after every move.
+    pub fn summary(&self) -> GameSummary {
+        return GameSummary {
+            state: self.state,
+            active_colour: self.active_colour,
+            is_check: self.is_in_check(self.active_colour),
+            game_over_reason: self.game_over_reason,
+            winner: self.get_winner(),
+            fullmove_number: self.fullmoves,
+            halfmove_clock: self.halfmoves,
+            repetitions: self.repetitions(),
+            can_claim_threefold_repetition: self.is_threefold_repetition(),
+            can_claim_50_move_rule: self.is_50_move_rule(),
+        };
+    }
+
+    /// Get a copy of the board as a vector of length 8 * 8 of `Option<Piece>`-s.
+    /// 
+    /// NOTE: Needs to be updated after every mutation of game!
+    /// 
+    /// # Example code
+    /// 
+    /// TODO Write doctest!
+    pub fn get_board(&self) -> [Option<Piece>; 8 * 8] {
+        return self.board;
+    }
+
+    /// Get a vector of contents `HistoryEntry` which denote the engine's recorded history for this game.
+    pub fn get_history(&self) -> Vec<HistoryEntry> {
+        return self.history.clone();
+    }
+
+    /// Set the move restrictions used in teaching mode. Pass None to allow every legal move again.
+    pub fn set_restrictions(&mut self, restrictions: Option<MoveRestrictions>) {
+        self.restrictions = restrictions;
+    }
+
+    /// Get the move restrictions used in teaching mode. Is None if the game is not restricted.
+    pub fn get_restrictions(&self) -> Option<&MoveRestrictions> {
+        return self.restrictions.as_ref();
+    }
+
+    /// Set the rules of the chess variant played, see `variant::Rules`. Standard chess is played unless this is called.
+    ///
+    /// The game state is updated for the new rules, so a position that the variant counts as won ends the game at once.
+    /// With the `serde` feature, the rules are not serialized, so a deserialized game plays standard chess.
+    pub fn set_rules(&mut self, rules: Arc<dyn Rules>) {
+        self.rules = rules;
+        if self.state == GameState::InProgress || self.state == GameState::Check {
+            self.evaluate_game_state();
+        }
+    }
+
+    /// Get the rules of the chess variant played.
+    pub fn get_rules(&self) -> &dyn Rules {
+        return self.rules.as_ref();
+    }
+
+    /// Get the most recent entry in the engine's recorded history. Is None if no move has been made.
+    ///
+    /// Unlike `get_history()`, this does not clone the history.
+    pub fn last_move(&self) -> Option<&HistoryEntry> {
+        return self.history.last();
+    }
+
+    /// Get the pieces each colour has captured so far, in the order they were captured, e.g. for a captured-pieces tray.
+    ///
+    /// Pawns captured en passant are included. A captured promoted piece is listed as the piece it was promoted to.
+    /// Moves that have been undone are not counted.
+    pub fn captured_pieces(&self) -> CapturedPieces {
+        return CapturedPieces {
+            by_white: self.captured_by(Colour::White),
+            by_black: self.captured_by(Colour::Black),
+        };
+    }
+
+    /// Get the pieces `colour` has captured so far, in the order they were captured, like `captured_pieces()`.
+    pub fn captured_by(&self, colour: Colour) -> Vec<Piece> {
+        return self
+            .history
+            .iter()
+            .filter(|entry| entry.piece_moved.colour == colour)
+            .filter_map(|entry| entry.piece_captured)
+            .collect();
+    }
+
+    /// Get the positions moved from and to in the most recent move. Is None if no move has been made.
+    ///
+    /// Useful for highlighting the previous move.
+    pub fn last_move_squares(&self) -> Option<(Position, Position)> {
+        let entry = self.last_move()?;
+        return Some((entry.from, entry.to));
+    }
+
+    /// Returns all possible new positions of the piece at position `pos` as a vector of positions.
+    ///
+    /// In teaching mode, moves that violate the restrictions are left out.
+    ///
+    /// Errors if `pos` is not valid.
+    pub fn get_possible_moves(&self, pos: Position) -> Result<Vec<Position>, ChessError> {
+        let possible_moves = self._get_possible_moves(pos)?;
+        return Ok(match (&self.restrictions, self.board[pos.idx]) {
+            (Some(restrictions), Some(piece)) => possible_moves
+                .into_iter()
+                .filter(|to_pos| restrictions.check(piece, *to_pos).is_ok())
+                .collect(),
+            _ => possible_moves,
+        });
+    }
+
+    /// Returns all possible new positions of the piece at position `pos` as if it were `colour_to_move`'s turn,
+    /// e.g. to analyse the threats of the colour that is not to move.
+    ///
+    /// Is empty if the piece is not of `colour_to_move`. When it is not the active colour, no en passant captures
+    /// can be made, since the en passant target was made by `colour_to_move` itself.
+    /// In teaching mode, moves that violate the restrictions are left out.
+    ///
+    /// Errors if `pos` is not valid.
+    pub fn get_possible_moves_for(
+        &self,
+        pos: Position,
+        colour_to_move: Colour,
+    ) -> Result<Vec<Position>, ChessError> {
+        if !self.get(pos)?.is_some_and(|p| p.colour == colour_to_move) {
+            return Ok(vec![]);
+        }
+        if colour_to_move == self.active_colour {
+            return self.get_possible_moves(pos);
+        }
+        let mut game = self.clone();
+        game.active_colour = colour_to_move;
+        game.en_passant_target = Position::NULL;
+        return game.get_possible_moves(pos);
+    }
+
+    /// Returns all possible moves of the piece at position `pos` as a vector of `Move`-s, with flags set.
+    ///
+    /// Promotions are expanded into one move per piece type that the pawn can be promoted to.
+    ///
+    /// In teaching mode, moves that violate the restrictions are left out.
+    ///
+    /// Errors if `pos` is not valid.
+    pub fn get_moves(&self, pos: Position) -> Result<Vec<Move>, ChessError> {
+        return self.get_possible_moves_with(
+            pos,
+            MoveGenOptions {
+                include_inactive_colour: true,
+                ..Default::default()
+            },
+        );
+    }
+
+    /// Returns the possible moves of the piece at position `pos` as a vector of `Move`-s, with flags set,
+    /// generated as controlled by `options` (see `MoveGenOptions`).
+    ///
+    /// In teaching mode, moves that violate the restrictions are left out.
+    ///
+    /// Errors if `pos` is not valid.
+    pub fn get_possible_moves_with(
+        &self,
+        pos: Position,
+        options: MoveGenOptions,
+    ) -> Result<Vec<Move>, ChessError> {
+        let mut moves = vec![];
+        self.for_each_move_with(pos, options, &mut |mv| moves.push(mv))?;
+        return Ok(moves);
+    }
+
+    /// Calls `add` with every possible move of the piece at position `pos`, like `get_possible_moves_with`,
+    /// but without collecting them.
+    fn for_each_move_with(
+        &self,
+        pos: Position,
+        options: MoveGenOptions,
+        add: &mut dyn FnMut(Move),
+    ) -> Result<(), ChessError> {
+        let piece = match self.get(pos)? {
+            None => return Ok(()),
+            Some(piece) => piece,
+        };
+        if !options.include_inactive_colour && piece.colour != self.active_colour {
+            return Ok(());
+        }
+
+        let mut result = Ok(());
+        self._for_each_possible_move(pos, &mut |to_pos| {
+            if let Some(restrictions) = &self.restrictions {
+                if restrictions.check(piece, to_pos).is_err() {
+                    return;
+                }
+            }
+            let is_capture = match self.is_capture(pos, to_pos) {
+                Ok(is_capture) => is_capture,
+                Err(err) => {
+                    result = Err(err);
+                    return;
+                }
+            };
+            let mv = Move {
+                is_capture,
+                is_en_passant: piece.is_pawn()
+                    && to_pos == self.en_passant_target
+                    && pos.file != to_pos.file,
+                is_castle: piece.is_king() && pos.file.abs_diff(to_pos.file) == 2,
+                ..Move::new(pos, to_pos)
+            };
+            if mv.is_castle && !options.include_castling {
+                return;
+            }
+            if options.expand_promotions
+                && piece.is_pawn()
+                && (to_pos.rank == 0 || to_pos.rank == 7)
+            {
+                for promotion in self.rules.promotion_piece_types() {
+                    add(Move {
+                        promotion: Some(promotion),
+                        ..mv
+                    });
+                }
+            } else {
+                add(mv);
+            }
+        })?;
+        return result;
+    }
+
+    /// Returns every possible move of the active colour as a vector of `Move`-s, with flags set.
+    ///
+    /// Promotions are expanded into one move per piece type that the pawn can be promoted to.
+    /// In teaching mode, moves that violate the restrictions are left out.
+    pub fn get_all_possible_moves(&self) -> Vec<Move> {
+        let mut moves = Vec::with_capacity(64);
+        self.generate_moves_into(&mut moves);
+        return moves;
+    }
+
+    /// Replaces the contents of `moves` with every possible move of the active colour, like `get_all_possible_moves()`.
+    ///
+    /// Nothing is allocated once `moves` has grown large enough, so hot loops such as a search
+    /// can reuse the same vector for every position.
+    pub fn generate_moves_into(&self, moves: &mut Vec<Move>) {
+        moves.clear();
+        for i in 0..64 {
+            if self.board[i].is_some_and(|p| p.colour == self.active_colour) {
+                let pos = Position::new_from_idx(i).expect("enumerated");
+                self.for_each_move_with(pos, MoveGenOptions::default(), &mut |mv| moves.push(mv))
+                    .expect("enumerated");
+            }
+        }
+    }
+
+    /// Returns a uniformly random possible move of the active colour, picked with `rng`, e.g. for random playouts
+    /// or a beginner opponent. Needs the `rand` feature.
+    ///
+    /// Every move of `get_all_possible_moves()` is equally likely, so a promotion to each piece counts as a move.
+    /// Is None if the game is over or waiting on a promotion choice.
+    ///
+    /// # Example code
+    ///
+    /// ```rust
+    /// use chess_engine::*;
+    /// use rand::SeedableRng;
+    ///
+    /// let mut rng = rand::rngs::StdRng::seed_from_u64(1);
+    /// let game = Game::new();
+    /// let mv = game.random_legal_move(&mut rng).unwrap();
+    /// assert!(game.get_all_possible_moves().contains(&mv));
+    /// ```
+    #[cfg(feature = "rand")]
+    pub fn random_legal_move(&self, rng: &mut impl rand::Rng) -> Option<Move> {
+        if self.state != GameState::InProgress && self.state != GameState::Check {
+            return None;
+        }
+        let moves = self.get_all_possible_moves();
+        if moves.is_empty() {
+            return None;
+        }
+        return Some(moves[rng.gen_range(0..moves.len())]);
     }
 
-    /// Get the game over reason. Is None if the game is not over.
-    pub fn get_game_over_reason(&self) -> Option<GameOverReason> {
-        self.game_over_reason
+    /// Returns the number of move sequences `depth` half-moves long from the position (perft),
+    /// which is compared to known counts to test the move generation. A depth of 0 gives 1.
+    ///
+    /// Promotions count once per piece promoted to, as in `get_all_possible_moves()`.
+    /// A line that ends the game, also by a draw such as insufficient material, is not continued,
+    /// so a finished game has no move sequences.
+    ///
+    /// # Example code
+    ///
+    /// ```rust
+    /// use chess_engine::*;
+    ///
+    /// assert_eq!(Game::new().perft(2), 400);
+    /// ```
+    pub fn perft(&self, depth: u32) -> u64 {
+        return self.search_copy().perft_in_place(depth);
     }
 
-    /// Get the active colour.
-    pub fn get_active_colour(&self) -> Colour {
-        self.active_colour
+    /// Returns the count of `perft`, making and taking back the moves on this game.
+    fn perft_in_place(&mut self, depth: u32) -> u64 {
+        if depth == 0 {
+            return 1;
+        }
+        if self.is_gameover() {
+            return 0;
+        }
+        let mut moves = Vec::with_capacity(64);
+        self.generate_moves_into(&mut moves);
+        if depth == 1 {
+            return moves.len() as u64;
+        }
+        let mut nodes = 0;
+        for mv in moves {
+            if self.play_move(mv).is_err() {
+                continue;
+            }
+            nodes += self.perft_in_place(depth - 1);
+            self.unmake_move();
+        }
+        return nodes;
     }
 
-    /// Get a copy of the board as a vector of length 8 * 8 of `Option<Piece>`-s.
-    /// 
-    /// NOTE: Needs to be updated after every mutation of game!
-    /// 
+    /// Returns the same count as `perft`, but counts the move sequences after each move of the position on its own
+    /// thread with rayon, with the `parallel` feature.
+    ///
     /// # Example code
-    /// 
-    /// TODO Write doctest!
-    pub fn get_board(&self) -> [Option<Piece>; 8 * 8] {
-        return self.board.clone();
+    ///
+    /// ```rust
+    /// use chess_engine::*;
+    ///
+    /// assert_eq!(Game::new().perft_parallel(3), 8902);
+    /// ```
+    #[cfg(feature = "parallel")]
+    pub fn perft_parallel(&self, depth: u32) -> u64 {
+        use rayon::prelude::*;
+
+        if depth <= 1 || self.is_gameover() {
+            return self.perft(depth);
+        }
+        let mut moves = Vec::with_capacity(64);
+        self.generate_moves_into(&mut moves);
+        return moves
+            .into_par_iter()
+            .map(|mv| {
+                let mut child = self.search_copy();
+                if child.play_move(mv).is_err() {
+                    return 0;
+                }
+                return child.perft_in_place(depth - 1);
+            })
+            .sum();
     }
 
-    /// Get a vector of contents `HistoryEntry` which denote the engine's recorded history for this game.
-    pub fn get_history(&self) -> Vec<HistoryEntry> {
-        return self.history.clone();
+    /// Returns an iterator over every possible move of the active colour, in the order of `get_all_possible_moves()`.
+    ///
+    /// The moves are generated lazily, one piece at a time, and nothing is allocated.
+    /// E.g. finding whether any move captures stops generating at the first capture.
+    ///
+    /// # Example code
+    ///
+    /// ```rust
+    /// use chess_engine::*;
+    ///
+    /// let game = Game::new();
+    /// assert_eq!(game.legal_moves().count(), 20);
+    /// assert!(!game.legal_moves().any(|mv| mv.is_capture));
+    /// ```
+    pub fn legal_moves(&self) -> impl Iterator<Item = Move> + '_ {
+        return LegalMoves {
+            game: self,
+            next_idx: 0,
+            buffer: [Move::new(Position::NULL, Position::NULL); 27],
+            len: 0,
+            returned: 0,
+        };
     }
 
-    /// Returns all possible new positions of the piece at position `pos` as a vector of positions.
+    /// Returns all positions the piece at position `pos` can move to, each flagged as a capture, promotion or castling.
+    ///
+    /// Unlike calling both `get_possible_capture_moves` and `get_possible_non_capture_moves`,
+    /// the moves are only generated once.
     ///
     /// Errors if `pos` is not valid.
-    pub fn get_possible_moves(&self, pos: Position) -> Result<Vec<Position>, String> {
-        // This method just relays the position to _get_possible_moves with recursion_order 0.
-        return self._get_possible_moves(pos, 0);
+    pub fn destinations(&self, pos: Position) -> Result<Vec<Destination>, ChessError> {
+        let piece = match self.get(pos)? {
+            None => return Ok(vec![]),
+            Some(piece) => piece,
+        };
+
+        let mut destinations = vec![];
+        for to_pos in self.get_possible_moves(pos)? {
+            destinations.push(Destination {
+                to: to_pos,
+                is_capture: self.is_capture(pos, to_pos)?,
+                is_promotion: piece.is_pawn() && (to_pos.rank == 0 || to_pos.rank == 7),
+                is_castle: piece.is_king() && pos.file.abs_diff(to_pos.file) == 2,
+            });
+        }
+        return Ok(destinations);
     }
 
     /// Returns all possible new positions of the piece at position `pos`, that also capture a piece, as a vector of positions.
     ///
     /// Errors if `pos` is not valid.
-    pub fn get_possible_capture_moves(&self, pos: Position) -> Result<Vec<Position>, String> {
+    pub fn get_possible_capture_moves(&self, pos: Position) -> Result<Vec<Position>, ChessError> {
         return Ok(self
-            ._get_possible_moves(pos, 0)?
+            .get_possible_moves(pos)?
             .into_iter()
             .filter(|to_pos| self.is_capture(pos, *to_pos).expect("pos is ok"))
             .collect());
@@ -1396,9 +3019,12 @@ impl Game {
     /// Returns all possible new positions of the piece at position `pos`, that also do not capture a piece, as a vector of positions.
     ///
     /// Errors if `pos` is not valid.
-    pub fn get_possible_non_capture_moves(&self, pos: Position) -> Result<Vec<Position>, String> {
+    pub fn get_possible_non_capture_moves(
+        &self,
+        pos: Position,
+    ) -> Result<Vec<Position>, ChessError> {
         return Ok(self
-            ._get_possible_moves(pos, 0)?
+            .get_possible_moves(pos)?
             .into_iter()
             .filter(|to_pos| !self.is_capture(pos, *to_pos).expect("pos is ok"))
             .collect());
@@ -1406,26 +3032,34 @@ impl Game {
 
     /// If a piece is standing on the given tile, this method returns all possible new positions of that piece.
     ///
-    /// Takes the arguments `pos` of type Position and `recursion_order`. Put `recursion_order` to 0 if you do not know what you are doing.
-    /// `recursion_order` is an auxiliary variable that prevents the function from checking for potential Check-states further in the future than MAX_RECURSIONS.
-    fn _get_possible_moves(
+    /// Moves that put the piece's own king in check are left out, see `try_move`.
+    fn _get_possible_moves(&self, pos: Position) -> Result<Vec<Position>, ChessError> {
+        let mut possible_moves: Vec<Position> = Vec::with_capacity(60);
+        self._for_each_possible_move(pos, &mut |to_pos| possible_moves.push(to_pos))?;
+        return Ok(possible_moves);
+    }
+
+    /// Calls `add` with every possible new position of the piece standing on the given tile, like `_get_possible_moves`,
+    /// but without collecting them, so that the move generators can be run without allocating.
+    fn _for_each_possible_move(
         &self,
         pos: Position,
-        mut recursion_order: i32,
-    ) -> Result<Vec<Position>, String> {
+        add: &mut dyn FnMut(Position),
+    ) -> Result<(), ChessError> {
         pos.valid()?;
 
-        // Increment recursion_order. See docstring for details.
-        recursion_order += 1;
-
-        // Get piece. If it is None, it cannot move so return an empty vector.
+        // Get piece. If it is None, it cannot move so there is nothing to add.
         let piece: Piece = match self.get(pos)? {
-            None => return Ok(vec![]),
+            None => return Ok(()),
             Some(piece) => piece,
         };
 
-        // Start listing possible moves.
-        let mut possible_moves: Vec<Position> = Vec::with_capacity(60);
+        // Start listing possible moves, leaving out the moves that the variant does not allow.
+        let mut add = |to_pos: Position| {
+            if self.rules.allows_move(self, pos, to_pos) {
+                add(to_pos);
+            }
+        };
 
         // For each piece_type, follow some set of rules.
         /* This function declares how pieces can move, `try_move` tries if the piece can move somewhere.
@@ -1454,18 +3088,25 @@ impl Game {
                     (-1, 0),
                     (-1, -1),
                 ] {
-                    if self.try_move(pos, rank_step, file_step, 1, recursion_order) {
-                        possible_moves.push(pos.offset(rank_step, file_step)?);
+                    if self.try_move(pos, rank_step, file_step, 1) {
+                        add(pos.offset(rank_step, file_step)?);
                     }
                 }
 
                 // Castling.
                 // (One case per castling opportunity, since they have hardcoded positioning.)
+                // A king in check cannot castle.
+                // The castling rights are not updated when the board is edited directly with `put` and `remove`,
+                // so check that the king is in place too.
+                let can_castle = (pos.idx == 4 || pos.idx == 60) && !self.is_in_check(piece.colour);
                 match piece.colour {
-                    Colour::White => {
+                    Colour::White if can_castle => {
                         let king_pos = Position::new(0, 4).unwrap();
-                        if self.white_has_right_to_castle_queenside {
-                            // Boolean is true iff the king is at e1 and the rook is at a1.
+                        if self
+                            .castling_rights
+                            .can_castle(Colour::White, CastlingSide::Queenside)
+                        {
+                            // The right is kept only while the king is at e1 and the rook is at a1.
                             // Check if b1 [idx 1], c1 [idx 2], and d1 [idx 3] are free.
                             if self.board[1].is_none()
                                 && self.board[2].is_none()
@@ -1474,36 +3115,42 @@ impl Game {
                                 // In that case check if the king is checked on the way to castling at c1.
                                 let mut ok = true;
                                 for i in 1..=2 {
-                                    if !self.try_move(king_pos, 0, -i, 1, recursion_order) {
+                                    if !self.try_move(king_pos, 0, -i, 1) {
                                         ok = false;
                                     }
                                 }
                                 if ok {
-                                    possible_moves.push(Position::new(0, 2).unwrap());
+                                    add(Position::new(0, 2).unwrap());
                                 }
                             }
                         }
-                        if self.white_has_right_to_castle_kingside {
-                            // Boolean is true iff the king is at e1 and the rook is at h1.
+                        if self
+                            .castling_rights
+                            .can_castle(Colour::White, CastlingSide::Kingside)
+                        {
+                            // The right is kept only while the king is at e1 and the rook is at h1.
                             // Check if f1 [idx 5] and g1 [idx 6] are free.
                             if self.board[5].is_none() && self.board[6].is_none() {
                                 // In that case check if the king is checked on the way to castling at g1.
                                 let mut ok = true;
                                 for i in 1..=2 {
-                                    if !self.try_move(king_pos, 0, i, 1, recursion_order) {
+                                    if !self.try_move(king_pos, 0, i, 1) {
                                         ok = false;
                                     }
                                 }
                                 if ok {
-                                    possible_moves.push(Position::new(0, 6).unwrap());
+                                    add(Position::new(0, 6).unwrap());
                                 }
                             }
                         }
                     }
-                    Colour::Black => {
+                    Colour::Black if can_castle => {
                         let king_pos = Position::new(7, 4).unwrap();
-                        if self.black_has_right_to_castle_queenside {
-                            // Boolean is true iff the king is at e8 and the rook is at a8.
+                        if self
+                            .castling_rights
+                            .can_castle(Colour::Black, CastlingSide::Queenside)
+                        {
+                            // The right is kept only while the king is at e8 and the rook is at a8.
                             // Check if b8 [idx 57], c8 [idx 58] and d8 [idx 59] are free.
                             if self.board[57].is_none()
                                 && self.board[58].is_none()
@@ -1511,32 +3158,36 @@ impl Game {
                             {
                                 let mut ok = true;
                                 for i in 1..=2 {
-                                    if !self.try_move(king_pos, 0, -i, 1, recursion_order) {
+                                    if !self.try_move(king_pos, 0, -i, 1) {
                                         ok = false;
                                     }
                                 }
                                 if ok {
-                                    possible_moves.push(Position::new(7, 2).unwrap());
+                                    add(Position::new(7, 2).unwrap());
                                 }
                             }
                         }
-                        if self.black_has_right_to_castle_kingside {
-                            // Boolean is true iff the king is at d8 and the rook is at h8.
+                        if self
+                            .castling_rights
+                            .can_castle(Colour::Black, CastlingSide::Kingside)
+                        {
+                            // The right is kept only while the king is at e8 and the rook is at h8.
                             // Check if f8 [idx 61] and g8 [idx 62] are free.
                             if self.board[61].is_none() && self.board[62].is_none() {
                                 // In that case check if the king is checked on the way to castling at g8.
                                 let mut ok = true;
                                 for i in 1..=2 {
-                                    if !self.try_move(king_pos, 0, i, 1, recursion_order) {
+                                    if !self.try_move(king_pos, 0, i, 1) {
                                         ok = false;
                                     }
                                 }
                                 if ok {
-                                    possible_moves.push(Position::new(7, 6).unwrap());
+                                    add(Position::new(7, 6).unwrap());
                                 }
                             }
                         }
                     }
+                    _ => {}
                 }
             }
             PieceType::Queen => {
@@ -1552,9 +3203,15 @@ impl Game {
                     (-1, -1),
                 ] {
                     for steps in 1..8 {
-                        if self.try_move(pos, rank_step, file_step, steps, recursion_order) {
-                            possible_moves.push(pos.offset(rank_step * steps, file_step * steps)?)
-                        } else {
+                        let to_pos = match pos.offset(rank_step * steps, file_step * steps) {
+                            Ok(to_pos) => to_pos,
+                            Err(_) => break, // outside board
+                        };
+                        if self.try_move(pos, rank_step, file_step, steps) {
+                            add(to_pos);
+                        }
+                        if self.board[to_pos.idx].is_some() {
+                            // A move can be illegal only because of check, so only a piece ends the ray
                             break;
                         }
                     }
@@ -1564,9 +3221,15 @@ impl Game {
                 // Bishops can move all diagonal directions and however far they like. (The board is size 8.)
                 for (rank_step, file_step) in [(1, 1), (1, -1), (-1, 1), (-1, -1)] {
                     for steps in 1..8 {
-                        if self.try_move(pos, rank_step, file_step, steps, recursion_order) {
-                            possible_moves.push(pos.offset(rank_step * steps, file_step * steps)?)
-                        } else {
+                        let to_pos = match pos.offset(rank_step * steps, file_step * steps) {
+                            Ok(to_pos) => to_pos,
+                            Err(_) => break, // outside board
+                        };
+                        if self.try_move(pos, rank_step, file_step, steps) {
+                            add(to_pos);
+                        }
+                        if self.board[to_pos.idx].is_some() {
+                            // A move can be illegal only because of check, so only a piece ends the ray
                             break;
                         }
                     }
@@ -1584,8 +3247,8 @@ impl Game {
                     (-2, 1),
                     (-2, -1),
                 ] {
-                    if self.try_move(pos, rank_step, file_step, 1, recursion_order) {
-                        possible_moves.push(pos.offset(rank_step, file_step)?);
+                    if self.try_move(pos, rank_step, file_step, 1) {
+                        add(pos.offset(rank_step, file_step)?);
                     }
                 }
             }
@@ -1593,9 +3256,15 @@ impl Game {
                 // Rooks can move all non-diagonal directions and however far they like. (The board is size 8.)
                 for (rank_step, file_step) in [(1, 0), (0, 1), (0, -1), (-1, 0)] {
                     for steps in 1..8 {
-                        if self.try_move(pos, rank_step, file_step, steps, recursion_order) {
-                            possible_moves.push(pos.offset(rank_step * steps, file_step * steps)?)
-                        } else {
+                        let to_pos = match pos.offset(rank_step * steps, file_step * steps) {
+                            Ok(to_pos) => to_pos,
+                            Err(_) => break, // outside board
+                        };
+                        if self.try_move(pos, rank_step, file_step, steps) {
+                            add(to_pos);
+                        }
+                        if self.board[to_pos.idx].is_some() {
+                            // A move can be illegal only because of check, so only a piece ends the ray
                             break;
                         }
                     }
@@ -1610,11 +3279,11 @@ impl Game {
 
                 // forward direction
                 for i in 1..=2 {
-                    if self.try_move(pos, dir, 0, i, recursion_order) {
+                    if self.try_move(pos, dir, 0, i) {
                         let new_pos = pos.offset(dir * i, 0)?;
                         if !self.is_capture(pos, new_pos)? {
                             // pawns cannot capture forwards
-                            possible_moves.push(new_pos);
+                            add(new_pos);
                         }
                     }
                     if !is_on_first_rank {
@@ -1624,37 +3293,27 @@ impl Game {
 
                 // diagonal direction
                 for i in [-1, 1] {
-                    if self.try_move(pos, dir, i, 1, recursion_order) {
+                    if self.try_move(pos, dir, i, 1) {
                         let new_pos = pos.offset(dir, i)?;
                         if self.is_capture(pos, new_pos)? {
                             // pawns must capture diagonally (en passant included in this check)
-                            possible_moves.push(new_pos);
+                            add(new_pos);
                         }
                     }
                 }
             }
         }
-        return Ok(possible_moves);
+        return Ok(());
     }
 
     /// Tries to offset (move) a piece at `from_pos` by `(rank_step, file_step)*steps`.
     ///
     /// Returns true if the move is not obstructed and does not put the king in check.
     ///
-    /// Takes as input `recursion_order` too, which is an integer describing which order in the recursion this iteration of try_move is.
-    /// If the iteration is higher than MAX_RECURSIONS, this function will not check whether a move implies putting the king in check.
-    ///
     /// # Panics
     ///
     /// Panics if `from_pos` is not the position of a piece
-    fn try_move(
-        &self,
-        from_pos: Position,
-        rank_step: i32,
-        file_step: i32,
-        steps: i32,
-        recursion_order: i32,
-    ) -> bool {
+    fn try_move(&self, from_pos: Position, rank_step: i32, file_step: i32, steps: i32) -> bool {
         if from_pos.valid().is_err() {
             panic!("try_move was called from an invalid from_pos");
         }
@@ -1667,11 +3326,10 @@ impl Game {
 
         // Generate new position and check if it is reachable (not obstructed).
         // If the position captures a piece on its last step, the position is reachable.
-        let mut to_pos = from_pos.clone();
+        let mut to_pos = from_pos;
         for i in 1..=steps {
-            match to_pos.offset_self(rank_step, file_step) {
-                Err(_) => return false, // outside board
-                _ => {}
+            if to_pos.offset_self(rank_step, file_step).is_err() {
+                return false; // outside board
             }
             match self.get(to_pos).expect("pos is ok") {
                 Some(attacked_piece) => {
@@ -1690,24 +3348,53 @@ impl Game {
             }
         } // If we exit the for-loop, to_pos is reachable.
 
-        // If a move is found to move to a space, this function will check whether the move puts the own king in check by calling _is_check on the new board.
-        // This step is skipped if the recursion order is greater than MAX_RECURSIONS.
-
-        if recursion_order >= Game::MAX_RECURSIONS {
-            // We do not care if the position puts the king in check
-            return true;
-        }
+        return self.leaves_king_safe(from_pos, to_pos);
+    }
 
-        // Clone into a new game to try the movement in that game
-        let mut game_clone = self.clone();
-        match game_clone._perfom_move(from_pos, to_pos) {
-            // does not update active_colour
-            Ok(_) => {}
-            Err(_) => return false,
+    /// Returns true if moving the piece at `from_pos` to `to_pos` does not put its own king in check.
+    ///
+    /// The move is tried on a copy of the board only, which is cheap compared to the rest of the game.
+    pub(crate) fn leaves_king_safe(&self, from_pos: Position, to_pos: Position) -> bool {
+        let colour = match self.board[from_pos.idx] {
+            Some(piece) => piece.colour,
+            None => return true,
+        };
+        let board = self.board_after_move(from_pos, to_pos);
+        let king_pos = match board
+            .iter()
+            .position(|p| p.is_some_and(|p| p.is_king() && p.colour == colour))
+        {
+            Some(idx) => Position::new_from_idx(idx).expect("enumerated"),
+            None => return true, // no king to put in check
         };
-        game_clone.active_colour = game_clone.active_colour.invert();
-        return !game_clone.is_in_check(game_clone.active_colour.invert(), recursion_order);
-        // the move is valid if it does not put the king in check
+        return Game::attackers(&board, king_pos, colour.invert()).is_empty();
+    }
+
+    /// Returns a copy of the board where the piece at `from_pos` has been moved to `to_pos`.
+    ///
+    /// Removes an en passant-ed pawn, and moves the rook in the event of a castle, like `_perfom_move`,
+    /// but leaves the game itself untouched. Used by `leaves_king_safe` to check whether a move puts the king in check.
+    fn board_after_move(&self, from_pos: Position, to_pos: Position) -> [Option<Piece>; 8 * 8] {
+        let mut board = self.board;
+        let moved_piece = board[from_pos.idx];
+        board[from_pos.idx] = None;
+        board[to_pos.idx] = moved_piece;
+
+        if moved_piece.is_some_and(|p| p.is_pawn())
+            && to_pos == self.en_passant_target
+            && from_pos.file != to_pos.file
+        {
+            // The captured pawn stands beside the moved pawn
+            board[Position::idx(from_pos.rank, to_pos.file)] = None;
+        }
+        if moved_piece.is_some_and(|p| p.is_king()) && from_pos.file.abs_diff(to_pos.file) == 2 {
+            // The rook jumps over the king
+            let (rook_file, new_rook_file) = if to_pos.file == 6 { (7, 5) } else { (0, 3) };
+            board[Position::idx(to_pos.rank, new_rook_file)] =
+                board[Position::idx(to_pos.rank, rook_file)];
+            board[Position::idx(to_pos.rank, rook_file)] = None;
+        }
+        return board;
     }
 
     /// Returns true if a move from `from_pos` to `to_pos` captures a piece, otherwise false.
@@ -1715,16 +3402,24 @@ impl Game {
     /// Does not care if the move is valid.
     ///
     /// Checks the en passant case, too.
-    fn is_capture(&self, from_pos: Position, to_pos: Position) -> Result<bool, String> {
+    fn is_capture(&self, from_pos: Position, to_pos: Position) -> Result<bool, ChessError> {
         let p1 = match self.get(from_pos)? {
             Some(piece) => piece,
-            None => return Err("There is no piece at from_pos".to_owned()),
+            None => {
+                return Err(ChessError::InvalidPosition(
+                    "There is no piece at from_pos".to_owned(),
+                ))
+            }
         };
         let p2 = match self.get(to_pos)? {
             Some(piece) => piece,
             None => {
                 // The move is moving into an empty space, check if it is en passant
-                if to_pos == self.en_passant_target && p1.is_pawn() {
+                // (which only the active colour can capture, the other colour's pawn just moved past it)
+                if to_pos == self.en_passant_target
+                    && p1.is_pawn()
+                    && p1.colour == self.active_colour
+                {
                     return Ok(true); // en passant
                 }
                 return Ok(false); // not a capture
@@ -1737,49 +3432,212 @@ impl Game {
     }
 }
 
-/// Implement print routine for Game.
-///
-/// Output example:
-/// |:-------------:|
-/// |R N B Q K B N R|
-/// |P P P P P P P P|
-/// |* * * * * * * *|
-/// |* * * * * * * *|
-/// |* * * * * * * *|
-/// |* * * * * * * *|
-/// |p p p p p p p p|
-/// |r n b q k b n r|
-/// |:-------------:|,
-///
-impl fmt::Display for Game {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+/// The styles in which `Game::format_board` can output the board.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum BoardStyle {
+    /// The style used by `Display`. White pieces are uppercase, black pieces lowercase and empty squares `*`.
+    ///
+    /// Output example:
+    /// |:-------------:|
+    /// |r n b q k b n r|
+    /// |p p p p p p p p|
+    /// |* * * * * * * *|
+    /// |* * * * * * * *|
+    /// |* * * * * * * *|
+    /// |* * * * * * * *|
+    /// |P P P P P P P P|
+    /// |R N B Q K B N R|
+    /// |:-------------:|
+    Ascii,
+    /// Unicode braille cells for braille displays, following braille chess notation conventions.
+    ///
+    /// Every square is two cells: a colour prefix followed by the piece letter.
+    /// White pieces are prefixed by the capital sign ⠠ and black pieces by a blank cell ⠀,
+    /// so that, as in FEN, white pieces read as uppercase letters and black pieces as lowercase.
+    /// The letters are k ⠅, q ⠟, r ⠗, b ⠃, n ⠝ and p ⠏.
+    /// Empty light squares are ⠀⠀ and empty dark squares are ⠀⠤ (dots 3-6).
+    ///
+    /// Ranks are printed from 8 down to 1, one per line, each line starting with the rank number.
+    Braille,
+}
+
+/// Returns the braille cell for the letter that represents `piece_type`.
+fn braille_piece(piece_type: PieceType) -> char {
+    return match piece_type {
+        PieceType::King => '⠅',
+        PieceType::Queen => '⠟',
+        PieceType::Rook => '⠗',
+        PieceType::Bishop => '⠃',
+        PieceType::Knight => '⠝',
+        PieceType::Pawn => '⠏',
+    };
+}
+
+impl Game {
+    /// Returns the board as a String in the given `style`. See `BoardStyle` for the available styles.
+    pub fn format_board(&self, style: BoardStyle) -> String {
+        return self.format_board_from(style, Colour::White);
+    }
+
+    /// Returns the board as a String in the given `style`, seen from `perspective`'s side.
+    ///
+    /// For black, the board is turned around: rank 1 is printed first and the files run from h to a,
+    /// e.g. for the black player in a hot-seat game. See `format_board`.
+    pub fn format_board_from(&self, style: BoardStyle, perspective: Colour) -> String {
         // init output, the string we'll be coding our format to
         let mut output = String::new();
 
-        // start with the top rank
-        output.push_str("|:-------------:|\n");
+        // the ranks top to bottom and the files left to right, as seen from perspective
+        let (ranks, files): (Vec<usize>, Vec<usize>) = match perspective {
+            Colour::White => ((0..8).rev().collect(), (0..8).collect()),
+            Colour::Black => ((0..8).collect(), (0..8).rev().collect()),
+        };
+
+        match style {
+            BoardStyle::Ascii => {
+                // start with the top rank
+                output.push_str("|:-------------:|\n");
+
+                // for every Option<piece> in board, print a representation.
+                // Also, for every beginning of a rank i % 8 == 0 and end of a rank i & 8 == 7 add corresponding slices.
+                for rank in ranks.iter().copied() {
+                    output.push('|');
+                    for (i, file) in files.iter().copied().enumerate() {
+                        output.push(match self.board[Position::idx(rank, file)] {
+                            Some(p) => p.to_char_colourcased(),
+                            None => '*',
+                        });
+
+                        if i < 7 {
+                            output.push(' ');
+                        }
+                    }
+                    output.push_str("|\n");
+                }
+
+                // end with the bottom rank
+                output.push_str("|:-------------:|");
+            }
+            BoardStyle::Braille => {
+                // braille digits are the letters a-j preceded by the number sign
+                const DIGITS: [char; 8] = ['⠁', '⠃', '⠉', '⠙', '⠑', '⠋', '⠛', '⠓'];
+                for rank in ranks.iter().copied() {
+                    output.push('⠼'); // number sign
+                    output.push(DIGITS[rank]);
+                    output.push('⠀');
+                    for file in files.iter().copied() {
+                        match self.board[Position::idx(rank, file)] {
+                            Some(p) => {
+                                output.push(if p.is_white() { '⠠' } else { '⠀' });
+                                output.push(braille_piece(p.piece_type));
+                            }
+                            None => {
+                                output.push('⠀');
+                                // a1 (rank + file even) is a dark square
+                                output.push(if (rank + file) % 2 == 0 { '⠤' } else { '⠀' });
+                            }
+                        }
+                    }
+                    if rank != ranks[7] {
+                        output.push('\n');
+                    }
+                }
+            }
+        }
+
+        return output;
+    }
+}
 
-        // for every Option<piece> in board, print a representation.
-        // Also, for every beginning of a rank i % 8 == 0 and end of a rank i & 8 == 7 add corresponding slices.
+impl Game {
+    /// Returns the board as a plain grid of 8 lines with 8 characters each, one character per square,
+    /// from rank 8 down to rank 1 and from file a to h.
+    ///
+    /// White pieces are uppercase, black pieces lowercase (as in FEN) and empty squares `.`.
+    /// Unlike `format_board`, there are no decorations, so boards can be compared by diff tools or golden files.
+    /// Read the grid back with `Game::parse_grid`.
+    ///
+    /// Output example:
+    /// rnbqkbnr
+    /// pppppppp
+    /// ........
+    /// ........
+    /// ........
+    /// ........
+    /// PPPPPPPP
+    /// RNBQKBNR
+    pub fn to_grid_string(&self) -> String {
+        let mut output = String::new();
         for rank in (0..8).rev() {
-            output.push('|');
             for file in 0..8 {
                 output.push(match self.board[Position::idx(rank, file)] {
                     Some(p) => p.to_char_colourcased(),
-                    None => '*',
+                    None => '.',
                 });
+            }
+            if rank != 0 {
+                output.push('\n');
+            }
+        }
+        return output;
+    }
+
+    /// Parses a board written as by `to_grid_string` into an array like the one returned by `get_board`.
+    ///
+    /// Whitespace around the lines is ignored.
+    ///
+    /// Errors if the grid does not have 8 lines of 8 characters, or some character is neither `.` nor a piece.
+    pub fn parse_grid(grid: &str) -> Result<[Option<Piece>; 8 * 8], ChessError> {
+        let lines: Vec<&str> = grid.trim().lines().map(|line| line.trim()).collect();
+        if lines.len() != 8 {
+            return Err(ChessError::InvalidGrid(format!(
+                "The grid should have 8 lines, but has {}",
+                lines.len()
+            )));
+        }
 
-                if file < 7 {
-                    output.push(' ');
+        let mut board = [None; 8 * 8];
+        for (i, line) in lines.iter().enumerate() {
+            let rank = 7 - i; // the grid starts at rank 8
+            if line.chars().count() != 8 {
+                return Err(ChessError::InvalidGrid(format!(
+                    "The grid line '{}' should have 8 characters",
+                    line
+                )));
+            }
+            for (file, ch) in line.chars().enumerate() {
+                if ch == '.' {
+                    continue;
+                }
+                if !ch.is_ascii_alphabetic() {
+                    return Err(ChessError::InvalidGrid(format!(
+                        "'{}' does not represent a piece",
+                        ch
+                    )));
                 }
+                board[Position::idx(rank, file)] = Some(Piece {
+                    piece_type: PieceType::from_char(ch)
+                        .map_err(|err| ChessError::InvalidGrid(err.to_string()))?,
+                    colour: if ch.is_ascii_uppercase() {
+                        Colour::White
+                    } else {
+                        Colour::Black
+                    },
+                });
             }
-            output.push_str("|\n");
         }
+        return Ok(board);
+    }
+}
 
-        // end with the bottom rank
-        output.push_str("|:-------------:|");
-
-        write!(f, "{}", output)
+/// Implement print routine for Game.
+///
+/// Prints the board in the style `BoardStyle::Ascii` from white's side,
+/// or with the alternate flag (`{:#}`) from black's side, see `Game::format_board_from`.
+impl fmt::Display for Game {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let perspective = if f.alternate() { Colour::Black } else { Colour::White };
+        write!(f, "{}", self.format_board_from(BoardStyle::Ascii, perspective))
     }
 }
 
@@ -1790,6 +3648,228 @@ impl fmt::Display for Colour {
     }
 }
 
+/// Prints the state in lowercase English, e.g. "in progress" or "waiting on a promotion choice".
+impl fmt::Display for GameState {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                GameState::InProgress => "in progress",
+                GameState::Check => "check",
+                GameState::WaitingOnPromotionChoice => "waiting on a promotion choice",
+                GameState::GameOver => "game over",
+            }
+        )
+    }
+}
+
+/// Prints how the game ended in lowercase English, to follow e.g. "The game is over by",
+/// such as "checkmate" or "the resignation of White".
+impl fmt::Display for GameOverReason {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        return match self {
+            GameOverReason::Checkmate => write!(f, "checkmate"),
+            GameOverReason::Stalemate => write!(f, "stalemate"),
+            GameOverReason::SeventyFiveMoveRule => write!(f, "the 75-move rule"),
+            GameOverReason::FivefoldRepetitionRule => write!(f, "fivefold repetition"),
+            GameOverReason::InsufficientMaterial => write!(f, "insufficient material"),
+            GameOverReason::DeadPosition => write!(f, "a dead position"),
+            GameOverReason::ManualDraw => write!(f, "a draw"),
+            GameOverReason::DrawByAgreement => write!(f, "a draw by agreement"),
+            GameOverReason::FiftyMoveRule => write!(f, "the 50-move rule"),
+            GameOverReason::ThreefoldRepetition => write!(f, "threefold repetition"),
+            GameOverReason::VariantWin(colour) => {
+                write!(f, "a win of {} by the rules of the variant", colour)
+            }
+            GameOverReason::Resignation(colour) => write!(f, "the resignation of {}", colour),
+            GameOverReason::Timeout(colour) => write!(f, "{} running out of time", colour),
+            GameOverReason::TimeoutDraw(colour) => write!(
+                f,
+                "{} running out of time when {} cannot checkmate",
+                colour,
+                colour.invert()
+            ),
+        };
+    }
+}
+
+/// Prints the English name of the piece type, which `from_str` parses back.
+impl fmt::Display for PieceType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+/// Parses a piece type, written as a lower-, upper- or mixed case English word, a single character,
+/// or a unicode miscellaneous symbol, e.g. "queen", "q" or "♛".
+impl FromStr for PieceType {
+    type Err = ChessError;
+
+    fn from_str(str: &str) -> Result<PieceType, ChessError> {
+        let mut chars = str.trim().chars();
+        let c1 = chars.next();
+        if let (Some(ch), None) = (c1, chars.next()) {
+            return PieceType::from_char(ch);
+        }
+        return Ok(match str.trim().to_ascii_lowercase().as_str() {
+            "king" => PieceType::King,
+            "queen" => PieceType::Queen,
+            "rook" => PieceType::Rook,
+            "bishop" => PieceType::Bishop,
+            "knight" => PieceType::Knight,
+            "pawn" => PieceType::Pawn,
+            _ => {
+                return Err(ChessError::InvalidPiece(format!(
+                    "'{}' does not represent a piece",
+                    str
+                )))
+            }
+        });
+    }
+}
+
+/// Prints the piece as in FEN, uppercase for white and lowercase for black.
+impl fmt::Display for Piece {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.to_char_colourcased())
+    }
+}
+
+/// Parses a piece written as in FEN, uppercase for white and lowercase for black.
+impl FromStr for Piece {
+    type Err = ChessError;
+
+    fn from_str(str: &str) -> Result<Piece, ChessError> {
+        let mut chars = str.trim().chars();
+        return match (chars.next(), chars.next()) {
+            (Some(ch), None) if ch.is_ascii_alphabetic() => Ok(Piece {
+                piece_type: PieceType::from_char(ch)?,
+                colour: if ch.is_ascii_uppercase() {
+                    Colour::White
+                } else {
+                    Colour::Black
+                },
+            }),
+            _ => Err(ChessError::InvalidPiece(format!(
+                "'{}' does not represent a piece",
+                str
+            ))),
+        };
+    }
+}
+
+/// Prints the position on the format `XF`, e.g. e4.
+///
+/// Position::NULL is displayed as a single hyphen (-)
+///
+/// # Panics
+///
+/// Panics if self does not represent some position on the chessboard
+/// and is not Position::NULL.
+impl fmt::Display for Position {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self == &Position::NULL {
+            return write!(f, "-");
+        }
+        write!(
+            f,
+            "{}{}",
+            match self.file {
+                0 => "a",
+                1 => "b",
+                2 => "c",
+                3 => "d",
+                4 => "e",
+                5 => "f",
+                6 => "g",
+                7 => "h",
+                _default => panic!("Method called on a position outside the chess board"),
+            },
+            self.rank + 1
+        )
+    }
+}
+
+/// See `Position::parse_str`.
+impl FromStr for Position {
+    type Err = ChessError;
+
+    fn from_str(str: &str) -> Result<Position, ChessError> {
+        return Position::parse_str(str);
+    }
+}
+
+/// Prints the move in coordinate notation (as in UCI), e.g. e2e4, or e7e8q for a promotion.
+impl fmt::Display for Move {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}{}", self.from, self.to)?;
+        if let Some(piece_type) = self.promotion {
+            write!(f, "{}", piece_type.char().to_ascii_lowercase())?;
+        }
+        Ok(())
+    }
+}
+
+/// Parses a move in coordinate notation (as in UCI), e.g. e2e4, or e7e8q for a promotion.
+/// The piece promoted to is one of the lowercase letters n, b, r and q.
+///
+/// The flags of the parsed move are not set, see `Move`.
+impl FromStr for Move {
+    type Err = ChessError;
+
+    fn from_str(str: &str) -> Result<Move, ChessError> {
+        let chars: Vec<char> = str.trim().chars().collect();
+        if chars.len() != 4 && chars.len() != 5 {
+            return Err(ChessError::InvalidPosition(format!(
+                "'{}' is not a move on the format e2e4 or e7e8q",
+                str
+            )));
+        }
+        let from = Position::parse_str(&chars[0..2].iter().collect::<String>())?;
+        let to = Position::parse_str(&chars[2..4].iter().collect::<String>())?;
+        let promotion = match chars.get(4) {
+            None => return Ok(Move::new(from, to)),
+            Some('n') => PieceType::Knight,
+            Some('b') => PieceType::Bishop,
+            Some('r') => PieceType::Rook,
+            Some('q') => PieceType::Queen,
+            Some(ch) => {
+                return Err(ChessError::InvalidPromotion(format!(
+                    "'{}' is not a piece to promote to, which is one of n, b, r and q",
+                    ch
+                )))
+            }
+        };
+        return Ok(Move::new_promotion(from, to, promotion));
+    }
+}
+
+/// Returns the `Option<Piece>` at a position, like `Game::get`, e.g. `game[pos]`.
+///
+/// # Panics
+///
+/// Panics if the position is not valid.
+impl Index<Position> for Game {
+    type Output = Option<Piece>;
+
+    fn index(&self, pos: Position) -> &Option<Piece> {
+        if let Err(err) = pos.valid() {
+            panic!("{}", err);
+        }
+        return &self.board[pos.idx];
+    }
+}
+
+/// See `Game::from_fen`.
+impl FromStr for Game {
+    type Err = ChessError;
+
+    fn from_str(fen: &str) -> Result<Game, ChessError> {
+        return Game::from_fen(fen);
+    }
+}
+
 /// Tests are present in lib_tests.rs
 #[cfg(test)]
 mod lib_tests;
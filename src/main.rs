@@ -1,7 +1,15 @@
+use chess_engine::BoardStyle;
+use chess_engine::Colour;
 use chess_engine::Game;
 use chess_engine::GameState;
+use chess_engine::Move;
+use chess_engine::net::{Connection, NetMessage};
+use chess_engine::OpeningBook;
 use chess_engine::Position;
 use chess_engine::PieceType;
+use chess_engine::TranspositionTable;
+
+use std::time::{Duration, Instant, SystemTime};
 
 /*
 
@@ -10,22 +18,487 @@ This file shows a basic way to interact with the chess engine.
 
 */
 
-fn main() {
-    let mut game = Game::new();
+/// The computer opponents that can be played against, from weakest to strongest.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum Opponent {
+    /// Plays a random possible move.
+    Random,
+    /// Searches some number of half-moves ahead.
+    Depth(u32),
+    /// Searches deeper and deeper, as long as the next depth is expected to finish within the time.
+    Timed(Duration),
+}
+
+impl Opponent {
+    /// Parses a difficulty level: 'random', 'easy' (depth 1), 'medium' (depth 3) or 'timed' (one second per move).
+    fn parse(str: &str) -> Option<Opponent> {
+        return match str {
+            "random" => Some(Opponent::Random),
+            "easy" => Some(Opponent::Depth(1)),
+            "medium" => Some(Opponent::Depth(3)),
+            "timed" => Some(Opponent::Timed(Duration::from_secs(1))),
+            _ => None,
+        };
+    }
+
+    /// Returns the move the opponent plays in `game`, or None if there is none.
+    /// `seed` is the state of the random number generator used by `Opponent::Random`.
+    fn choose_move(&self, game: &Game, seed: &mut u64) -> Option<Move> {
+        match self {
+            Opponent::Random => {
+                let moves = game.get_all_possible_moves();
+                if moves.is_empty() {
+                    return None;
+                }
+                *seed = seed
+                    .wrapping_mul(6364136223846793005)
+                    .wrapping_add(1442695040888963407);
+                return Some(moves[(*seed >> 33) as usize % moves.len()]);
+            }
+            Opponent::Depth(depth) => return game.best_move(*depth),
+            Opponent::Timed(time) => {
+                // the table keeps the best moves of the shallower searches, so each depth starts from them
+                let start = Instant::now();
+                let mut table = TranspositionTable::new(1 << 16);
+                let mut best = None;
+                let mut last = Duration::ZERO;
+                // how many times longer a depth takes than the one before, until it has been measured
+                let mut growth = game.get_all_possible_moves().len().max(2) as u32;
+                for depth in 1..=8 {
+                    // a search is not stopped once started, so a depth that would not finish in time is skipped
+                    if depth > 1 && start.elapsed() + last * growth > *time {
+                        break;
+                    }
+                    let depth_start = Instant::now();
+                    best = game.best_move_with_table(depth, &mut table);
+                    let took = depth_start.elapsed();
+                    if !last.is_zero() {
+                        growth = ((took.as_secs_f64() / last.as_secs_f64()).ceil() as u32).max(2);
+                    }
+                    last = took;
+                }
+                return best;
+            }
+        }
+    }
+}
+
+/// The options the binary is started with, see `USAGE`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+struct Args {
+    /// The FEN of the position to start from.
+    fen: Option<String>,
+    /// The PGN file of a game to replay and continue.
+    pgn: Option<String>,
+    /// The number of engine-vs-engine games to play instead of an interactive game.
+    selfplay: Option<u32>,
+    /// The depth to count the move tree to instead of playing, for testing the move generation.
+    perft: Option<u32>,
+    /// The address to wait for the other player at, for a game over the network.
+    host: Option<String>,
+    /// The address of the player hosting a game over the network, to join it.
+    join: Option<String>,
+    /// The Polyglot opening book that the computer opponent plays from while the position is in it.
+    book: Option<String>,
+    /// Print the usage instead of playing.
+    help: bool,
+}
+
+const USAGE: &str = "Usage: chess_engine [OPTIONS]
+
+Options:
+  --fen <FEN>       start from the position in FEN
+  --pgn <file>      replay the game saved as PGN in the file, and continue it
+  --selfplay <n>    let the engine play n games against itself, and print them as PGN
+  --perft <depth>   count the move sequences of depth half-moves from the position, and exit
+  --host <address>  wait for another player to join at the address, e.g. 0.0.0.0:7878, and move first
+  --join <address>  join the game hosted at the address, e.g. 192.168.0.2:7878
+  --book <file>     let the computer play the moves of the Polyglot opening book in the file while it has them
+  --help            print this message";
+
+impl Args {
+    /// Parses the command-line arguments `args`, without the name of the binary.
+    ///
+    /// Errors with an explanation if an option is unknown or is missing its value.
+    fn parse(mut args: impl Iterator<Item = String>) -> Result<Args, String> {
+        let mut parsed = Args::default();
+        while let Some(arg) = args.next() {
+            if arg == "--help" {
+                parsed.help = true;
+                continue;
+            }
+            // every other option takes a value
+            let value = match arg.as_str() {
+                "--fen" | "--pgn" | "--selfplay" | "--perft" | "--host" | "--join" | "--book" => args
+                    .next()
+                    .ok_or_else(|| format!("The option {} needs a value", arg))?,
+                _ => return Err(format!("Unknown option '{}'", arg)),
+            };
+            let number = || {
+                value
+                    .parse::<u32>()
+                    .map_err(|_| format!("The option {} needs a number, not '{}'", arg, value))
+            };
+            match arg.as_str() {
+                "--fen" => parsed.fen = Some(value.clone()),
+                "--pgn" => parsed.pgn = Some(value.clone()),
+                "--selfplay" => parsed.selfplay = Some(number()?),
+                "--host" => parsed.host = Some(value.clone()),
+                "--join" => parsed.join = Some(value.clone()),
+                "--book" => parsed.book = Some(value.clone()),
+                _ => parsed.perft = Some(number()?),
+            }
+        }
+        if parsed.fen.is_some() && parsed.pgn.is_some() {
+            return Err("Only one of --fen and --pgn can be given".to_owned());
+        }
+        if parsed.host.is_some() && parsed.join.is_some() {
+            return Err("Only one of --host and --join can be given".to_owned());
+        }
+        if parsed.join.is_some() && (parsed.fen.is_some() || parsed.pgn.is_some()) {
+            return Err("The host chooses the position, so --join cannot be given with --fen or --pgn".to_owned());
+        }
+        return Ok(parsed);
+    }
+
+    /// Returns the game to start from: the position of --fen, the game of --pgn, or a new game.
+    fn starting_game(&self) -> Result<Game, String> {
+        if let Some(fen) = &self.fen {
+            return Game::from_fen(fen).map_err(|err| err.to_string());
+        }
+        if let Some(file) = &self.pgn {
+            let pgn = std::fs::read_to_string(file).map_err(|err| err.to_string())?;
+            return Game::from_pgn(&pgn).map_err(|err| err.to_string());
+        }
+        return Ok(Game::new());
+    }
+}
+
+/// Plays `games` engine-vs-engine games from `start` and prints each as PGN, followed by the score.
+///
+/// The first two moves of each side are random, so that the games differ, after which both sides search
+/// one half-move ahead. Games are cut off after 300 half-moves.
+fn selfplay(start: &Game, games: u32, seed: &mut u64) {
+    let mut wins = [0; 2];
+    let mut draws = 0;
+    for _ in 0..games {
+        let mut game = start.clone();
+        let mut plies = 0;
+        while !game.is_gameover() && plies < 300 {
+            let player = if plies < 4 { Opponent::Random } else { Opponent::Depth(1) };
+            match player.choose_move(&game, seed) {
+                Some(mv) => {
+                    game.play_move(mv).expect("the computer plays possible moves");
+                }
+                None => break,
+            }
+            plies += 1;
+        }
+        println!("{}", game.to_pgn());
+        match game.get_winner() {
+            Some(colour) => wins[colour as usize] += 1,
+            None if game.is_gameover() => draws += 1,
+            None => {}
+        }
+    }
+    println!(
+        "White won {}, black won {} and {} were drawn of {} games.",
+        wins[0], wins[1], draws, games
+    );
+}
+
+/// Reads the next line from the standard input, trimmed. Is None when the input ends.
+fn read_line() -> Option<String> {
+    use std::io::prelude::*;
+
+    let mut line = String::new();
+    match std::io::stdin().lock().read_line(&mut line) {
+        Ok(0) | Err(_) => return None,
+        Ok(_) => return Some(line.trim().to_owned()),
+    }
+}
 
+/// Plays `game` against the other player over `connection`, where this player plays `colour`.
+///
+/// On this player's turn, a move is read from the standard input and sent, as are draw offers and resignations,
+/// and 'sync' asks for the other player's position. On the other player's turn, their messages are received and
+/// applied.
+///
+/// A synced position is only taken if the game reaches it, see `Game::apply_message`, and the game ends otherwise.
+fn play_online(mut connection: Connection, mut game: Game, colour: Colour) -> std::io::Result<()> {
     loop {
-        use std::io;
-        use std::io::prelude::*;
+        println!("{}", game.format_board_from(BoardStyle::Ascii, colour));
+        if game.is_gameover() {
+            println!("The game is over because of {:?}.", game.get_game_over_reason());
+            return Ok(());
+        }
+
+        if game.get_active_colour() != colour {
+            println!("Waiting for {} to move...", colour.invert());
+            let message = match connection.receive()? {
+                Some(message) => message,
+                None => {
+                    println!("The other player left the game.");
+                    return Ok(());
+                }
+            };
+            match message {
+                NetMessage::OfferDraw => {
+                    game.apply_message(&message, colour.invert()).expect("the game is not over");
+                    println!("{} offers a draw. Do you accept, 'yes' or 'no'?", colour.invert());
+                    let answer = if read_line().as_deref() == Some("yes") {
+                        NetMessage::AcceptDraw
+                    } else {
+                        NetMessage::DeclineDraw
+                    };
+                    game.apply_message(&answer, colour).expect("a draw was offered");
+                    connection.send(&answer)?;
+                }
+                NetMessage::RequestSync => connection.send(&NetMessage::Sync(game.fen()))?,
+                NetMessage::Sync(_) => {
+                    if let Err(err) = game.apply_message(&message, colour.invert()) {
+                        println!("Could not catch up with the other player: {}", err);
+                        return Ok(());
+                    }
+                }
+                message => {
+                    if let Err(err) = game.apply_message(&message, colour.invert()) {
+                        // the games have gone out of sync, so ask for the other player's position
+                        println!("Could not apply '{}': {}", message, err);
+                        connection.send(&NetMessage::RequestSync)?;
+                    } else if let NetMessage::Move(mv) = message {
+                        println!("{} played {}.", colour.invert(), mv);
+                    }
+                }
+            }
+            continue;
+        }
 
+        println!("Your move ('XF XF'), or 'draw' to offer a draw, 'resign' or 'sync' to compare positions.");
+        let line = match read_line() {
+            Some(line) => line,
+            None => return Ok(()),
+        };
+        let input: Vec<&str> = line.split_whitespace().collect();
+        match input.as_slice() {
+            ["draw"] => {
+                if let Err(err) = game.apply_message(&NetMessage::OfferDraw, colour) {
+                    println!("Error received: \n'{}'", err);
+                    continue;
+                }
+                connection.send(&NetMessage::OfferDraw)?;
+                println!("Waiting for the answer to your draw offer...");
+                // the answer comes before anything else, since it is still this player's turn
+                match connection.receive()? {
+                    Some(answer) if answer == NetMessage::AcceptDraw || answer == NetMessage::DeclineDraw => {
+                        game.apply_message(&answer, colour.invert()).expect("a draw was offered");
+                        if answer == NetMessage::DeclineDraw {
+                            println!("The draw offer was declined.");
+                        }
+                    }
+                    Some(other) => println!("Expected an answer to the draw offer, not '{}'.", other),
+                    None => {
+                        println!("The other player left the game.");
+                        return Ok(());
+                    }
+                }
+            }
+            ["resign"] => {
+                game.apply_message(&NetMessage::Resign, colour).expect("the game is not over");
+                connection.send(&NetMessage::Resign)?;
+            }
+            ["sync"] => {
+                connection.send(&NetMessage::RequestSync)?;
+                match connection.receive()? {
+                    Some(sync @ NetMessage::Sync(_)) => {
+                        if let Err(err) = game.apply_message(&sync, colour.invert()) {
+                            println!("Could not catch up with the other player: {}", err);
+                            return Ok(());
+                        }
+                    }
+                    Some(other) => println!("Expected the other player's position, not '{}'.", other),
+                    None => {
+                        println!("The other player left the game.");
+                        return Ok(());
+                    }
+                }
+            }
+            [from, to] => {
+                if let Err(err) = game.make_move(from, to) {
+                    println!("Error received: \n'{}'\nPlease try again!", err);
+                    continue;
+                }
+                while game.get_game_state() == GameState::WaitingOnPromotionChoice {
+                    println!("What would you like to promote the pawn to?");
+                    let piece = read_line().unwrap_or_default();
+                    match piece.parse::<PieceType>().and_then(|piece| game.set_promotion(piece)) {
+                        Ok(_) => {}
+                        Err(err) => println!("Error received:\n{}\nPlease try again!", err),
+                    }
+                }
+                let mv = game.last_move().expect("a move was made").as_move();
+                connection.send(&NetMessage::Move(mv))?;
+            }
+            _ => println!("Invalid input. Please try again!"),
+        }
+    }
+}
+
+fn main() {
+    use std::io;
+    use std::io::prelude::*;
+
+    let args = match Args::parse(std::env::args().skip(1)) {
+        Ok(args) => args,
+        Err(message) => {
+            eprintln!("{}\n\n{}", message, USAGE);
+            std::process::exit(2);
+        }
+    };
+    if args.help {
+        println!("{}", USAGE);
+        return;
+    }
+    if let Some(addr) = &args.join {
+        println!("Joining the game at {}...", addr);
+        let result = Connection::join(addr).and_then(|mut connection| {
+            // the host says which colour to play, and from which position
+            let colour = match connection.receive()? {
+                Some(NetMessage::Hello(colour)) => colour,
+                _ => return Err(io::Error::new(io::ErrorKind::InvalidData, "expected HELLO")),
+            };
+            let game = match connection.receive()? {
+                Some(NetMessage::Sync(fen)) => {
+                    Game::from_fen(&fen).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?
+                }
+                _ => return Err(io::Error::new(io::ErrorKind::InvalidData, "expected SYNC")),
+            };
+            println!("Joined the game as {}!", colour);
+            return play_online(connection, game, colour);
+        });
+        if let Err(err) = result {
+            eprintln!("The network game failed: {}", err);
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    let mut game = match args.starting_game() {
+        Ok(game) => game,
+        Err(message) => {
+            eprintln!("Could not start from the position: {}", message);
+            std::process::exit(1);
+        }
+    };
+
+    let book = match args.book.as_ref().map(OpeningBook::load).transpose() {
+        Ok(book) => book,
+        Err(err) => {
+            eprintln!("Could not load the opening book: {}", err);
+            std::process::exit(1);
+        }
+    };
+
+    // seed the random opponent from the clock, so that games differ
+    let mut seed = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map_or(0, |time| time.as_nanos() as u64);
+
+    if let Some(depth) = args.perft {
+        let start = Instant::now();
+        let nodes = game.perft(depth);
+        println!("perft({}) = {} ({:?})", depth, nodes, start.elapsed());
+        return;
+    }
+    if let Some(games) = args.selfplay {
+        selfplay(&game, games, &mut seed);
+        return;
+    }
+    if let Some(addr) = &args.host {
+        println!("Waiting for the other player to join at {}...", addr);
+        let colour = game.get_active_colour();
+        let result = Connection::host(addr).and_then(|mut connection| {
+            connection.send(&NetMessage::Hello(colour.invert()))?;
+            connection.send(&NetMessage::Sync(game.fen()))?;
+            println!("The other player joined! You play {}.", colour);
+            return play_online(connection, game.clone(), colour);
+        });
+        if let Err(err) = result {
+            eprintln!("The network game failed: {}", err);
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    // choose between a hot-seat game and a computer opponent, and the colour of the human player
+    let mut opponent = None;
+    let mut human_colour = Colour::White;
+    println!("Who do you want to play against? Input 'human' for a hot-seat game, or a computer: 'random', 'easy', 'medium' or 'timed'.");
+    for line in io::stdin().lock().lines() {
+        let line = line.expect("Error.");
+        if line.trim() == "human" {
+            break;
+        }
+        match Opponent::parse(line.trim()) {
+            Some(computer) => {
+                opponent = Some(computer);
+                break;
+            }
+            None => println!("Invalid input. Please try again!"),
+        }
+    }
+    if opponent.is_some() {
+        println!("Which colour do you want to play, 'white' or 'black'?");
+        for line in io::stdin().lock().lines() {
+            match line.expect("Error.").trim() {
+                "white" => break,
+                "black" => {
+                    human_colour = Colour::Black;
+                    break;
+                }
+                _ => println!("Invalid input. Please try again!"),
+            }
+        }
+    }
+    loop {
         let input = io::stdin();
         let mut lines = input.lock().lines(); // we've built an iterator over the lines input to stdin
 
+        if game.is_gameover() {
+            println!("{}", game.format_board_from(BoardStyle::Ascii, human_colour));
+            println!("The game is over because of {:?}.", game.get_game_over_reason());
+            break;
+        }
+
+        // let the computer move when it is its turn
+        if let Some(computer) = opponent {
+            if game.get_active_colour() != human_colour {
+                println!("The computer is thinking...");
+                let book_move = book.as_ref().and_then(|book| book.best_move(&game));
+                match book_move.or_else(|| computer.choose_move(&game, &mut seed)) {
+                    Some(mv) => {
+                        game.play_move(mv).expect("the computer plays possible moves");
+                        println!("The computer played {}.", mv);
+                    }
+                    None => {
+                        println!("The computer found no move to play.");
+                        break;
+                    }
+                }
+                continue;
+            }
+        }
+
         println!(
             "This is the current board. It is {}'s turn.",
             game.get_active_colour()
         );
-        println!("{}", game);
-        println!("Please input your move (on the format 'XF XF' where X is a character and F is a number).");
+        // show the board from the side of the player to move
+        println!(
+            "{}",
+            game.format_board_from(BoardStyle::Ascii, game.get_active_colour())
+        );
+        println!("Please input your move (on the format 'XF XF' where X is a character and F is a number), or 'help' for the other commands.");
 
         // read next input
         let input_tmp = lines
@@ -52,6 +525,71 @@ fn main() {
                 "{:?}",
                 game.get_board()[Position::parse_str(input[1]).unwrap().idx]
             );
+        } else if input[0] == "help" {
+            println!("Commands:");
+            println!("  XF XF        move the piece on the first position to the second, e.g. 'e2 e4'");
+            println!("  undo         take back the last move (and the computer's reply)");
+            println!("  moves        print the moves made so far");
+            println!("  fen          print the position as FEN");
+            println!("  load <fen>   start from the position in FEN");
+            println!("  save <file>  save the game as PGN");
+            println!("  open <file>  continue a game saved as PGN");
+            println!("  state, colour, gm XF, piece XF  print the game state, active colour, possible moves or piece");
+        } else if input[0] == "undo" {
+            match game.undo_move() {
+                Err(message) => println!("Error received: \n'{}'", message),
+                Ok(_) => {
+                    // take back the computer's reply too, so that it is the human's turn again
+                    if opponent.is_some() && game.get_active_colour() != human_colour {
+                        let _ = game.undo_move();
+                    }
+                    println!("Took back the move!");
+                }
+            }
+        } else if input[0] == "moves" {
+            // one line per move number, white's move first
+            let mut move_lines: Vec<String> = vec![];
+            for (i, (entry, san)) in game.get_history().iter().zip(game.history_san()).enumerate() {
+                if entry.piece_moved().is_white() {
+                    move_lines.push(format!("{}. {}", entry.move_number(), san));
+                } else if i == 0 {
+                    move_lines.push(format!("{}... {}", entry.move_number(), san));
+                } else {
+                    move_lines.last_mut().expect("white moved first").push_str(&format!(" {}", san));
+                }
+            }
+            if move_lines.is_empty() {
+                println!("No moves have been made yet.");
+            }
+            for line in move_lines {
+                println!("{}", line);
+            }
+        } else if input[0] == "fen" {
+            println!("{}", game.fen());
+        } else if input[0] == "load" {
+            match Game::from_fen(&input[1..].join(" ")) {
+                Err(message) => println!("Error received: \n'{}'\nPlease try again!", message),
+                Ok(loaded) => {
+                    game = loaded;
+                    println!("Loaded the position!");
+                }
+            }
+        } else if input[0] == "save" && input.len() == 2 {
+            match std::fs::write(input[1], game.to_pgn()) {
+                Err(message) => println!("Error received: \n'{}'\nPlease try again!", message),
+                Ok(_) => println!("Saved the game to {}!", input[1]),
+            }
+        } else if input[0] == "open" && input.len() == 2 {
+            match std::fs::read_to_string(input[1]) {
+                Err(message) => println!("Error received: \n'{}'\nPlease try again!", message),
+                Ok(pgn) => match Game::from_pgn(&pgn) {
+                    Err(message) => println!("Error received: \n'{}'\nPlease try again!", message),
+                    Ok(opened) => {
+                        game = opened;
+                        println!("Opened the game from {}!", input[1]);
+                    }
+                },
+            }
         } else if input.len() == 2 {
             // try to make the move
             match game.make_move(input[0], input[1]) {
@@ -73,7 +611,7 @@ fn main() {
                 .expect("Error."); // expect errors
             let input = input_tmp
                 .trim(); // remove whitespaces
-            match PieceType::from_str(input) {
+            match input.parse::<PieceType>() {
                 Ok(piece) => match game.set_promotion(piece) {
                     Ok(_) => println!("Successfully promoted the piece!"),
                     Err(msg) => println!("Error received:\n{}\nPlease try again!", msg),
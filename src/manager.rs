@@ -0,0 +1,238 @@
+// Author: Eskil Nyberg
+
+/*!
+ * A manager of many games at once, e.g. for a server: the games are kept by id, each behind its own lock,
+ * with sweeps that end games whose active colour has run out of time and remove finished games.
+ */
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant};
+
+use super::ChessError;
+use super::Colour;
+use super::Game;
+use super::GameState;
+use super::Move;
+use super::MoveResult;
+
+/// Identifies a game of a `GameManager`.
+pub type GameId = u64;
+
+/// A game with the time its active colour's turn started.
+#[derive(Debug)]
+struct ManagedGame {
+    game: Game,
+    turn_started: Instant,
+    /// The length of the history when the turn started, so that moves made in any way start a new turn.
+    plies: usize,
+}
+
+/// Many games kept by id, which can be shared between threads, e.g. in an `Arc`.
+///
+/// Each game has its own lock, so threads working on different games do not wait for each other. The games are
+/// reached through `with_game`, or through `play_move` and `resign` for the common cases.
+///
+/// The engine keeps no clocks, so a manager created with `with_move_time` times the turns itself: every move starts
+/// the turn of the other colour, and `sweep_timeouts` ends the games whose active colour has used more than the move
+/// time with `Game::submit_timeout`, as a draw if the other colour cannot checkmate. Finished games stay until they
+/// are taken out with `remove` or `sweep_finished`.
+///
+/// # Example code
+///
+/// ```rust
+/// use chess_engine::*;
+/// use std::time::{Duration, Instant};
+///
+/// let manager = GameManager::with_move_time(Duration::from_secs(30));
+/// let id = manager.create();
+/// manager.play_move(id, Move::from_uci("e2e4").unwrap()).unwrap();
+/// assert_eq!(manager.with_game(id, |game| game.get_active_colour()), Ok(Colour::Black));
+///
+/// // a minute later, black has run out of time
+/// assert_eq!(manager.sweep_timeouts(Instant::now() + Duration::from_secs(60)), vec![id]);
+/// let (_, game) = manager.sweep_finished().pop().unwrap();
+/// assert_eq!(game.get_game_over_reason(), Some(GameOverReason::Timeout(Colour::Black)));
+/// assert!(manager.is_empty());
+/// ```
+#[derive(Debug)]
+pub struct GameManager {
+    games: RwLock<HashMap<GameId, Arc<Mutex<ManagedGame>>>>,
+    next_id: AtomicU64,
+    /// The time each colour has for a move, or None if the turns are not timed.
+    move_time: Option<Duration>,
+}
+
+impl Default for GameManager {
+    fn default() -> Self {
+        GameManager::new()
+    }
+}
+
+impl GameManager {
+    /// Constructor for a manager without games, whose turns are not timed.
+    pub fn new() -> GameManager {
+        return GameManager {
+            games: RwLock::new(HashMap::new()),
+            next_id: AtomicU64::new(1),
+            move_time: None,
+        };
+    }
+
+    /// Constructor for a manager without games, where each colour has `move_time` for every move, see `sweep_timeouts`.
+    pub fn with_move_time(move_time: Duration) -> GameManager {
+        return GameManager {
+            move_time: Some(move_time),
+            ..GameManager::new()
+        };
+    }
+
+    /// Returns the time each colour has for a move, if the turns are timed.
+    pub fn move_time(&self) -> Option<Duration> {
+        return self.move_time;
+    }
+
+    /// Adds a new game from the starting position and returns its id.
+    pub fn create(&self) -> GameId {
+        return self.insert(Game::new());
+    }
+
+    /// Adds `game`, e.g. one set up from a FEN, and returns its id. The turn of its active colour starts now.
+    pub fn insert(&self, game: Game) -> GameId {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let managed = ManagedGame {
+            plies: game.plies(),
+            game,
+            turn_started: Instant::now(),
+        };
+        self.games
+            .write()
+            .expect("not poisoned")
+            .insert(id, Arc::new(Mutex::new(managed)));
+        return id;
+    }
+
+    /// Removes the game with `id` and returns it, or None if there is no such game.
+    pub fn remove(&self, id: GameId) -> Option<Game> {
+        let managed = self.games.write().expect("not poisoned").remove(&id)?;
+        let managed = match Arc::try_unwrap(managed) {
+            Ok(managed) => managed.into_inner().expect("not poisoned"),
+            // Another thread is using the game, so it is copied
+            Err(managed) => return Some(managed.lock().expect("not poisoned").game.clone()),
+        };
+        return Some(managed.game);
+    }
+
+    /// Returns the ids of the games, in increasing order.
+    pub fn ids(&self) -> Vec<GameId> {
+        let mut ids: Vec<GameId> = self
+            .games
+            .read()
+            .expect("not poisoned")
+            .keys()
+            .copied()
+            .collect();
+        ids.sort_unstable();
+        return ids;
+    }
+
+    /// Returns the number of games.
+    pub fn len(&self) -> usize {
+        return self.games.read().expect("not poisoned").len();
+    }
+
+    /// Returns true if there are no games.
+    pub fn is_empty(&self) -> bool {
+        return self.len() == 0;
+    }
+
+    /// Returns the game with `id`, to be locked after the lock of the manager is released.
+    fn game(&self, id: GameId) -> Result<Arc<Mutex<ManagedGame>>, ChessError> {
+        return match self.games.read().expect("not poisoned").get(&id) {
+            Some(managed) => Ok(Arc::clone(managed)),
+            None => Err(ChessError::NoSuchGame(id)),
+        };
+    }
+
+    /// Calls `f` with the game with `id`, which is locked for other threads meanwhile, and returns what `f` returns.
+    /// If `f` makes or takes back moves, the turn of the new active colour starts.
+    ///
+    /// Errors if there is no game with `id`.
+    pub fn with_game<R>(
+        &self,
+        id: GameId,
+        f: impl FnOnce(&mut Game) -> R,
+    ) -> Result<R, ChessError> {
+        let managed = self.game(id)?;
+        let mut managed = managed.lock().expect("not poisoned");
+        let result = f(&mut managed.game);
+        let plies = managed.game.plies();
+        if plies != managed.plies {
+            managed.plies = plies;
+            managed.turn_started = Instant::now();
+        }
+        return Ok(result);
+    }
+
+    /// Plays `mv` in the game with `id`, see `Game::play_move`.
+    ///
+    /// Errors if there is no game with `id`, or as `Game::play_move`.
+    pub fn play_move(&self, id: GameId, mv: Move) -> Result<MoveResult, ChessError> {
+        return self.with_game(id, |game| game.play_move(mv))?;
+    }
+
+    /// Resigns the game with `id` for `colour`, see `Game::resign`.
+    ///
+    /// Errors if there is no game with `id`, or if the game is over.
+    pub fn resign(&self, id: GameId, colour: Colour) -> Result<GameState, ChessError> {
+        return self.with_game(id, |game| game.resign(colour))?;
+    }
+
+    /// Ends the games whose active colour has used more than the move time by `now`, usually `Instant::now()`,
+    /// as lost on time for that colour, or drawn if the other colour has no mating material, see
+    /// `Game::submit_timeout`. Returns the ids of the games ended, in increasing order.
+    ///
+    /// Does nothing if the turns are not timed. Games that are over are left alone.
+    pub fn sweep_timeouts(&self, now: Instant) -> Vec<GameId> {
+        let move_time = match self.move_time {
+            Some(move_time) => move_time,
+            None => return vec![],
+        };
+        let mut ended = vec![];
+        for id in self.ids() {
+            let managed = match self.game(id) {
+                Ok(managed) => managed,
+                // Removed by another thread meanwhile
+                Err(_) => continue,
+            };
+            let mut managed = managed.lock().expect("not poisoned");
+            if managed.game.get_game_state() == GameState::GameOver
+                || now.saturating_duration_since(managed.turn_started) <= move_time
+            {
+                continue;
+            }
+            let colour = managed.game.get_active_colour();
+            if managed.game.submit_timeout(colour).is_ok() {
+                ended.push(id);
+            }
+        }
+        return ended;
+    }
+
+    /// Removes the games that are over and returns them with their ids, in increasing order of id.
+    pub fn sweep_finished(&self) -> Vec<(GameId, Game)> {
+        let finished: Vec<GameId> = self
+            .ids()
+            .into_iter()
+            .filter(|id| {
+                self.with_game(*id, |game| game.get_game_state() == GameState::GameOver)
+                    .unwrap_or(false)
+            })
+            .collect();
+        return finished
+            .into_iter()
+            .filter_map(|id| Some((id, self.remove(id)?)))
+            .collect();
+    }
+}
@@ -0,0 +1,100 @@
+// Author: Eskil Nyberg
+
+/*!
+ * Counting of material, e.g. for a material bar, and the piece values used by the search.
+ */
+
+use super::Colour;
+use super::Game;
+use super::PieceType;
+
+/// The number of pieces of each type that a colour has on the board, see `Game::material`.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MaterialCount {
+    pub pawns: u8,
+    pub knights: u8,
+    pub bishops: u8,
+    pub rooks: u8,
+    pub queens: u8,
+}
+
+impl MaterialCount {
+    /// Returns the number of pieces of type `piece_type`. Is 0 for kings, which are not counted.
+    pub fn count(&self, piece_type: PieceType) -> u8 {
+        return match piece_type {
+            PieceType::King => 0,
+            PieceType::Queen => self.queens,
+            PieceType::Rook => self.rooks,
+            PieceType::Bishop => self.bishops,
+            PieceType::Knight => self.knights,
+            PieceType::Pawn => self.pawns,
+        };
+    }
+
+    /// Returns the value of the material in centipawns, see `piece_value`.
+    pub fn value(&self) -> i32 {
+        return [
+            PieceType::Queen,
+            PieceType::Rook,
+            PieceType::Bishop,
+            PieceType::Knight,
+            PieceType::Pawn,
+        ]
+        .iter()
+        .map(|piece_type| self.count(*piece_type) as i32 * piece_value(*piece_type))
+        .sum();
+    }
+}
+
+/// Returns the value of a piece of type `piece_type` in centipawns:
+/// pawn 100, knight and bishop 300, rook 500, queen 900. Kings are worth 0, since they are never captured.
+pub(crate) fn piece_value(piece_type: PieceType) -> i32 {
+    return match piece_type {
+        PieceType::King => 0,
+        PieceType::Queen => 900,
+        PieceType::Rook => 500,
+        PieceType::Knight | PieceType::Bishop => 300,
+        PieceType::Pawn => 100,
+    };
+}
+
+impl Game {
+    /// Returns the number of pieces of each type that `colour` has on the board, e.g. for a captured-pieces tray.
+    ///
+    /// # Example code
+    ///
+    /// ```rust
+    /// use chess_engine::*;
+    ///
+    /// let material = Game::new().material(Colour::White);
+    /// assert_eq!((material.pawns, material.knights, material.queens), (8, 2, 1));
+    /// assert_eq!(material.value(), 3900);
+    /// ```
+    pub fn material(&self, colour: Colour) -> MaterialCount {
+        let mut material = MaterialCount::default();
+        for piece in self.board.iter().flatten().filter(|p| p.colour == colour) {
+            match piece.piece_type {
+                PieceType::King => {}
+                PieceType::Queen => material.queens += 1,
+                PieceType::Rook => material.rooks += 1,
+                PieceType::Bishop => material.bishops += 1,
+                PieceType::Knight => material.knights += 1,
+                PieceType::Pawn => material.pawns += 1,
+            }
+        }
+        return material;
+    }
+
+    /// Returns the value of white's material minus the value of black's material in centipawns, e.g. for a material bar.
+    ///
+    /// Is positive if white is ahead and negative if black is ahead. See `MaterialCount::value`.
+    pub fn material_balance(&self) -> i32 {
+        return self.material_advantage(Colour::White);
+    }
+
+    /// Returns the value of the material of `colour` minus the material of the opponent in centipawns.
+    pub(crate) fn material_advantage(&self, colour: Colour) -> i32 {
+        return self.material(colour).value() - self.material(colour.invert()).value();
+    }
+}
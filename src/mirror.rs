@@ -0,0 +1,60 @@
+// Author: Eskil Nyberg
+
+/*!
+ * Mirroring of positions, where the board is flipped and the colours are swapped.
+ */
+
+use super::Game;
+use super::Piece;
+use super::Position;
+
+impl Position {
+    /// Returns the position on the same file and the opposite rank, e.g. e2 for e7. Position::NULL stays Position::NULL.
+    pub fn flipped(&self) -> Position {
+        if self.valid().is_err() {
+            return *self;
+        }
+        return Position::new(7 - self.rank, self.file).expect("rank and file are 0-7");
+    }
+}
+
+impl Game {
+    /// Returns the mirrored position: the ranks are flipped and the colours swapped, including the active colour,
+    /// the castling rights and the en passant target.
+    ///
+    /// The mirrored position is as good for the other colour as this position is for the active colour, which is
+    /// useful for testing that an evaluation is symmetric, or for an engine that only evaluates from white's side.
+    /// Like a game loaded with `from_fen`, the mirrored game starts without history. The rules are kept.
+    ///
+    /// # Example code
+    ///
+    /// ```rust
+    /// use chess_engine::*;
+    ///
+    /// let mut game = Game::new();
+    /// game.make_move("e2", "e4").unwrap();
+    /// let mirrored = game.mirrored();
+    /// assert_eq!(mirrored.fen(), "rnbqkbnr/pppp1ppp/8/4p3/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1");
+    /// assert_eq!(mirrored.mirrored().fen(), game.fen());
+    /// ```
+    pub fn mirrored(&self) -> Game {
+        let mut game = Game::new();
+        for (idx, piece) in self.board.iter().enumerate() {
+            let pos = Position::new_from_idx(idx).expect("enumerated");
+            game.board[pos.flipped().idx] = piece.map(|piece| Piece {
+                piece_type: piece.piece_type,
+                colour: piece.colour.invert(),
+            });
+        }
+        game.active_colour = self.active_colour.invert();
+        game.castling_rights = self.castling_rights.mirrored();
+        game.en_passant_target = self.en_passant_target.flipped();
+        game.halfmoves = self.halfmoves;
+        game.fullmoves = self.fullmoves;
+        game.rules = self.rules.clone();
+
+        game.position_keys = vec![game.position_key()];
+        game.evaluate_game_state();
+        return game;
+    }
+}
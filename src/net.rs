@@ -0,0 +1,242 @@
+// Author: Eskil Nyberg
+
+/*!
+ * Two-player games over TCP, with a line-based protocol.
+ *
+ * One player hosts and the other joins. The host sends `HELLO` with the colour of the joining player, followed by
+ * `SYNC` with the starting position. After that, each player sends their moves, draw offers and resignation
+ * as they make them. Every message is one line:
+ * * `HELLO white` or `HELLO black`: the colour that the receiver plays.
+ * * `MOVE e2e4`: a move in coordinate notation (as in UCI), e.g. `MOVE e7e8q` for a promotion.
+ * * `DRAW OFFER`, `DRAW ACCEPT` and `DRAW DECLINE`: a draw offer and the answer to it.
+ * * `RESIGN`: the sender resigns.
+ * * `SYNC <FEN>`: the position of the sender, in answer to `RESYNC`, e.g. after a move the receiver could not make.
+ *   The receiver only takes it if it is its own position or one move on from it, see `Game::apply_message`.
+ * * `RESYNC`: asks the receiver to send `SYNC`.
+ *
+ * A `Connection` only receives a `SYNC` that was asked for with `RESYNC`, or the first one from the host.
+ *
+ * # Example code
+ *
+ * ```rust
+ * use chess_engine::net::*;
+ * use chess_engine::*;
+ *
+ * let message = NetMessage::parse("MOVE e2e4").unwrap();
+ * assert_eq!(message, NetMessage::Move(Move::from_uci("e2e4").unwrap()));
+ *
+ * let mut game = Game::new();
+ * game.apply_message(&message, Colour::White).unwrap();
+ * assert_eq!(game.get_active_colour(), Colour::Black);
+ * ```
+ */
+
+use std::fmt;
+use std::io;
+use std::io::prelude::*;
+use std::io::BufReader;
+use std::net::{TcpListener, TcpStream, ToSocketAddrs};
+
+use super::ChessError;
+use super::Colour;
+use super::Game;
+use super::Move;
+
+/// A message of the protocol, see the module documentation.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum NetMessage {
+    /// The colour that the receiver plays, sent by the host.
+    Hello(Colour),
+    Move(Move),
+    OfferDraw,
+    AcceptDraw,
+    DeclineDraw,
+    Resign,
+    /// The FEN of the sender's position.
+    Sync(String),
+    /// Asks the receiver to send its position.
+    RequestSync,
+}
+
+impl NetMessage {
+    /// Parses a line of the protocol, see the module documentation.
+    ///
+    /// Errors if `line` is not a message.
+    pub fn parse(line: &str) -> Result<NetMessage, ChessError> {
+        let line = line.trim();
+        let (command, argument) = match line.split_once(' ') {
+            Some((command, argument)) => (command, argument.trim()),
+            None => (line, ""),
+        };
+        return match (command, argument) {
+            ("HELLO", "white") => Ok(NetMessage::Hello(Colour::White)),
+            ("HELLO", "black") => Ok(NetMessage::Hello(Colour::Black)),
+            ("MOVE", uci) => match Move::from_uci(uci) {
+                Ok(mv) => Ok(NetMessage::Move(mv)),
+                Err(err) => Err(ChessError::InvalidMessage(format!(
+                    "'{}' is not a message: {}",
+                    line, err
+                ))),
+            },
+            ("DRAW", "OFFER") => Ok(NetMessage::OfferDraw),
+            ("DRAW", "ACCEPT") => Ok(NetMessage::AcceptDraw),
+            ("DRAW", "DECLINE") => Ok(NetMessage::DeclineDraw),
+            ("RESIGN", "") => Ok(NetMessage::Resign),
+            ("SYNC", fen) if !fen.is_empty() => Ok(NetMessage::Sync(fen.to_string())),
+            ("RESYNC", "") => Ok(NetMessage::RequestSync),
+            _ => Err(ChessError::InvalidMessage(format!(
+                "'{}' is not a message",
+                line
+            ))),
+        };
+    }
+}
+
+/// Writes the message as a line of the protocol, without the line break.
+impl fmt::Display for NetMessage {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        return match self {
+            NetMessage::Hello(colour) => {
+                write!(f, "HELLO {}", colour.to_string().to_lowercase())
+            }
+            NetMessage::Move(mv) => write!(f, "MOVE {}", mv),
+            NetMessage::OfferDraw => write!(f, "DRAW OFFER"),
+            NetMessage::AcceptDraw => write!(f, "DRAW ACCEPT"),
+            NetMessage::DeclineDraw => write!(f, "DRAW DECLINE"),
+            NetMessage::Resign => write!(f, "RESIGN"),
+            NetMessage::Sync(fen) => write!(f, "SYNC {}", fen),
+            NetMessage::RequestSync => write!(f, "RESYNC"),
+        };
+    }
+}
+
+/// A connection to the other player.
+#[derive(Debug)]
+pub struct Connection {
+    reader: BufReader<TcpStream>,
+    writer: TcpStream,
+    /// True if a `NetMessage::Sync` is expected, after sending `NetMessage::RequestSync` or when joining.
+    sync_requested: bool,
+}
+
+impl Connection {
+    /// Constructor for a connection over `stream`.
+    pub fn new(stream: TcpStream) -> io::Result<Connection> {
+        return Ok(Connection {
+            reader: BufReader::new(stream.try_clone()?),
+            writer: stream,
+            sync_requested: false,
+        });
+    }
+
+    /// Waits for the other player to join at `addr`, e.g. "0.0.0.0:7878", and returns the connection to them.
+    pub fn host(addr: impl ToSocketAddrs) -> io::Result<Connection> {
+        let listener = TcpListener::bind(addr)?;
+        let (stream, _) = listener.accept()?;
+        return Connection::new(stream);
+    }
+
+    /// Joins the player hosting at `addr`, e.g. "192.168.0.2:7878", and returns the connection to them.
+    pub fn join(addr: impl ToSocketAddrs) -> io::Result<Connection> {
+        let mut connection = Connection::new(TcpStream::connect(addr)?)?;
+        // the host sends the starting position
+        connection.sync_requested = true;
+        return Ok(connection);
+    }
+
+    /// Sends `message` to the other player.
+    pub fn send(&mut self, message: &NetMessage) -> io::Result<()> {
+        if *message == NetMessage::RequestSync {
+            self.sync_requested = true;
+        }
+        writeln!(self.writer, "{}", message)?;
+        return self.writer.flush();
+    }
+
+    /// Waits for the next message from the other player. Returns None if the other player has left.
+    ///
+    /// Errors if the connection fails, or with `io::ErrorKind::InvalidData` if a line is not a message
+    /// or is a `NetMessage::Sync` that was not asked for.
+    pub fn receive(&mut self) -> io::Result<Option<NetMessage>> {
+        let mut line = String::new();
+        if self.reader.read_line(&mut line)? == 0 {
+            return Ok(None);
+        }
+        let message = NetMessage::parse(&line)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+        if let NetMessage::Sync(_) = message {
+            if !self.sync_requested {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "received SYNC without asking for it with RESYNC",
+                ));
+            }
+            self.sync_requested = false;
+        }
+        return Ok(Some(message));
+    }
+}
+
+impl Game {
+    /// Applies `message` sent by the player of `sender`: makes the move, offers, accepts or declines a draw,
+    /// resigns, or catches up with the position of a `NetMessage::Sync`. Hello and RequestSync do not
+    /// change the game.
+    ///
+    /// A synced position is only taken if it is the position of the game, which is then not changed, or the position
+    /// after one of its possible moves, which is then made. So the history is kept, and the other player cannot set
+    /// up a position that the game does not reach.
+    ///
+    /// Errors if the move is not legal or not `sender`'s, a draw is answered without an offer from the other colour,
+    /// the synced position is not reached by at most one move, or the game is over, as for the methods applied.
+    /// The game is not changed if an error is returned.
+    pub fn apply_message(
+        &mut self,
+        message: &NetMessage,
+        sender: Colour,
+    ) -> Result<(), ChessError> {
+        match message {
+            NetMessage::Move(mv) => {
+                if sender != self.active_colour {
+                    return Err(ChessError::WrongColour);
+                }
+                self.play_move(*mv)?;
+            }
+            NetMessage::OfferDraw => self.offer_draw(sender)?,
+            NetMessage::AcceptDraw | NetMessage::DeclineDraw => {
+                if self.draw_offer != Some(sender.invert()) {
+                    return Err(ChessError::NoDrawOffer);
+                }
+                if *message == NetMessage::AcceptDraw {
+                    self.accept_draw(sender)?;
+                } else {
+                    self.decline_draw()?;
+                }
+            }
+            NetMessage::Resign => {
+                self.resign(sender)?;
+            }
+            NetMessage::Sync(fen) => {
+                let synced = Game::from_fen(fen)?.position_key();
+                if self.position_key() == synced {
+                    return Ok(());
+                }
+                // the other player may have made a move that was not received
+                let mv = self.get_all_possible_moves().into_iter().find(|mv| {
+                    let mut game = self.clone();
+                    return game.play_move(*mv).is_ok() && game.position_key() == synced;
+                });
+                match mv {
+                    Some(mv) => self.play_move(mv)?,
+                    None => {
+                        return Err(ChessError::InvalidMessage(format!(
+                            "The position '{}' is not reached by a move from this game",
+                            fen
+                        )))
+                    }
+                };
+            }
+            NetMessage::Hello(_) | NetMessage::RequestSync => {}
+        }
+        return Ok(());
+    }
+}
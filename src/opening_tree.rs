@@ -0,0 +1,169 @@
+// Author: Eskil Nyberg
+
+/*!
+ * Opening trees built from played games, exportable as Graphviz DOT graphs.
+ */
+
+use super::Game;
+
+/// A tree (strictly, a graph, since transpositions are merged) of the positions reached in a set of games,
+/// with the moves between them and how often each move was played.
+///
+/// # Example code
+///
+/// ```rust
+/// use chess_engine::*;
+///
+/// let mut game = Game::new();
+/// game.make_move("e2", "e4").unwrap();
+///
+/// let mut tree = OpeningTree::new(10);
+/// tree.add_game(&game);
+/// let dot = tree.to_dot(); // render with e.g. `dot -Tsvg`
+/// assert!(dot.contains("e2e4 (1)"));
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct OpeningTree {
+    /// How many plies of every game that are added to the tree.
+    max_plies: usize,
+    /// The positions in the tree, identified by the first four FEN fields, in the order they were found.
+    positions: Vec<String>,
+    /// The moves in the tree as (index of position from, index of position to, move, frequency).
+    moves: Vec<(usize, usize, String, u32)>,
+}
+
+impl OpeningTree {
+    /// Constructor for an empty tree that includes the first `max_plies` half-moves of every added game.
+    pub fn new(max_plies: usize) -> OpeningTree {
+        return OpeningTree {
+            max_plies,
+            positions: vec![],
+            moves: vec![],
+        };
+    }
+
+    /// Adds the moves of `game` to the tree.
+    pub fn add_game(&mut self, game: &Game) {
+        let history = game.get_history();
+        let mut fens: Vec<String> = history.iter().map(|entry| entry.fen.clone()).collect();
+        fens.push(game.fen());
+
+        for (i, entry) in history.iter().enumerate().take(self.max_plies) {
+            let from_idx = self.position_idx(&fens[i]);
+            let to_idx = self.position_idx(&fens[i + 1]);
+            let mv = format!("{}{}", entry.from, entry.to);
+            match self
+                .moves
+                .iter_mut()
+                .find(|(from, to, m, _)| *from == from_idx && *to == to_idx && *m == mv)
+            {
+                Some((_, _, _, frequency)) => *frequency += 1,
+                None => self.moves.push((from_idx, to_idx, mv, 1)),
+            }
+        }
+    }
+
+    /// Adds the moves of every game in `games` to the tree.
+    pub fn add_games(&mut self, games: &[Game]) {
+        for game in games {
+            self.add_game(game);
+        }
+    }
+
+    /// Returns the tree as a Graphviz DOT graph.
+    ///
+    /// Positions are nodes labelled with their piece placement, and moves are edges labelled with
+    /// the move and its frequency. More frequent moves are drawn with thicker lines.
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::from("digraph opening_tree {\n");
+        dot.push_str("    node [shape=box, fontname=\"monospace\"];\n");
+        for (i, position) in self.positions.iter().enumerate() {
+            let placement = position.split(' ').next().expect("fen");
+            dot.push_str(&format!(
+                "    p{} [label=\"{}\"];\n",
+                i,
+                placement.replace('/', "\\n")
+            ));
+        }
+        for (from, to, mv, frequency) in &self.moves {
+            dot.push_str(&format!(
+                "    p{} -> p{} [label=\"{} ({})\", penwidth={}];\n",
+                from, to, mv, frequency, frequency
+            ));
+        }
+        dot.push('}');
+        return dot;
+    }
+
+    /// Returns the moves played from the position with FEN `fen` with their frequencies, most frequent first.
+    fn book_moves(&self, fen: &str) -> Vec<(String, u32)> {
+        let key = position_key(fen);
+        let from_idx = match self.positions.iter().position(|p| *p == key) {
+            None => return vec![],
+            Some(idx) => idx,
+        };
+        let mut moves: Vec<(String, u32)> = self
+            .moves
+            .iter()
+            .filter(|(from, _, _, _)| *from == from_idx)
+            .map(|(_, _, mv, frequency)| (mv.clone(), *frequency))
+            .collect();
+        moves.sort_by_key(|(_, frequency)| std::cmp::Reverse(*frequency));
+        return moves;
+    }
+
+    /// Returns the index of the position with FEN `fen`, adding it to the tree if it is new.
+    fn position_idx(&mut self, fen: &str) -> usize {
+        let key = position_key(fen);
+        match self.positions.iter().position(|p| *p == key) {
+            Some(idx) => return idx,
+            None => {
+                self.positions.push(key);
+                return self.positions.len() - 1;
+            }
+        }
+    }
+}
+
+/// Where a game left the opening book, see `Game::left_book_at`.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct BookExit {
+    /// The half-move (counted from 0 at the start of the game) that left the book.
+    pub ply: usize,
+    /// The move number of that half-move, counted from 1 at the start of the game.
+    pub move_number: usize,
+    /// The move played, on the format XFXF, e.g. "e2e4".
+    pub played: String,
+    /// The most frequent book move in the position, on the same format. Is None if the book has no moves
+    /// for the position, i.e. the game reached the end of the known theory.
+    pub book_move: Option<String>,
+}
+
+impl Game {
+    /// Returns where this game deviated from the moves in the opening book `book`,
+    /// with the most frequent book move of the position for post-game reports.
+    ///
+    /// Is None if every move of the game is in the book.
+    pub fn left_book_at(&self, book: &OpeningTree) -> Option<BookExit> {
+        for (ply, entry) in self.history.iter().enumerate() {
+            let played = format!("{}{}", entry.from, entry.to);
+            let book_moves = book.book_moves(&entry.fen);
+            if !book_moves.iter().any(|(mv, _)| *mv == played) {
+                return Some(BookExit {
+                    ply,
+                    move_number: ply / 2 + 1,
+                    played,
+                    book_move: book_moves.first().map(|(mv, _)| mv.clone()),
+                });
+            }
+        }
+        return None;
+    }
+}
+
+/// Returns the first four FEN fields of `fen`, which identify a position in the tree.
+///
+/// The clocks are left out so that transpositions are merged.
+fn position_key(fen: &str) -> String {
+    return fen.split(' ').take(4).collect::<Vec<&str>>().join(" ");
+}
@@ -0,0 +1,286 @@
+// Author: Eskil Nyberg
+
+/*!
+ * Swiss tournament pairing, following the Dutch system.
+ *
+ * Players are ranked by score and then by rating. Each score group is split into an upper and a lower half,
+ * and the n:th player of the upper half is paired with the n:th player of the lower half. Players that cannot
+ * be paired within their score group float down to the next one. No two players meet twice, no player gets
+ * more than one bye, and two players that must both have the same colour are never paired.
+ *
+ * # Example code
+ *
+ * ```rust
+ * use chess_engine::pairing::*;
+ * use chess_engine::Colour;
+ *
+ * let players: Vec<PairingPlayer> = (1..=4).map(|id| PairingPlayer::new(id, 2000 - id as u32)).collect();
+ * let round = pair_round(&players).unwrap();
+ * assert_eq!(round.pairings, vec![Pairing { white: 1, black: 3 }, Pairing { white: 4, black: 2 }]);
+ * ```
+ */
+
+use super::ChessError;
+use super::Colour;
+
+/// A player in a Swiss tournament, with the results needed to pair the next round.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PairingPlayer {
+    /// Some unique id for the player.
+    pub id: usize,
+    /// The rating of the player, used for ranking players with equal scores.
+    pub rating: u32,
+    /// The score of the player so far, one point per win and half a point per draw (a bye counts as a win).
+    pub score: f32,
+    /// The ids of the players that this player has met, in order.
+    pub opponents: Vec<usize>,
+    /// The colours this player has played, in order. Rounds with a bye are left out.
+    pub colours: Vec<Colour>,
+    /// True if the player has had a bye.
+    pub had_bye: bool,
+}
+
+impl PairingPlayer {
+    /// Constructor for a player with id `id` and rating `rating` that has not played yet.
+    pub fn new(id: usize, rating: u32) -> PairingPlayer {
+        return PairingPlayer {
+            id,
+            rating,
+            score: 0.0,
+            opponents: vec![],
+            colours: vec![],
+            had_bye: false,
+        };
+    }
+
+    /// Returns the colour this player should get next, and how strongly (higher is stronger, 0 is no preference).
+    ///
+    /// A player with a colour difference above one, or with the same colour twice in a row, must get the other colour (3).
+    /// A player with a colour difference of one should get the other colour (2).
+    /// Otherwise, a player should alternate colours (1).
+    fn colour_preference(&self) -> (Option<Colour>, u8) {
+        let whites = self.colours.iter().filter(|c| c.is_white()).count() as i32;
+        let difference = whites - (self.colours.len() as i32 - whites);
+        let last = self.colours.last().copied();
+        let repeated = self.colours.len() >= 2
+            && self.colours[self.colours.len() - 1] == self.colours[self.colours.len() - 2];
+        if difference > 1 || repeated && last == Some(Colour::White) {
+            return (Some(Colour::Black), 3);
+        }
+        if difference < -1 || repeated && last == Some(Colour::Black) {
+            return (Some(Colour::White), 3);
+        }
+        if difference == 1 {
+            return (Some(Colour::Black), 2);
+        }
+        if difference == -1 {
+            return (Some(Colour::White), 2);
+        }
+        return match last {
+            Some(colour) => (Some(colour.invert()), 1),
+            None => (None, 0),
+        };
+    }
+}
+
+/// A game in a round, given as the ids of the players.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Pairing {
+    pub white: usize,
+    pub black: usize,
+}
+
+/// The pairings of a round, ordered by board.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct RoundPairings {
+    pub pairings: Vec<Pairing>,
+    /// The id of the player that gets a bye, if the number of players is odd.
+    pub bye: Option<usize>,
+}
+
+/// Pairs the next round for `players` following the Dutch system.
+///
+/// Errors if the ids are not unique, or if no pairing satisfies the rules
+/// (e.g. in a small tournament where everyone has already met).
+pub fn pair_round(players: &[PairingPlayer]) -> Result<RoundPairings, ChessError> {
+    for (i, player) in players.iter().enumerate() {
+        if players[..i].iter().any(|p| p.id == player.id) {
+            return Err(ChessError::PairingFailed(format!(
+                "The player id {} is used more than once",
+                player.id
+            )));
+        }
+    }
+
+    // Rank the players by score, then rating, then id.
+    let mut ranked: Vec<&PairingPlayer> = players.iter().collect();
+    ranked.sort_by(|a, b| {
+        b.score
+            .partial_cmp(&a.score)
+            .expect("scores are not NaN")
+            .then(b.rating.cmp(&a.rating))
+            .then(a.id.cmp(&b.id))
+    });
+
+    // With an odd number of players, the lowest ranked player that has not had a bye gets one.
+    let mut bye_candidates: Vec<Option<usize>> = vec![None];
+    if ranked.len() % 2 == 1 {
+        bye_candidates = (0..ranked.len())
+            .rev()
+            .filter(|i| !ranked[*i].had_bye)
+            .map(Some)
+            .collect();
+    }
+
+    for bye in bye_candidates {
+        let mut remaining: Vec<&PairingPlayer> = ranked.clone();
+        if let Some(i) = bye {
+            remaining.remove(i);
+        }
+        let preferences = opponent_preferences(&remaining);
+        let mut paired = vec![false; remaining.len()];
+        let mut games = vec![];
+        if pair_remaining(&remaining, &preferences, &mut paired, &mut games) {
+            // The games are found in the order of the higher ranked player, which is also the board order.
+            let pairings: Vec<Pairing> = games
+                .iter()
+                .enumerate()
+                .map(|(board, (a, b))| allocate_colours(remaining[*a], remaining[*b], board))
+                .collect();
+            return Ok(RoundPairings {
+                pairings,
+                bye: bye.map(|i| ranked[i].id),
+            });
+        }
+    }
+    return Err(ChessError::PairingFailed(
+        "No pairing avoids rematches, repeated byes and colour conflicts".to_owned(),
+    ));
+}
+
+/// Returns, for every player in `ranked`, the indices of the lower ranked players in the order they should be tried as opponents.
+///
+/// Within a score group, the n:th player of the upper half prefers the n:th player of the lower half, then the rest
+/// of the lower half, then the rest of the group. Lower score groups are tried last, in ranking order.
+fn opponent_preferences(ranked: &[&PairingPlayer]) -> Vec<Vec<usize>> {
+    let mut preferences = vec![];
+    for i in 0..ranked.len() {
+        let group_start = (0..i)
+            .rev()
+            .take_while(|j| ranked[*j].score == ranked[i].score)
+            .last()
+            .unwrap_or(i);
+        let group_end = (i..ranked.len())
+            .take_while(|j| ranked[*j].score == ranked[i].score)
+            .last()
+            .expect("contains i")
+            + 1;
+        let half = (group_end - group_start) / 2;
+
+        let mut order = vec![];
+        let ideal = i + half;
+        if i < group_start + half {
+            order.extend(ideal..group_end);
+            order.extend(group_start + half..ideal);
+        }
+        order.extend(i + 1..group_end);
+        order.extend(group_end..ranked.len());
+        let mut unique = vec![];
+        for j in order {
+            if !unique.contains(&j) {
+                unique.push(j);
+            }
+        }
+        preferences.push(unique);
+    }
+    return preferences;
+}
+
+/// Pairs every unpaired player, highest ranked first, backtracking when a later player cannot be paired.
+///
+/// Returns true if everyone was paired, with the games (as indices in `ranked`) pushed to `games`.
+fn pair_remaining(
+    ranked: &[&PairingPlayer],
+    preferences: &[Vec<usize>],
+    paired: &mut [bool],
+    games: &mut Vec<(usize, usize)>,
+) -> bool {
+    let i = match paired.iter().position(|p| !p) {
+        None => return true,
+        Some(i) => i,
+    };
+    paired[i] = true;
+    for &j in &preferences[i] {
+        if paired[j] || !compatible(ranked[i], ranked[j]) {
+            continue;
+        }
+        paired[j] = true;
+        games.push((i, j));
+        if pair_remaining(ranked, preferences, paired, games) {
+            return true;
+        }
+        games.pop();
+        paired[j] = false;
+    }
+    paired[i] = false;
+    return false;
+}
+
+/// Returns true if `a` and `b` may be paired: they have not met, and they do not both need the same colour.
+fn compatible(a: &PairingPlayer, b: &PairingPlayer) -> bool {
+    if a.opponents.contains(&b.id) {
+        return false;
+    }
+    let (colour_a, strength_a) = a.colour_preference();
+    let (colour_b, strength_b) = b.colour_preference();
+    return !(strength_a == 3 && strength_b == 3 && colour_a == colour_b);
+}
+
+/// Returns the colours for the game between `higher` and `lower` (ranked in that order) on `board` (counted from 0).
+///
+/// Both players get their preferred colour if possible, otherwise the stronger preference wins. With equally strong
+/// preferences, the players alternate from the last round where they had different colours, or else the higher ranked
+/// player gets their preference. In the first round, the higher ranked player gets white on every other board.
+fn allocate_colours(higher: &PairingPlayer, lower: &PairingPlayer, board: usize) -> Pairing {
+    let (colour_higher, strength_higher) = higher.colour_preference();
+    let (colour_lower, strength_lower) = lower.colour_preference();
+
+    let higher_colour = match (colour_higher, colour_lower) {
+        (None, None) => match board % 2 {
+            0 => Colour::White,
+            _ => Colour::Black,
+        },
+        (Some(colour), None) => colour,
+        (None, Some(colour)) => colour.invert(),
+        (Some(a), Some(b)) if a != b => a,
+        (Some(a), Some(_)) => {
+            if strength_higher > strength_lower {
+                a
+            } else if strength_lower > strength_higher {
+                a.invert()
+            } else {
+                let last_different = higher
+                    .colours
+                    .iter()
+                    .rev()
+                    .zip(lower.colours.iter().rev())
+                    .find(|(h, l)| h != l);
+                match last_different {
+                    Some((h, _)) => h.invert(),
+                    None => a,
+                }
+            }
+        }
+    };
+
+    return match higher_colour {
+        Colour::White => Pairing {
+            white: higher.id,
+            black: lower.id,
+        },
+        Colour::Black => Pairing {
+            white: lower.id,
+            black: higher.id,
+        },
+    };
+}
@@ -0,0 +1,378 @@
+// Author: Eskil Nyberg
+
+/*!
+ * Portable Game Notation (PGN) import and export.
+ */
+
+use super::ChessError;
+use super::Colour;
+use super::Game;
+use super::GameOverReason;
+use super::GameState;
+use super::HistoryEntry;
+use super::STARTING_FEN;
+
+/// Movetext lines are wrapped before this many characters, as the PGN standard asks.
+const LINE_LENGTH: usize = 80;
+
+/// The move suffix annotations and the numeric annotation glyphs they stand for.
+const SUFFIX_NAGS: [(&str, u8); 6] = [
+    ("!", 1),
+    ("?", 2),
+    ("!!", 3),
+    ("??", 4),
+    ("!?", 5),
+    ("?!", 6),
+];
+
+impl Game {
+    /// Constructor that loads a game from PGN and replays its moves, so the game has a full history.
+    ///
+    /// Tag pairs, comments (`{...}` and `;...`), variations (`(...)`), numeric annotation glyphs (`$1`),
+    /// move numbers and result markers (`1-0`, `0-1`, `1/2-1/2`, `*`) are tolerated.
+    /// Comments, numeric annotation glyphs and suffix annotations (e.g. `!?`) after a move are kept on the move,
+    /// see `set_comment` and `set_nags`. Comments before the first move, those in variations and the comment
+    /// that `to_pgn` writes on how the game ended are skipped.
+    /// If the game has a `FEN` tag, the moves are replayed from that position.
+    /// Only the first game is loaded if `pgn` contains several.
+    ///
+    /// Errors if some move is not legal, or a tag pair or FEN is invalid.
+    pub fn from_pgn(pgn: &str) -> Result<Game, ChessError> {
+        let mut game = Game::new();
+        let mut chars = pgn.chars();
+        let mut token = String::new();
+        let mut seen_move = false;
+        // A comment is kept on the last move once it is clear that it is not the comment on how the game ended
+        let mut pending_comment: Option<String> = None;
+
+        loop {
+            let ch = chars.next();
+            // Tokens end at whitespace and at the start of comments, variations and tag pairs.
+            let ends_token = match ch {
+                Some(ch) => ch.is_whitespace() || "{;([".contains(ch),
+                None => true,
+            };
+            if ends_token && !token.is_empty() {
+                // Strip move numbers, e.g. "12." or "12...", which may be written together with the move.
+                let san = match token.rfind('.') {
+                    Some(idx) => &token[idx + 1..],
+                    None => &token,
+                };
+                if ["1-0", "0-1", "1/2-1/2", "*"].contains(&token.as_str()) {
+                    if let Some(comment) = pending_comment.take() {
+                        if !game.is_game_over_description(&comment) {
+                            game.add_comment(comment);
+                        }
+                    }
+                    break; // result marker, the game is over
+                } else if let Some(nag) = san.strip_prefix('$') {
+                    if let (Ok(nag), Some(entry)) = (nag.parse(), game.history.last_mut()) {
+                        entry.nags.push(nag);
+                    }
+                } else if !san.is_empty() {
+                    if let Some(comment) = pending_comment.take() {
+                        game.add_comment(comment);
+                    }
+                    let move_number = game.history.len() / 2 + 1;
+                    let dots = if game.active_colour.is_white() {
+                        "."
+                    } else {
+                        "..."
+                    };
+                    if let Err(err) = game.make_move_san(san) {
+                        return Err(ChessError::InvalidPgn(format!(
+                            "Move {}{} '{}': {}",
+                            move_number, dots, san, err
+                        )));
+                    }
+                    let suffix = &san[san.trim_end_matches(['!', '?']).len()..];
+                    if let Some((_, nag)) = SUFFIX_NAGS.iter().find(|(glyph, _)| *glyph == suffix) {
+                        let entry = game.history.last_mut().expect("a move was made");
+                        entry.nags.push(*nag);
+                    }
+                    seen_move = true;
+                }
+                token.clear();
+            }
+
+            match ch {
+                None => break,
+                Some(ch @ '{') | Some(ch @ ';') => {
+                    // comment until } or the end of the line
+                    let end = if ch == '{' { '}' } else { '\n' };
+                    let comment: String = chars.by_ref().take_while(|c| *c != end).collect();
+                    let comment = comment.split_whitespace().collect::<Vec<&str>>().join(" ");
+                    if seen_move && !comment.is_empty() {
+                        if let Some(pending) = pending_comment.replace(comment) {
+                            game.add_comment(pending);
+                        }
+                    }
+                }
+                Some('(') => {
+                    // variation until the matching ), which may contain variations and comments
+                    let mut depth = 1;
+                    while depth > 0 {
+                        match chars.next() {
+                            Some('(') => depth += 1,
+                            Some(')') => depth -= 1,
+                            Some('{') => {
+                                for c in chars.by_ref() {
+                                    if c == '}' {
+                                        break;
+                                    }
+                                }
+                            }
+                            None => break,
+                            _ => {}
+                        }
+                    }
+                }
+                Some('[') => {
+                    // tag pair, e.g. [FEN "..."]
+                    let tag: String = chars.by_ref().take_while(|c| *c != ']').collect();
+                    let (name, value) = match tag.split_once('"') {
+                        Some((name, value)) => {
+                            (name.trim(), value.trim_end().trim_end_matches('"'))
+                        }
+                        None => {
+                            return Err(ChessError::InvalidPgn(format!(
+                                "Invalid tag pair '[{}]'",
+                                tag
+                            )))
+                        }
+                    };
+                    if seen_move {
+                        break; // the tag pairs of the next game
+                    }
+                    if name == "FEN" {
+                        game = Game::from_fen(value)?;
+                    }
+                }
+                Some(ch) if ch.is_whitespace() => {}
+                Some(ch) => token.push(ch),
+            }
+        }
+
+        if let Some(comment) = pending_comment {
+            game.add_comment(comment);
+        }
+        return Ok(game);
+    }
+
+    /// Returns the game in PGN, with its moves written in SAN, e.g. to save it or open it in other chess software.
+    ///
+    /// The Seven Tag Roster is written with unknown values (`"?"`), except for the Result tag. A Termination tag
+    /// tells if the game ended normally, on time, or is unterminated. The game over reason, e.g. a resignation or
+    /// the draw rule applied, is written as a comment before the result marker, which `from_pgn` skips.
+    /// `SetUp` and `FEN` tags are written if the game did not start from the standard position,
+    /// and a `Variant` tag if other rules than standard chess are set.
+    ///
+    /// The numeric annotation glyphs and comments of the moves are written after them, see `set_nags` and `set_comment`.
+    /// A promotion that has not been chosen yet is left out.
+    ///
+    /// # Example code
+    ///
+    /// ```rust
+    /// use chess_engine::*;
+    ///
+    /// let mut game = Game::new();
+    /// game.make_move("e2", "e4").unwrap();
+    /// game.resign(Colour::Black).unwrap();
+    /// let pgn = game.to_pgn();
+    /// assert!(pgn.contains("[Result \"1-0\"]"));
+    /// assert!(pgn.ends_with("1. e4 {Black resigns} 1-0\n"));
+    /// ```
+    pub fn to_pgn(&self) -> String {
+        let result = self.result_string().unwrap_or("*");
+        let termination = if !self.is_gameover() {
+            "unterminated"
+        } else if let Some(GameOverReason::Timeout(_)) | Some(GameOverReason::TimeoutDraw(_)) =
+            self.game_over_reason
+        {
+            "time forfeit"
+        } else {
+            "normal"
+        };
+        let start_fen = match self.history.first() {
+            Some(entry) => entry.fen().to_string(),
+            None => self.fen(),
+        };
+
+        let mut pgn = String::new();
+        for (name, value) in [
+            ("Event", "?"),
+            ("Site", "?"),
+            ("Date", "????.??.??"),
+            ("Round", "?"),
+            ("White", "?"),
+            ("Black", "?"),
+            ("Result", result),
+            ("Termination", termination),
+        ] {
+            pgn.push_str(&format!("[{} \"{}\"]\n", name, value));
+        }
+        if self.get_rules().name() != "Standard" {
+            pgn.push_str(&format!("[Variant \"{}\"]\n", self.get_rules().name()));
+        }
+        // The starting position needs no FEN tag
+        if start_fen != STARTING_FEN {
+            pgn.push_str(&format!("[SetUp \"1\"]\n[FEN \"{}\"]\n", start_fen));
+        }
+        pgn.push('\n');
+
+        // The movetext, as tokens to be wrapped into lines
+        let mut tokens = vec![];
+        let mut moves = self.history_san();
+        if self.state == GameState::WaitingOnPromotionChoice {
+            moves.pop();
+        }
+        let mut after_comment = false;
+        for (entry, san) in self.history.iter().zip(moves) {
+            if entry.piece_moved.is_white() {
+                tokens.push(format!("{}. {}", entry.move_number(), san));
+            } else if tokens.is_empty() || after_comment {
+                tokens.push(format!("{}... {}", entry.move_number(), san));
+            } else {
+                tokens.push(san);
+            }
+            for nag in &entry.nags {
+                tokens.push(format!("${}", nag));
+            }
+            // A comment cannot contain its closing brace
+            after_comment = entry.comment.is_some();
+            if let Some(comment) = &entry.comment {
+                tokens.push(format!("{{{}}}", comment.replace('}', ")")));
+            }
+        }
+        if let Some(reason) = self.game_over_reason {
+            tokens.push(format!("{{{}}}", self.describe_game_over(reason)));
+        }
+        tokens.push(result.to_string());
+
+        let mut line_length = 0;
+        for token in tokens {
+            if line_length > 0 && line_length + 1 + token.len() >= LINE_LENGTH {
+                pgn.push('\n');
+                line_length = 0;
+            } else if line_length > 0 {
+                pgn.push(' ');
+                line_length += 1;
+            }
+            line_length += token.len();
+            pgn.push_str(&token);
+        }
+        pgn.push('\n');
+        return pgn;
+    }
+
+    /// Sets the comment on the `ply`-th half-move of the history, counting from 1, or removes it with None.
+    /// The comment is written after the move by `to_pgn`.
+    ///
+    /// Errors if fewer than `ply` half-moves have been made.
+    ///
+    /// # Example code
+    ///
+    /// ```rust
+    /// use chess_engine::*;
+    ///
+    /// let mut game = Game::new();
+    /// game.make_move_san("e4").unwrap();
+    /// game.make_move_san("e5").unwrap();
+    /// game.set_comment(1, Some("Best by test")).unwrap();
+    /// game.set_nags(2, &[5]).unwrap();
+    /// assert!(game.to_pgn().ends_with("1. e4 {Best by test} 1... e5 $5 *\n"));
+    /// assert_eq!(Game::from_pgn(&game.to_pgn()).unwrap().get_history(), game.get_history());
+    /// ```
+    pub fn set_comment(&mut self, ply: usize, comment: Option<&str>) -> Result<(), ChessError> {
+        self.history_entry_mut(ply)?.comment = comment.map(|comment| comment.to_string());
+        return Ok(());
+    }
+
+    /// Sets the numeric annotation glyphs of the `ply`-th half-move of the history, counting from 1, e.g. 1 for a good
+    /// move (!), 2 for a mistake (?), 3 for a brilliant move (!!), 4 for a blunder (??), 5 for an interesting move (!?)
+    /// and 6 for a dubious move (?!). The glyphs are written as `$1` etc. after the move by `to_pgn`.
+    ///
+    /// Errors if fewer than `ply` half-moves have been made.
+    pub fn set_nags(&mut self, ply: usize, nags: &[u8]) -> Result<(), ChessError> {
+        self.history_entry_mut(ply)?.nags = nags.to_vec();
+        return Ok(());
+    }
+
+    /// Returns the `ply`-th half-move of the history, counting from 1.
+    fn history_entry_mut(&mut self, ply: usize) -> Result<&mut HistoryEntry, ChessError> {
+        if ply == 0 {
+            return Err(ChessError::NoSuchPly(ply));
+        }
+        return self
+            .history
+            .get_mut(ply - 1)
+            .ok_or(ChessError::NoSuchPly(ply));
+    }
+
+    /// Adds `comment` to the comment on the last move, for `from_pgn`.
+    fn add_comment(&mut self, comment: String) {
+        if let Some(entry) = self.history.last_mut() {
+            entry.comment = Some(match entry.comment.take() {
+                Some(existing) => format!("{} {}", existing, comment),
+                None => comment,
+            });
+        }
+    }
+
+    /// Returns true if `comment` is how `to_pgn` writes that the game ended, for some way it can have ended.
+    fn is_game_over_description(&self, comment: &str) -> bool {
+        let mut reasons = vec![
+            GameOverReason::Checkmate,
+            GameOverReason::Stalemate,
+            GameOverReason::SeventyFiveMoveRule,
+            GameOverReason::FivefoldRepetitionRule,
+            GameOverReason::InsufficientMaterial,
+            GameOverReason::DeadPosition,
+            GameOverReason::ManualDraw,
+            GameOverReason::DrawByAgreement,
+            GameOverReason::FiftyMoveRule,
+            GameOverReason::ThreefoldRepetition,
+        ];
+        for colour in [Colour::White, Colour::Black] {
+            reasons.push(GameOverReason::VariantWin(colour));
+            reasons.push(GameOverReason::Resignation(colour));
+            reasons.push(GameOverReason::Timeout(colour));
+            reasons.push(GameOverReason::TimeoutDraw(colour));
+        }
+        return reasons
+            .into_iter()
+            .any(|reason| self.describe_game_over(reason) == comment);
+    }
+
+    /// Returns how the game ended by `reason`, for the comment before the result marker of `to_pgn`.
+    fn describe_game_over(&self, reason: GameOverReason) -> String {
+        return match reason {
+            GameOverReason::Checkmate => {
+                format!("{} wins by checkmate", self.active_colour.invert())
+            }
+            GameOverReason::Stalemate => "Draw by stalemate".to_string(),
+            GameOverReason::SeventyFiveMoveRule => "Draw by the 75-move rule".to_string(),
+            GameOverReason::FivefoldRepetitionRule => "Draw by fivefold repetition".to_string(),
+            GameOverReason::InsufficientMaterial => "Draw by insufficient material".to_string(),
+            GameOverReason::DeadPosition => "Draw by dead position".to_string(),
+            GameOverReason::ManualDraw => "Draw".to_string(),
+            GameOverReason::DrawByAgreement => "Draw by agreement".to_string(),
+            GameOverReason::FiftyMoveRule => "Draw by the 50-move rule".to_string(),
+            GameOverReason::ThreefoldRepetition => "Draw by threefold repetition".to_string(),
+            GameOverReason::VariantWin(winner) => {
+                format!(
+                    "{} wins by the rules of {}",
+                    winner,
+                    self.get_rules().name()
+                )
+            }
+            GameOverReason::Resignation(colour) => format!("{} resigns", colour),
+            GameOverReason::Timeout(colour) => format!("{} runs out of time", colour),
+            GameOverReason::TimeoutDraw(colour) => format!(
+                "Draw as {} runs out of time but {} cannot checkmate",
+                colour,
+                colour.invert()
+            ),
+        };
+    }
+}
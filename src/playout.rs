@@ -0,0 +1,63 @@
+// Author: Eskil Nyberg
+
+/*!
+ * Random playouts, where random moves are played until the game is over, e.g. for Monte Carlo tree search.
+ */
+
+use super::Colour;
+use super::Game;
+
+/// How a random playout ended, see `Game::simulate_random_playout`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum PlayoutResult {
+    /// The colour won the game.
+    Win(Colour),
+    /// The game was drawn.
+    Draw,
+    /// The game was not over after the most half-moves allowed.
+    Unfinished,
+}
+
+impl Game {
+    /// Plays random moves (see `random_legal_move`) from the position until the game is over or `max_plies`
+    /// half-moves have been played, and returns how the game ended. The game itself is left untouched.
+    /// Needs the `rand` feature.
+    ///
+    /// The game ends by the same rules as when moves are made, e.g. checkmate, stalemate, insufficient material,
+    /// the 75-move rule and fivefold repetition.
+    ///
+    /// # Example code
+    ///
+    /// ```rust
+    /// use chess_engine::*;
+    /// use rand::SeedableRng;
+    ///
+    /// let mut rng = rand::rngs::StdRng::seed_from_u64(1);
+    /// // A lone king can only draw
+    /// let game = Game::from_fen("4k3/8/8/8/8/8/8/3QK3 b - - 0 1").unwrap();
+    /// let result = game.simulate_random_playout(1000, &mut rng);
+    /// assert_ne!(result, PlayoutResult::Win(Colour::Black));
+    /// ```
+    pub fn simulate_random_playout(
+        &self,
+        max_plies: usize,
+        rng: &mut impl rand::Rng,
+    ) -> PlayoutResult {
+        let mut game = self.clone();
+        for _ in 0..max_plies {
+            match game.random_legal_move(rng) {
+                Some(mv) => {
+                    game.play_move(mv).expect("possible moves are legal");
+                }
+                None => break,
+            }
+        }
+        if !game.is_gameover() {
+            return PlayoutResult::Unfinished;
+        }
+        return match game.get_winner() {
+            Some(winner) => PlayoutResult::Win(winner),
+            None => PlayoutResult::Draw,
+        };
+    }
+}
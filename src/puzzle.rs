@@ -0,0 +1,75 @@
+// Author: Eskil Nyberg
+
+/*!
+ * Checking of move sequences, e.g. the solution lines of tactics puzzles.
+ */
+
+use std::error::Error;
+use std::fmt;
+
+use super::ChessError;
+use super::Game;
+use super::Move;
+
+/// The move of a line that could not be played, see `Game::verify_line`.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct LineError {
+    /// The index of the move in the line, counted from 0, so that even indices are moves of the colour to move first.
+    pub index: usize,
+    /// The move as it was written in the line.
+    pub mv: String,
+    /// Why the move could not be played.
+    pub error: ChessError,
+}
+
+impl fmt::Display for LineError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        return write!(
+            f,
+            "Move {} of the line, '{}', could not be played: {}",
+            self.index + 1,
+            self.mv,
+            self.error
+        );
+    }
+}
+
+impl Error for LineError {}
+
+impl Game {
+    /// Checks that the moves of `line` can be played one after another from the position,
+    /// e.g. the solution of a puzzle including the opponent's replies. The game itself is left untouched.
+    ///
+    /// Each move is written in coordinate notation (as in UCI), e.g. "e2e4", or in SAN, e.g. "Nf3" or "O-O".
+    ///
+    /// Errors with the first move that is not legal or cannot be read, and its index in the line.
+    ///
+    /// # Example code
+    ///
+    /// ```rust
+    /// use chess_engine::*;
+    ///
+    /// let game = Game::new();
+    /// assert!(game.verify_line(&["e4", "e7e5", "Qh5", "Nc6", "Bc4", "Nf6", "Qxf7#"]).is_ok());
+    ///
+    /// let err = game.verify_line(&["e4", "e5", "Nf3", "Nf3"]).unwrap_err();
+    /// assert_eq!(err.index, 3);
+    /// ```
+    pub fn verify_line(&self, line: &[&str]) -> Result<(), LineError> {
+        let mut game = self.clone();
+        for (index, mv) in line.iter().enumerate() {
+            let result = match Move::from_uci(mv) {
+                Ok(parsed) => game.play_move(parsed),
+                Err(_) => game.make_move_san(mv),
+            };
+            if let Err(error) = result {
+                return Err(LineError {
+                    index,
+                    mv: mv.to_string(),
+                    error,
+                });
+            }
+        }
+        return Ok(());
+    }
+}
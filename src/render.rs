@@ -0,0 +1,186 @@
+// Author: Eskil Nyberg
+
+/*!
+ * Rendering of the board for terminals, with chess glyphs, coordinates and optional ANSI colours.
+ */
+
+use super::Colour;
+use super::Game;
+use super::Piece;
+use super::Position;
+
+/// Options for `Game::render`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct RenderOptions {
+    /// The colour whose side of the board is at the bottom.
+    pub perspective: Colour,
+    /// Label the ranks to the left of the board and the files below it.
+    pub coordinates: bool,
+    /// Colour the squares and pieces with ANSI escape codes, for terminals that support them.
+    pub ansi_colours: bool,
+    /// Highlight the positions moved from and to by the last move. Needs `ansi_colours`.
+    pub highlight_last_move: bool,
+    /// Highlight the possible moves of the piece at this position, if any.
+    /// Without `ansi_colours`, only the empty positions among them are marked, with `•`.
+    pub targets_of: Option<Position>,
+}
+
+impl Default for RenderOptions {
+    /// White at the bottom, with coordinates and the last move highlighted, but without colours.
+    fn default() -> RenderOptions {
+        return RenderOptions {
+            perspective: Colour::White,
+            coordinates: true,
+            ansi_colours: false,
+            highlight_last_move: true,
+            targets_of: None,
+        };
+    }
+}
+
+/// ANSI escape codes for the backgrounds of light and dark squares, of the squares of the last move, and of targets.
+const LIGHT_SQUARE: &str = "\x1b[48;5;180m";
+const DARK_SQUARE: &str = "\x1b[48;5;137m";
+const LIGHT_LAST_MOVE: &str = "\x1b[48;5;186m";
+const DARK_LAST_MOVE: &str = "\x1b[48;5;143m";
+const LIGHT_TARGET: &str = "\x1b[48;5;115m";
+const DARK_TARGET: &str = "\x1b[48;5;72m";
+/// ANSI escape codes for the foregrounds of white and black pieces, and to reset all colours.
+const WHITE_PIECE: &str = "\x1b[97m";
+const BLACK_PIECE: &str = "\x1b[30m";
+const RESET: &str = "\x1b[0m";
+
+impl Game {
+    /// Returns the board drawn with Unicode chess glyphs, seen from white's side, with coordinates.
+    ///
+    /// Output example:
+    /// 8 ♜ ♞ ♝ ♛ ♚ ♝ ♞ ♜
+    /// 7 ♟ ♟ ♟ ♟ ♟ ♟ ♟ ♟
+    /// 6 · · · · · · · ·
+    /// 5 · · · · · · · ·
+    /// 4 · · · · · · · ·
+    /// 3 · · · · · · · ·
+    /// 2 ♙ ♙ ♙ ♙ ♙ ♙ ♙ ♙
+    /// 1 ♖ ♘ ♗ ♕ ♔ ♗ ♘ ♖
+    ///   a b c d e f g h
+    pub fn to_string_unicode(&self) -> String {
+        return self.render(&RenderOptions::default());
+    }
+
+    /// Returns the board drawn with Unicode chess glyphs on light and dark squares coloured with ANSI escape codes,
+    /// seen from `perspective`'s side, with coordinates and the last move highlighted.
+    ///
+    /// Meant to be printed in a terminal that supports 256 colours.
+    pub fn to_string_ansi(&self, perspective: Colour) -> String {
+        return self.render(&RenderOptions {
+            perspective,
+            ansi_colours: true,
+            ..RenderOptions::default()
+        });
+    }
+
+    /// Returns the board drawn with Unicode chess glyphs as set by `options`, see `RenderOptions`.
+    ///
+    /// # Example code
+    ///
+    /// ```rust
+    /// use chess_engine::*;
+    ///
+    /// let game = Game::new();
+    /// let board = game.render(&RenderOptions {
+    ///     perspective: Colour::Black,
+    ///     coordinates: false,
+    ///     targets_of: Some(Position::parse_str("g1").unwrap()),
+    ///     ..RenderOptions::default()
+    /// });
+    /// assert_eq!(board.lines().next(), Some("♖ ♘ ♗ ♔ ♕ ♗ ♘ ♖"));
+    /// assert_eq!(board.lines().nth(2), Some("• · • · · · · ·"));
+    /// ```
+    pub fn render(&self, options: &RenderOptions) -> String {
+        let targets = match options.targets_of {
+            Some(pos) => self.get_possible_moves(pos).unwrap_or_default(),
+            None => vec![],
+        };
+        let last_move = match self.last_move() {
+            Some(entry) if options.highlight_last_move => vec![entry.from(), entry.to()],
+            _ => vec![],
+        };
+        let (ranks, files): (Vec<usize>, Vec<usize>) = match options.perspective {
+            Colour::White => ((0..8).rev().collect(), (0..8).collect()),
+            Colour::Black => ((0..8).collect(), (0..8).rev().collect()),
+        };
+
+        let mut output = String::new();
+        for (i, rank) in ranks.iter().enumerate() {
+            if options.coordinates {
+                output.push_str(&format!("{} ", rank + 1));
+            }
+            for (j, file) in files.iter().enumerate() {
+                let pos = Position::new(*rank, *file).expect("rank and file are 0-7");
+                let piece = self.board[pos.idx];
+                if options.ansi_colours {
+                    // a1 (rank + file even) is a dark square
+                    let dark = (rank + file) % 2 == 0;
+                    output.push_str(
+                        match (targets.contains(&pos), last_move.contains(&pos), dark) {
+                            (true, _, false) => LIGHT_TARGET,
+                            (true, _, true) => DARK_TARGET,
+                            (false, true, false) => LIGHT_LAST_MOVE,
+                            (false, true, true) => DARK_LAST_MOVE,
+                            (false, false, false) => LIGHT_SQUARE,
+                            (false, false, true) => DARK_SQUARE,
+                        },
+                    );
+                    match piece {
+                        Some(piece) => {
+                            // The filled glyphs read best on coloured squares, coloured by the piece's colour
+                            let glyph = Piece {
+                                piece_type: piece.piece_type,
+                                colour: Colour::Black,
+                            }
+                            .to_char_unicode();
+                            let foreground = if piece.is_white() {
+                                WHITE_PIECE
+                            } else {
+                                BLACK_PIECE
+                            };
+                            output.push_str(&format!("{} {} ", foreground, glyph));
+                        }
+                        None if targets.contains(&pos) => {
+                            output.push_str(&format!("{} • ", BLACK_PIECE))
+                        }
+                        None => output.push_str("   "),
+                    }
+                } else {
+                    output.push(match piece {
+                        Some(piece) => piece.to_char_unicode(),
+                        None if targets.contains(&pos) => '•',
+                        None => '·',
+                    });
+                    if j < 7 {
+                        output.push(' ');
+                    }
+                }
+            }
+            if options.ansi_colours {
+                output.push_str(RESET);
+            }
+            if i < 7 || options.coordinates {
+                output.push('\n');
+            }
+        }
+
+        if options.coordinates {
+            let labels: Vec<String> = files
+                .iter()
+                .map(|file| ((b'a' + *file as u8) as char).to_string())
+                .collect();
+            if options.ansi_colours {
+                output.push_str(&format!("   {}", labels.join("  ")));
+            } else {
+                output.push_str(&format!("  {}", labels.join(" ")));
+            }
+        }
+        return output;
+    }
+}
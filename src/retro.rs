@@ -0,0 +1,218 @@
+// Author: Eskil Nyberg
+
+/*!
+ * Retrograde analysis: sanity checks for whether a position could have arisen in a real game.
+ *
+ * Useful for validating user-submitted puzzle positions.
+ */
+
+use super::Colour;
+use super::Game;
+use super::GameState;
+use super::PieceType;
+use super::Position;
+
+impl Game {
+    /// Runs a sanity retro-analysis of the current position and returns every reason found why it
+    /// cannot have been reached from the initial position. An empty vector means no problems were found.
+    ///
+    /// The analysis is not complete (a position without problems may still be unreachable), but it catches:
+    /// * missing kings, or more than one king of some colour,
+    /// * pawns on the first or last rank,
+    /// * more pawns and promoted pieces than a side's eight pawns allow,
+    /// * pawn structures that need more captures than there are missing opponent pieces,
+    /// * the side not to move being in check, or the side to move being checked by more than two pieces.
+    pub fn retro_analysis(&self) -> Vec<String> {
+        let mut problems = vec![];
+
+        for colour in [Colour::White, Colour::Black] {
+            let mut count = [0; 6]; // indexed as in `piece_type_idx`
+            let mut light_bishops = 0;
+            let mut dark_bishops = 0;
+            let mut pawn_files = vec![];
+            for (i, piece) in self.board.iter().enumerate() {
+                let piece = match piece {
+                    Some(piece) if piece.colour == colour => piece,
+                    _ => continue,
+                };
+                let pos = Position::new_from_idx(i).expect("enumerated");
+                count[piece_type_idx(piece.piece_type)] += 1;
+                match piece.piece_type {
+                    PieceType::Bishop => {
+                        if (pos.rank + pos.file) % 2 == 1 {
+                            light_bishops += 1;
+                        } else {
+                            dark_bishops += 1;
+                        }
+                    }
+                    PieceType::Pawn => {
+                        if pos.rank == 0 || pos.rank == 7 {
+                            problems.push(format!(
+                                "The {:?} pawn at {} is on the first or last rank",
+                                colour, pos
+                            ));
+                        }
+                        pawn_files.push(pos.file);
+                    }
+                    _ => {}
+                }
+            }
+
+            let kings = count[piece_type_idx(PieceType::King)];
+            if kings != 1 {
+                problems.push(format!("{:?} has {} kings, should have 1", colour, kings));
+            }
+
+            // Every piece beyond the initial setup must be a promoted pawn.
+            let pawns = count[piece_type_idx(PieceType::Pawn)];
+            let promoted = excess(count[piece_type_idx(PieceType::Queen)], 1)
+                + excess(count[piece_type_idx(PieceType::Rook)], 2)
+                + excess(count[piece_type_idx(PieceType::Knight)], 2)
+                + excess(light_bishops, 1)
+                + excess(dark_bishops, 1);
+            if pawns + promoted > 8 {
+                problems.push(format!(
+                    "{:?} has {} pawns and at least {} promoted pieces, which is more than 8 pawns allow",
+                    colour, pawns, promoted
+                ));
+            }
+
+            // Pawns can only change file by capturing.
+            let pawn_captures = min_pawn_captures(&mut pawn_files);
+            let opponent_pieces = self
+                .board
+                .iter()
+                .flatten()
+                .filter(|p| p.colour != colour)
+                .count();
+            let missing_opponent_pieces = 16 - opponent_pieces.min(16);
+            if pawn_captures > missing_opponent_pieces {
+                problems.push(format!(
+                    "The {:?} pawns need at least {} captures to reach their files, but only {} {:?} pieces are missing",
+                    colour,
+                    pawn_captures,
+                    missing_opponent_pieces,
+                    colour.invert()
+                ));
+            }
+        }
+
+        // The side that just moved can never have left its own king in check.
+        let inactive_colour = self.active_colour.invert();
+        if !self.checkers(inactive_colour).is_empty() {
+            problems.push(format!(
+                "{:?} is in check although it is {:?}'s turn",
+                inactive_colour, self.active_colour
+            ));
+        }
+        let checkers = self.checkers(self.active_colour).len();
+        if checkers > 2 {
+            problems.push(format!(
+                "{:?} is checked by {} pieces, but at most two pieces can give check at once",
+                self.active_colour, checkers
+            ));
+        }
+
+        return problems;
+    }
+
+    /// Returns true if the current piece placement and active colour can be reached from the initial position
+    /// within `plies` half-moves, otherwise false.
+    ///
+    /// NOTE! This is a brute-force search over every legal move sequence and is very expensive.
+    /// Keep `plies` small (at most 4 or so).
+    pub fn is_reachable_within(&self, plies: u32) -> bool {
+        let target = self.placement_key();
+        return Game::reaches(&Game::new(), &target, plies);
+    }
+
+    /// Returns the piece placement and active colour fields of the FEN, which identify the position for `is_reachable_within`.
+    fn placement_key(&self) -> String {
+        return self
+            .fen()
+            .split(' ')
+            .take(2)
+            .collect::<Vec<&str>>()
+            .join(" ");
+    }
+
+    /// Depth-first search used by `is_reachable_within`.
+    fn reaches(game: &Game, target: &str, plies_left: u32) -> bool {
+        if game.placement_key() == target {
+            return true;
+        }
+        if plies_left == 0 || game.is_gameover() {
+            return false;
+        }
+        for i in 0..64 {
+            if !game.board[i].is_some_and(|p| p.colour == game.active_colour) {
+                continue;
+            }
+            let from_pos = Position::new_from_idx(i).expect("enumerated");
+            for to_pos in game.get_possible_moves(from_pos).expect("enumerated") {
+                let mut next = game.clone();
+                if next.make_move_pos(from_pos, to_pos).is_err() {
+                    continue;
+                }
+                if next.get_game_state() == GameState::WaitingOnPromotionChoice {
+                    for piece_type in [
+                        PieceType::Queen,
+                        PieceType::Rook,
+                        PieceType::Bishop,
+                        PieceType::Knight,
+                    ] {
+                        let mut promoted = next.clone();
+                        if promoted.set_promotion(piece_type).is_ok()
+                            && Game::reaches(&promoted, target, plies_left - 1)
+                        {
+                            return true;
+                        }
+                    }
+                } else if Game::reaches(&next, target, plies_left - 1) {
+                    return true;
+                }
+            }
+        }
+        return false;
+    }
+}
+
+/// Index of `piece_type` in the piece count array used by `retro_analysis`.
+fn piece_type_idx(piece_type: PieceType) -> usize {
+    return match piece_type {
+        PieceType::King => 0,
+        PieceType::Queen => 1,
+        PieceType::Rook => 2,
+        PieceType::Knight => 3,
+        PieceType::Bishop => 4,
+        PieceType::Pawn => 5,
+    };
+}
+
+/// Returns how many more than `initial` pieces there are.
+fn excess(count: usize, initial: usize) -> usize {
+    return count.saturating_sub(initial);
+}
+
+/// Returns the least number of captures needed for pawns to end up on `pawn_files`,
+/// given that every pawn starts on its own file a-h and each capture moves a pawn one file.
+///
+/// This is a minimum cost matching of the pawns to distinct starting files. Sorts `pawn_files`.
+fn min_pawn_captures(pawn_files: &mut [usize]) -> usize {
+    pawn_files.sort();
+    let n = pawn_files.len();
+    if n > 8 {
+        return 0; // too many pawns, reported separately
+    }
+    // cost[i][j] is the least cost of matching the first i pawns to starting files among the first j files
+    let mut cost = [[usize::MAX; 9]; 9];
+    cost[0] = [0; 9];
+    for i in 1..=n {
+        for j in i..=8 {
+            let skip = cost[i][j - 1];
+            let take = cost[i - 1][j - 1].saturating_add(pawn_files[i - 1].abs_diff(j - 1));
+            cost[i][j] = skip.min(take);
+        }
+    }
+    return cost[n][8];
+}
@@ -0,0 +1,269 @@
+// Author: Eskil Nyberg
+
+/*!
+ * Standard Algebraic Notation (SAN), e.g. "Nf3", "exd5", "Rbd1", "e8=Q" and "O-O".
+ *
+ * Moves are both parsed from SAN (`make_move_san`) and written as SAN (`move_to_san`, `history_san`).
+ */
+
+use super::ChessError;
+use super::Game;
+use super::GameOverReason;
+use super::Move;
+use super::MoveResult;
+use super::PieceType;
+use super::Position;
+
+impl Game {
+    /// Parses the SAN move `san` for the active colour into the positions moved from and to,
+    /// and the piece type to promote to if the move is a promotion.
+    ///
+    /// Check, checkmate and annotation suffixes (`+`, `#`, `!`, `?`) are accepted and ignored.
+    ///
+    /// Errors if `san` is not valid SAN, or does not describe exactly one legal move.
+    pub(crate) fn parse_san(
+        &self,
+        san: &str,
+    ) -> Result<(Position, Position, Option<PieceType>), ChessError> {
+        let trimmed = san.trim().trim_end_matches(['+', '#', '!', '?']);
+
+        // Castling
+        let back_rank = match self.active_colour.is_white() {
+            true => 0,
+            false => 7,
+        };
+        match trimmed {
+            "O-O" | "0-0" => {
+                return Ok((
+                    Position::new(back_rank, 4)?,
+                    Position::new(back_rank, 6)?,
+                    None,
+                ))
+            }
+            "O-O-O" | "0-0-0" => {
+                return Ok((
+                    Position::new(back_rank, 4)?,
+                    Position::new(back_rank, 2)?,
+                    None,
+                ))
+            }
+            _ => {}
+        }
+
+        let mut chars: Vec<char> = trimmed.chars().filter(|ch| *ch != 'x').collect();
+
+        // Promotion, written as "e8=Q" or "e8Q"
+        let mut promotion = None;
+        if chars.len() > 2 && "QRBN".contains(chars[chars.len() - 1]) {
+            promotion = Some(PieceType::from_char(chars.pop().expect("not empty"))?);
+            if chars.last() == Some(&'=') {
+                chars.pop();
+            }
+        }
+
+        // Piece type
+        let piece_type = match chars.first() {
+            Some(ch) if "KQRBN".contains(*ch) => {
+                let piece_type = PieceType::from_char(*ch)?;
+                chars.remove(0);
+                piece_type
+            }
+            Some(_) => PieceType::Pawn,
+            None => {
+                return Err(ChessError::InvalidSan(format!(
+                    "'{}' is not a valid SAN move",
+                    san
+                )))
+            }
+        };
+
+        // Destination, and the disambiguating file and/or rank in front of it
+        if chars.len() < 2 || chars.len() > 4 {
+            return Err(ChessError::InvalidSan(format!(
+                "'{}' is not a valid SAN move",
+                san
+            )));
+        }
+        let to_str: String = chars[chars.len() - 2..].iter().collect();
+        let to_pos = Position::parse_str(&to_str)?;
+        let mut from_file = None;
+        let mut from_rank = None;
+        for ch in &chars[..chars.len() - 2] {
+            match ch {
+                'a'..='h' => from_file = Some(*ch as usize - 'a' as usize),
+                '1'..='8' => from_rank = Some(*ch as usize - '1' as usize),
+                _ => {
+                    return Err(ChessError::InvalidSan(format!(
+                        "'{}' is not a valid SAN move",
+                        san
+                    )))
+                }
+            }
+        }
+
+        // Find the one piece that matches the description and can make the move
+        let mut candidates = vec![];
+        for (i, piece) in self.board.iter().enumerate() {
+            let from_pos = Position::new_from_idx(i)?;
+            if piece.is_some_and(|p| p.piece_type == piece_type && p.colour == self.active_colour)
+                && (from_file.is_none() || from_file == Some(from_pos.file))
+                && (from_rank.is_none() || from_rank == Some(from_pos.rank))
+                && self._get_possible_moves(from_pos)?.contains(&to_pos)
+            {
+                candidates.push(from_pos);
+            }
+        }
+        return match candidates.len() {
+            1 => Ok((candidates[0], to_pos, promotion)),
+            0 => Err(ChessError::InvalidSan(format!(
+                "'{}' is not a legal move",
+                san
+            ))),
+            _ => Err(ChessError::InvalidSan(format!("'{}' is ambiguous", san))),
+        };
+    }
+
+    /// If the game is not over, try to perform the move `san` written in Standard Algebraic Notation (SAN).
+    ///
+    /// Supports disambiguation (`Rbd1`), captures (`exd5`), check and checkmate suffixes (`+`, `#`),
+    /// promotions (`e8=Q`, which also sets the promotion) and castling (`O-O`, `O-O-O`).
+    ///
+    /// Errors if the move is not legal, is ambiguous, is missing the promotion piece, or the game is over.
+    /// The game is not changed if an error is returned.
+    ///
+    /// # Example code
+    ///
+    /// ```rust
+    /// # use chess_engine::*;
+    /// let mut game = Game::new();
+    /// assert!(game.make_move_san("e4").is_ok());
+    /// assert!(game.make_move_san("e5").is_ok());
+    /// assert!(game.make_move_san("Nf3").is_ok());
+    /// assert!(game.make_move_san("Nf3").is_err()); // illegal for black
+    /// ```
+    pub fn make_move_san(&mut self, san: &str) -> Result<MoveResult, ChessError> {
+        let (from, to, promotion) = self.parse_san(san)?;
+        return self.play_move(Move {
+            promotion,
+            ..Move::new(from, to)
+        });
+    }
+
+    /// Returns the move `mv` of the active colour written in SAN, e.g. "Nf3", "exd5", "Rbd1", "e8=Q+" or "O-O".
+    ///
+    /// Pieces are disambiguated by file, rank or both, only when needed. Checks and checkmates get a `+` or `#` suffix.
+    /// If `mv` is a promotion without `mv.promotion`, the promotion and suffix are left out.
+    ///
+    /// Errors if the move is not legal or the game is over.
+    ///
+    /// # Example code
+    ///
+    /// ```rust
+    /// # use chess_engine::*;
+    /// let game = Game::new();
+    /// let mv = Move::new(Position::parse_str("g1").unwrap(), Position::parse_str("f3").unwrap());
+    /// assert_eq!(game.move_to_san(mv).unwrap(), "Nf3");
+    /// ```
+    pub fn move_to_san(&self, mv: Move) -> Result<String, ChessError> {
+        return self
+            .clone()
+            .play_and_write_san(mv.from, mv.to, mv.promotion);
+    }
+
+    /// Returns every move in the history written in SAN, in the order they were made, e.g. for a move list.
+    ///
+    /// See `move_to_san`. A move waiting on a promotion choice is written without the promotion.
+    pub fn history_san(&self) -> Vec<String> {
+        // Replay the history from the start, so that every move is written from the position it was made in.
+        let mut game = self.clone();
+        game.restrictions = None;
+        game.undo_moves(self.history.len())
+            .expect("the history has this many moves");
+
+        let mut sans = vec![];
+        for entry in &self.history {
+            sans.push(
+                game.play_and_write_san(entry.from, entry.to, entry.promotion)
+                    .expect("recorded moves are legal"),
+            );
+        }
+        return sans;
+    }
+
+    /// Performs the move from `from` to `to` (promoting to `promotion`, if given) and returns it written in SAN,
+    /// as seen from the position before the move.
+    fn play_and_write_san(
+        &mut self,
+        from: Position,
+        to: Position,
+        promotion: Option<PieceType>,
+    ) -> Result<String, ChessError> {
+        let piece = match self.get(from)? {
+            Some(piece) => piece,
+            None => {
+                return Err(ChessError::IllegalMove(
+                    "There is no piece on the square you are trying to move from".to_owned(),
+                ))
+            }
+        };
+
+        let mut san = String::new();
+        if piece.is_king() && (to.file as i32 - from.file as i32).abs() == 2 {
+            // Castling
+            san.push_str(if to.file == 6 { "O-O" } else { "O-O-O" });
+        } else {
+            let is_capture = self.board[to.idx].is_some()
+                || (piece.is_pawn() && to == self.en_passant_target && from.file != to.file);
+            if piece.is_pawn() {
+                if is_capture {
+                    san.push((b'a' + from.file as u8) as char);
+                }
+            } else {
+                san.push(piece.to_char());
+                // Disambiguate from the other pieces of the same type that can move to `to`
+                let mut others = vec![];
+                for (i, other) in self.board.iter().enumerate() {
+                    let other_pos = Position::new_from_idx(i)?;
+                    if other_pos != from
+                        && *other == Some(piece)
+                        && self._get_possible_moves(other_pos)?.contains(&to)
+                    {
+                        others.push(other_pos);
+                    }
+                }
+                let from_str = from.to_string();
+                if !others.is_empty() {
+                    if others.iter().all(|pos| pos.file != from.file) {
+                        san.push_str(&from_str[..1]);
+                    } else if others.iter().all(|pos| pos.rank != from.rank) {
+                        san.push_str(&from_str[1..]);
+                    } else {
+                        san.push_str(&from_str);
+                    }
+                }
+            }
+            if is_capture {
+                san.push('x');
+            }
+            san.push_str(&to.to_string());
+        }
+
+        if self.make_move_pos(from, to)?.promotion_pending {
+            match promotion {
+                Some(piece_type) => {
+                    san.push('=');
+                    san.push(piece_type.char());
+                    self.set_promotion(piece_type)?;
+                }
+                None => return Ok(san),
+            }
+        }
+
+        if self.game_over_reason == Some(GameOverReason::Checkmate) {
+            san.push('#');
+        } else if self.is_in_check(self.active_colour) {
+            san.push('+');
+        }
+        return Ok(san);
+    }
+}
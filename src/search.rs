@@ -0,0 +1,557 @@
+// Author: Eskil Nyberg
+
+/*!
+ * A minimax search with alpha-beta pruning, used to pick a move for a computer opponent.
+ */
+
+use super::attacks::KING_ATTACKS;
+use super::material::piece_value;
+use super::transposition::{Bound, TableEntry};
+use super::Game;
+use super::GameState;
+use super::Move;
+use super::TranspositionTable;
+
+/// The score of being checkmated, in centipawns. Mates found sooner score further from 0.
+const MATE_SCORE: i32 = 1_000_000;
+
+/// Scores further from 0 than this are mate scores.
+const MATE_THRESHOLD: i32 = MATE_SCORE - 1000;
+
+/// The bitboard of the centre squares d4, e4, d5 and e5.
+const CENTRE: u64 = (1 << 27) | (1 << 28) | (1 << 35) | (1 << 36);
+
+/// The move that the engine prefers in a position and its score, see `Game::evaluate_batch`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Evaluation {
+    /// The move that the engine prefers for the active colour, like `Game::best_move`.
+    pub best_move: Move,
+    /// The score of the position for the active colour in centipawns, by material only.
+    /// Scores above 900 000 or below -900 000 are mates, found sooner the further from 0 they are.
+    pub score: i32,
+}
+
+/// A playing style for the computer opponent, see `Game::best_move_as`.
+///
+/// Every personality still plays for mate, but values positions differently and picks at random among moves
+/// that it scores close to its best move, so that games against it do not always go the same way.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Personality {
+    /// Evaluates material only and always plays its best move, like `Game::best_move`.
+    Balanced,
+    /// Likes attacking the squares around the enemy king.
+    Aggressive,
+    /// Likes controlling the centre and having many moves available.
+    Positional,
+    /// Values piece activity over material, so it readily gives up pawns, and varies its moves the most.
+    GambitProne,
+}
+
+/// How a personality evaluates positions, in centipawns.
+struct Weights {
+    /// Per pawn of material (see `Game::material`).
+    material: i32,
+    /// Per square attacked, more than the opponent.
+    mobility: i32,
+    /// Per centre square attacked, more than the opponent.
+    centre: i32,
+    /// Per square around the enemy king attacked, more than the opponent.
+    king_pressure: i32,
+    /// Moves scored at most this much below the best move may be picked instead of it.
+    margin: i32,
+}
+
+impl Personality {
+    fn weights(&self) -> Weights {
+        return match self {
+            Personality::Balanced => Weights {
+                material: 100,
+                mobility: 0,
+                centre: 0,
+                king_pressure: 0,
+                margin: 0,
+            },
+            Personality::Aggressive => Weights {
+                material: 100,
+                mobility: 2,
+                centre: 5,
+                king_pressure: 15,
+                margin: 20,
+            },
+            Personality::Positional => Weights {
+                material: 100,
+                mobility: 4,
+                centre: 12,
+                king_pressure: 3,
+                margin: 10,
+            },
+            Personality::GambitProne => Weights {
+                material: 70,
+                mobility: 6,
+                centre: 8,
+                king_pressure: 6,
+                margin: 40,
+            },
+        };
+    }
+}
+
+impl Game {
+    /// Returns the move that the engine prefers for the active colour, searching `depth` half-moves ahead
+    /// with minimax and alpha-beta pruning. A depth of 0 is treated as 1.
+    ///
+    /// Positions are evaluated by material only, so the engine plays for mate and material.
+    /// At the last half-move, captures are played out until the position is quiet, so that recaptures are seen.
+    /// Moves of equal score are decided by the order of `get_all_possible_moves()`.
+    ///
+    /// Is None if the game is over or waiting on a promotion choice.
+    ///
+    /// NOTE! The moves are made and taken back on a single copy of the game, without its history,
+    /// but the number of positions grows quickly with the depth, so depths above 4 or so are slow.
+    ///
+    /// # Example code
+    ///
+    /// ```rust
+    /// use chess_engine::*;
+    ///
+    /// // White mates with Ra8
+    /// let game = Game::from_fen("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1").unwrap();
+    /// let mv = game.best_move(2).unwrap();
+    /// assert_eq!(mv.to, Position::parse_str("a8").unwrap());
+    /// ```
+    pub fn best_move(&self, depth: u32) -> Option<Move> {
+        if self.state != GameState::InProgress && self.state != GameState::Check {
+            return None;
+        }
+
+        return self
+            .search_root(depth, None)
+            .map(|evaluation| evaluation.best_move);
+    }
+
+    /// Returns the move that the engine prefers for the active colour like `best_move`, but remembers the scores
+    /// of the positions it searches in `table`, see `TranspositionTable`.
+    ///
+    /// Positions already in the table are not searched again, so the same table can be reused for
+    /// the next move of a game, and for searching deeper step by step.
+    ///
+    /// Is None if the game is over or waiting on a promotion choice.
+    pub fn best_move_with_table(&self, depth: u32, table: &mut TranspositionTable) -> Option<Move> {
+        if self.state != GameState::InProgress && self.state != GameState::Check {
+            return None;
+        }
+        return self
+            .search_root(depth, Some(table))
+            .map(|evaluation| evaluation.best_move);
+    }
+
+    /// Returns the best move and score of each of `games`, searching `depth` half-moves ahead like `best_move`,
+    /// e.g. to label a dataset of positions loaded with `from_fen`. The evaluations are in the order of `games`.
+    ///
+    /// The positions share `table`, so positions that the games have in common are only searched once,
+    /// see `best_move_with_table`.
+    ///
+    /// An evaluation is None if its game is over or waiting on a promotion choice.
+    ///
+    /// # Example code
+    ///
+    /// ```rust
+    /// use chess_engine::*;
+    ///
+    /// let games = [Game::new(), Game::from_fen("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1").unwrap()];
+    /// let mut table = TranspositionTable::new(1 << 16);
+    /// let evaluations = Game::evaluate_batch(&games, 2, &mut table);
+    /// assert_eq!(evaluations[0].unwrap().score, 0);
+    /// // White mates with Ra8
+    /// assert!(evaluations[1].unwrap().score > 900_000);
+    /// ```
+    pub fn evaluate_batch(
+        games: &[Game],
+        depth: u32,
+        table: &mut TranspositionTable,
+    ) -> Vec<Option<Evaluation>> {
+        return games
+            .iter()
+            .map(|game| {
+                if game.state != GameState::InProgress && game.state != GameState::Check {
+                    return None;
+                }
+                return game.search_root(depth, Some(&mut *table));
+            })
+            .collect();
+    }
+
+    /// Returns the same evaluations as `evaluate_batch`, but evaluates the games on all cores with rayon,
+    /// with the `parallel` feature.
+    ///
+    /// Each thread has its own table of `table_size` slots, which the positions it evaluates share.
+    #[cfg(feature = "parallel")]
+    pub fn evaluate_batch_parallel(
+        games: &[Game],
+        depth: u32,
+        table_size: usize,
+    ) -> Vec<Option<Evaluation>> {
+        use rayon::prelude::*;
+
+        return games
+            .par_iter()
+            .map_init(
+                || TranspositionTable::new(table_size),
+                |table, game| {
+                    if game.state != GameState::InProgress && game.state != GameState::Check {
+                        return None;
+                    }
+                    return game.search_root(depth, Some(table));
+                },
+            )
+            .collect();
+    }
+
+    /// Returns the same move as `best_move`, but searches the moves of the active colour on all cores with rayon,
+    /// with the `parallel` feature. Each move is searched on its own thread, and the threads share the best score
+    /// found so far to prune the rest.
+    ///
+    /// Is None if the game is over or waiting on a promotion choice.
+    ///
+    /// # Example code
+    ///
+    /// ```rust
+    /// use chess_engine::*;
+    ///
+    /// let game = Game::from_fen("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1").unwrap();
+    /// assert_eq!(game.best_move_parallel(2), game.best_move(2));
+    /// ```
+    #[cfg(feature = "parallel")]
+    pub fn best_move_parallel(&self, depth: u32) -> Option<Move> {
+        use rayon::prelude::*;
+        use std::sync::atomic::{AtomicI32, Ordering};
+
+        if self.state != GameState::InProgress && self.state != GameState::Check {
+            return None;
+        }
+
+        let weights = Personality::Balanced.weights();
+        let shared_best = AtomicI32::new(-MATE_SCORE - 1);
+        let scored: Vec<(Move, i32)> = self
+            .ordered_moves(None)
+            .into_par_iter()
+            .filter_map(|mv| {
+                let mut child = self.search_copy();
+                child.play_move(mv).ok()?;
+                // Scores equal to the best one are searched exactly, so that ties go to the first move like in `best_move`
+                let alpha = shared_best.load(Ordering::Relaxed) - 1;
+                let score = -child.alpha_beta(
+                    depth.max(1) - 1,
+                    -MATE_SCORE - 1,
+                    -alpha,
+                    1,
+                    &weights,
+                    None,
+                );
+                shared_best.fetch_max(score, Ordering::Relaxed);
+                return Some((mv, score));
+            })
+            .collect();
+
+        let mut best = None;
+        let mut best_score = -MATE_SCORE - 1;
+        for (mv, score) in scored {
+            if best.is_none() || score > best_score {
+                best_score = score;
+                best = Some(mv);
+            }
+        }
+        return best;
+    }
+
+    /// Returns the best move of the active colour and its score, searching `depth` half-moves ahead
+    /// with Balanced weights.
+    fn search_root(
+        &self,
+        depth: u32,
+        mut table: Option<&mut TranspositionTable>,
+    ) -> Option<Evaluation> {
+        let weights = Personality::Balanced.weights();
+        let first = table
+            .as_deref_mut()
+            .and_then(|table| table.probe(self.zobrist_hash()))
+            .and_then(|entry| entry.best_move);
+        let mut game = self.search_copy();
+        let mut best = None;
+        let mut alpha = -MATE_SCORE - 1;
+        for mv in self.ordered_moves(first) {
+            if game.play_move(mv).is_err() {
+                continue;
+            }
+            let score = -game.alpha_beta(
+                depth.max(1) - 1,
+                -MATE_SCORE - 1,
+                -alpha,
+                1,
+                &weights,
+                table.as_deref_mut(),
+            );
+            game.unmake_move();
+            if best.is_none() || score > alpha {
+                alpha = score;
+                best = Some(mv);
+            }
+        }
+        if let Some(table) = table {
+            table.store(TableEntry {
+                hash: self.zobrist_hash(),
+                depth: depth.max(1),
+                score: to_table(alpha, 0),
+                bound: Bound::Exact,
+                best_move: best,
+            });
+        }
+        return best.map(|best_move| Evaluation {
+            best_move,
+            score: alpha,
+        });
+    }
+
+    /// Returns the move that the engine prefers for the active colour when playing as `personality`,
+    /// searching `depth` half-moves ahead. A depth of 0 is treated as 1.
+    ///
+    /// Among the moves scored close to the best one, a move is picked at random, favouring higher scores.
+    /// The pick is decided by `seed`, so the same seed gives the same move.
+    ///
+    /// Is None if the game is over or waiting on a promotion choice.
+    ///
+    /// NOTE! Every move is searched with a full window, which is slower than `best_move`.
+    pub fn best_move_as(&self, depth: u32, personality: Personality, seed: u64) -> Option<Move> {
+        if self.state != GameState::InProgress && self.state != GameState::Check {
+            return None;
+        }
+
+        let weights = personality.weights();
+        let mut game = self.search_copy();
+        let mut scored = vec![];
+        for mv in self.ordered_moves(None) {
+            if game.play_move(mv).is_err() {
+                continue;
+            }
+            let score = -game.alpha_beta(
+                depth.max(1) - 1,
+                -MATE_SCORE - 1,
+                MATE_SCORE + 1,
+                1,
+                &weights,
+                None,
+            );
+            game.unmake_move();
+            scored.push((mv, score));
+        }
+        let best_score = scored.iter().map(|(_, score)| *score).max()?;
+
+        // Each candidate is weighted by how close it is to the best score
+        let candidates: Vec<(Move, i64)> = scored
+            .into_iter()
+            .filter(|(_, score)| best_score - score <= weights.margin)
+            .map(|(mv, score)| (mv, (weights.margin - (best_score - score) + 1) as i64))
+            .collect();
+        if weights.margin == 0 {
+            // Ties are decided by move order, like in `best_move`
+            return candidates.first().map(|(mv, _)| *mv);
+        }
+        let total: i64 = candidates.iter().map(|(_, weight)| weight).sum();
+        // A step of a linear congruential generator spreads close seeds apart
+        let random = seed
+            .wrapping_mul(6364136223846793005)
+            .wrapping_add(1442695040888963407)
+            >> 33;
+        let mut pick = (random % total as u64) as i64;
+        for (mv, weight) in candidates {
+            if pick < weight {
+                return Some(mv);
+            }
+            pick -= weight;
+        }
+        return None;
+    }
+
+    /// Returns the score of the position for the active colour in centipawns, searching `depth` half-moves ahead
+    /// with the window `alpha` to `beta`. `ply` is the number of half-moves from the root of the search.
+    ///
+    /// If a `table` is given, positions found in it are not searched again, and searched positions are stored in it.
+    fn alpha_beta(
+        &mut self,
+        depth: u32,
+        mut alpha: i32,
+        beta: i32,
+        ply: i32,
+        weights: &Weights,
+        mut table: Option<&mut TranspositionTable>,
+    ) -> i32 {
+        if self.is_gameover() {
+            return match self.get_winner() {
+                Some(winner) if winner == self.active_colour => MATE_SCORE - ply,
+                Some(_) => -MATE_SCORE + ply,
+                None => 0,
+            };
+        }
+        if depth == 0 {
+            return self.quiescence(alpha, beta, ply, weights);
+        }
+
+        let hash = match table {
+            Some(_) => self.zobrist_hash(),
+            None => 0,
+        };
+        let mut first = None;
+        if let Some(entry) = table.as_deref_mut().and_then(|table| table.probe(hash)) {
+            first = entry.best_move;
+            if entry.depth >= depth {
+                let score = from_table(entry.score, ply);
+                match entry.bound {
+                    Bound::Exact => return score.clamp(alpha, beta),
+                    Bound::Lower if score >= beta => return beta,
+                    Bound::Upper if score <= alpha => return alpha,
+                    _ => {}
+                }
+            }
+        }
+
+        let original_alpha = alpha;
+        let mut best_move = None;
+        for mv in self.ordered_moves(first) {
+            if self.play_move(mv).is_err() {
+                continue;
+            }
+            let score = -self.alpha_beta(
+                depth - 1,
+                -beta,
+                -alpha,
+                ply + 1,
+                weights,
+                table.as_deref_mut(),
+            );
+            self.unmake_move();
+            if score >= beta {
+                if let Some(table) = table {
+                    table.store(TableEntry {
+                        hash,
+                        depth,
+                        score: to_table(beta, ply),
+                        bound: Bound::Lower,
+                        best_move: Some(mv),
+                    });
+                }
+                return beta;
+            }
+            if score > alpha {
+                alpha = score;
+                best_move = Some(mv);
+            }
+        }
+        if let Some(table) = table {
+            table.store(TableEntry {
+                hash,
+                depth,
+                score: to_table(alpha, ply),
+                bound: if alpha > original_alpha {
+                    Bound::Exact
+                } else {
+                    Bound::Upper
+                },
+                best_move,
+            });
+        }
+        return alpha;
+    }
+
+    /// Returns the score of the position for the active colour in centipawns like `alpha_beta` at depth 0,
+    /// but plays out captures until the position is quiet.
+    ///
+    /// Without this, a search that stops right after a queen captures a pawn never sees the queen being recaptured
+    /// (the horizon effect). The active colour may also stand pat, i.e. take the evaluation without capturing.
+    fn quiescence(&mut self, mut alpha: i32, beta: i32, ply: i32, weights: &Weights) -> i32 {
+        if self.is_gameover() {
+            return match self.get_winner() {
+                Some(winner) if winner == self.active_colour => MATE_SCORE - ply,
+                Some(_) => -MATE_SCORE + ply,
+                None => 0,
+            };
+        }
+
+        let stand_pat = self.evaluate(weights);
+        if stand_pat >= beta {
+            return beta;
+        }
+        alpha = alpha.max(stand_pat);
+
+        // The most valuable pieces are captured first, so that cutoffs come early
+        let mut captures = self.get_capture_moves_all();
+        captures.sort_by_key(|mv| {
+            std::cmp::Reverse(
+                self.board[mv.to.idx].map_or(100, |piece| piece_value(piece.piece_type)),
+            )
+        });
+        for mv in captures {
+            if self.play_move(mv).is_err() {
+                continue;
+            }
+            let score = -self.quiescence(-beta, -alpha, ply + 1, weights);
+            self.unmake_move();
+            if score >= beta {
+                return beta;
+            }
+            alpha = alpha.max(score);
+        }
+        return alpha;
+    }
+
+    /// Returns the score of the position for the active colour in centipawns, as valued by `weights`.
+    fn evaluate(&self, weights: &Weights) -> i32 {
+        let colour = self.active_colour;
+        let mut score = self.material_advantage(colour) * weights.material / 100;
+        if weights.mobility == 0 && weights.centre == 0 && weights.king_pressure == 0 {
+            return score;
+        }
+
+        let own = Game::attacked_by(&self.board, colour);
+        let opponent = Game::attacked_by(&self.board, colour.invert());
+        let count = |attacked: u64, mask: u64| (attacked & mask).count_ones() as i32;
+        score += weights.mobility * (count(own, !0) - count(opponent, !0));
+        score += weights.centre * (count(own, CENTRE) - count(opponent, CENTRE));
+        if let (Ok(own_king), Ok(opponent_king)) =
+            (self.find_king(colour), self.find_king(colour.invert()))
+        {
+            score += weights.king_pressure
+                * (count(own, KING_ATTACKS[opponent_king.idx])
+                    - count(opponent, KING_ATTACKS[own_king.idx]));
+        }
+        return score;
+    }
+
+    /// Returns every possible move of the active colour, with `first` (if it is possible) and then captures first
+    /// so that good moves are searched early. The moves are made and taken back on a game from `search_copy`.
+    fn ordered_moves(&self, first: Option<Move>) -> Vec<Move> {
+        let mut moves = self.get_all_possible_moves();
+        moves.sort_by_key(|mv| (Some(*mv) != first, !mv.is_capture));
+        return moves;
+    }
+}
+
+/// Returns `score` at `ply` half-moves from the root as stored in a transposition table,
+/// where mate scores are counted from the position instead of from the root.
+fn to_table(score: i32, ply: i32) -> i32 {
+    if score > MATE_THRESHOLD {
+        return score + ply;
+    } else if score < -MATE_THRESHOLD {
+        return score - ply;
+    }
+    return score;
+}
+
+/// Returns the score stored in a transposition table as counted from the root, `ply` half-moves away, see `to_table`.
+fn from_table(score: i32, ply: i32) -> i32 {
+    if score > MATE_THRESHOLD {
+        return score - ply;
+    } else if score < -MATE_THRESHOLD {
+        return score + ply;
+    }
+    return score;
+}
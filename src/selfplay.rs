@@ -0,0 +1,155 @@
+// Author: Eskil Nyberg
+
+/*!
+ * Self-play games of the engine against itself, written out as one record per move, e.g. as training data.
+ */
+
+use std::collections::VecDeque;
+
+use super::Game;
+use super::Move;
+
+/// Options for `Game::self_play`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct SelfPlayOptions {
+    /// The number of games to play.
+    pub games: usize,
+    /// The number of half-moves after which a game is cut off, unless it has ended before.
+    pub max_plies: usize,
+    /// The number of half-moves at the start of each game that are random, so that the games differ.
+    pub random_plies: usize,
+    /// The depth that the remaining moves are searched to, see `Game::best_move`. With 0, every move is random.
+    pub search_depth: u32,
+}
+
+impl Default for SelfPlayOptions {
+    /// One game of at most 300 half-moves, with 4 random half-moves followed by searches of depth 1.
+    fn default() -> SelfPlayOptions {
+        return SelfPlayOptions {
+            games: 1,
+            max_plies: 300,
+            random_plies: 4,
+            search_depth: 1,
+        };
+    }
+}
+
+/// A position of a self-play game, the move chosen in it, and how the game ended.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SelfPlayRecord {
+    /// The FEN of the position.
+    pub fen: String,
+    /// The move chosen in the position.
+    pub chosen_move: Move,
+    /// The score of white in the game: 1 for a win, 0.5 for a draw and 0 for a loss, like `standings::GameResult::white_score`.
+    /// Is None if the game was cut off after `SelfPlayOptions::max_plies` half-moves.
+    pub white_score: Option<f32>,
+}
+
+/// The iterator returned by `Game::self_play`, which plays the next game when the records of the last one run out.
+#[derive(Clone, Debug)]
+pub struct SelfPlay {
+    start: Game,
+    options: SelfPlayOptions,
+    /// The state of the random number generator.
+    seed: u64,
+    games_played: usize,
+    /// The records of the last game played that have not been returned yet.
+    records: VecDeque<SelfPlayRecord>,
+    /// Reused for the possible moves of every position.
+    moves: Vec<Move>,
+}
+
+impl SelfPlay {
+    /// Returns the next number of the random number generator, a step of a linear congruential generator.
+    fn next_random(&mut self) -> u64 {
+        self.seed = self
+            .seed
+            .wrapping_mul(6364136223846793005)
+            .wrapping_add(1442695040888963407);
+        return self.seed >> 33;
+    }
+
+    /// Plays a game from the start position and queues its records.
+    fn play_game(&mut self) {
+        let mut game = self.start.clone();
+        let mut played = vec![];
+        while !game.is_gameover() && played.len() < self.options.max_plies {
+            let chosen_move =
+                if played.len() < self.options.random_plies || self.options.search_depth == 0 {
+                    game.generate_moves_into(&mut self.moves);
+                    if self.moves.is_empty() {
+                        break;
+                    }
+                    let pick = self.next_random() as usize % self.moves.len();
+                    self.moves[pick]
+                } else {
+                    match game.best_move(self.options.search_depth) {
+                        Some(mv) => mv,
+                        None => break,
+                    }
+                };
+            played.push((game.fen(), chosen_move));
+            game.play_move(chosen_move)
+                .expect("possible moves are legal");
+        }
+
+        let white_score = if game.is_gameover() {
+            Some(match game.get_winner() {
+                Some(winner) if winner.is_white() => 1.0,
+                Some(_) => 0.0,
+                None => 0.5,
+            })
+        } else {
+            None
+        };
+        self.records
+            .extend(played.into_iter().map(|(fen, chosen_move)| SelfPlayRecord {
+                fen,
+                chosen_move,
+                white_score,
+            }));
+        self.games_played += 1;
+    }
+}
+
+impl Iterator for SelfPlay {
+    type Item = SelfPlayRecord;
+
+    fn next(&mut self) -> Option<SelfPlayRecord> {
+        while self.records.is_empty() && self.games_played < self.options.games {
+            self.play_game();
+        }
+        return self.records.pop_front();
+    }
+}
+
+impl Game {
+    /// Returns an iterator over the records of self-play games from this position: for every move of every game,
+    /// the position, the move chosen and the result of the game, see `SelfPlayRecord`.
+    ///
+    /// The games are played one at a time as the records are needed, so records can be streamed to a file
+    /// while the games are played. The random moves are decided by `seed`, so the same seed gives the same games.
+    ///
+    /// # Example code
+    ///
+    /// ```rust
+    /// use chess_engine::*;
+    ///
+    /// let options = SelfPlayOptions { games: 2, max_plies: 20, ..SelfPlayOptions::default() };
+    /// for record in Game::new().self_play(options, 7) {
+    ///     let game = Game::from_fen(&record.fen).unwrap();
+    ///     assert!(game.get_all_possible_moves().contains(&record.chosen_move));
+    /// }
+    /// ```
+    pub fn self_play(&self, options: SelfPlayOptions, seed: u64) -> SelfPlay {
+        return SelfPlay {
+            start: self.clone(),
+            options,
+            seed,
+            games_played: 0,
+            records: VecDeque::new(),
+            moves: Vec::with_capacity(64),
+        };
+    }
+}
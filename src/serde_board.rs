@@ -0,0 +1,35 @@
+// Author: Eskil Nyberg
+
+/*!
+ * Serde support for boards, since serde only derives for arrays of at most 32 elements.
+ *
+ * Used on board fields through `#[serde(with = "serde_board")]`. A board is (de)serialized as a sequence of 64 squares.
+ */
+
+use std::convert::TryInto;
+
+use serde::de::Error;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use super::Piece;
+
+/// Serializes `board` as a sequence of its squares.
+pub(crate) fn serialize<S: Serializer>(
+    board: &[Option<Piece>; 8 * 8],
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    return board[..].serialize(serializer);
+}
+
+/// Deserializes a board from a sequence of its squares.
+///
+/// Errors if there are not exactly 64 squares.
+pub(crate) fn deserialize<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<[Option<Piece>; 8 * 8], D::Error> {
+    let squares: Vec<Option<Piece>> = Vec::deserialize(deserializer)?;
+    let len = squares.len();
+    return squares
+        .try_into()
+        .map_err(|_| D::Error::invalid_length(len, &"64 squares"));
+}
@@ -0,0 +1,319 @@
+// Author: Eskil Nyberg
+
+/*!
+ * A WebSocket server hosting many games at once, each identified by an id. Needs the `websocket` feature.
+ *
+ * Two players join a game, one per colour, and any number of spectators can watch it. Every change to the game
+ * is broadcast to all of them. The clients send text messages:
+ * * `NEW`: creates a game, answered with `GAME <id>`.
+ * * `JOIN <id> <white|black>`: plays the colour in the game, answered with `JOINED <id> <colour>` and its state.
+ *   A client plays at most one colour in a game.
+ * * `WATCH <id>`: watches the game, answered with its state.
+ * * `MOVE <id> <move>`: makes a move in coordinate notation (as in UCI), e.g. `MOVE 1 e2e4`.
+ * * `RESIGN <id>`: resigns the game.
+ *
+ * The state of a game is sent as `STATE <id> <last move or -> <result> <FEN>`, where the result is `*` while the game
+ * goes on, e.g. `STATE 1 e2e4 * rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq - 0 1`.
+ * Messages that cannot be carried out are answered with `ERROR <explanation>`.
+ *
+ * # Example code
+ *
+ * ```rust
+ * use chess_engine::server::*;
+ *
+ * let mut lobby = Lobby::new();
+ * assert_eq!(lobby.handle(1, "NEW"), vec![(1, "GAME 1".to_owned())]);
+ * lobby.handle(1, "JOIN 1 white");
+ * lobby.handle(2, "JOIN 1 black");
+ * let sent = lobby.handle(1, "MOVE 1 e2e4");
+ * // both players are told about the move
+ * assert_eq!(sent.len(), 2);
+ * assert!(sent[1].1.starts_with("STATE 1 e2e4 * "));
+ *
+ * // let server = GameServer::bind("0.0.0.0:8080").unwrap();
+ * // server.run();
+ * ```
+ */
+
+use std::collections::HashMap;
+use std::io;
+use std::net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::mpsc::{self, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use tungstenite::{Message, WebSocket};
+
+use super::Colour;
+use super::Game;
+use super::Move;
+
+/// Identifies a client connected to the server.
+pub type ClientId = u64;
+
+/// A game with its players and spectators.
+#[derive(Clone, Debug)]
+struct Session {
+    game: Game,
+    /// The clients playing white and black.
+    players: [Option<ClientId>; 2],
+    spectators: Vec<ClientId>,
+}
+
+impl Session {
+    /// Returns every client that follows the game.
+    fn clients(&self) -> Vec<ClientId> {
+        let mut clients: Vec<ClientId> = self.players.iter().flatten().copied().collect();
+        clients.extend(&self.spectators);
+        return clients;
+    }
+
+    /// Returns the colour that `client` plays, if any.
+    fn colour_of(&self, client: ClientId) -> Option<Colour> {
+        return match self.players {
+            [Some(white), _] if white == client => Some(Colour::White),
+            [_, Some(black)] if black == client => Some(Colour::Black),
+            _ => None,
+        };
+    }
+
+    /// Returns the `STATE` message of game `id`.
+    fn state_message(&self, id: u64) -> String {
+        let last_move = match self.game.last_move() {
+            Some(entry) => entry.as_move().to_string(),
+            None => "-".to_owned(),
+        };
+        return format!(
+            "STATE {} {} {} {}",
+            id,
+            last_move,
+            self.game.result_string().unwrap_or("*"),
+            self.game.fen()
+        );
+    }
+}
+
+/// The games of the server and who follows them, without the network: messages are handled one at a time,
+/// and the messages to send in return are given back. See the module documentation for the messages.
+#[derive(Clone, Debug, Default)]
+pub struct Lobby {
+    sessions: HashMap<u64, Session>,
+    next_id: u64,
+}
+
+impl Lobby {
+    /// Constructor for a lobby without games.
+    pub fn new() -> Lobby {
+        return Lobby {
+            sessions: HashMap::new(),
+            next_id: 1,
+        };
+    }
+
+    /// Returns the game with `id`, if any.
+    pub fn game(&self, id: u64) -> Option<&Game> {
+        return self.sessions.get(&id).map(|session| &session.game);
+    }
+
+    /// Handles the message `text` from `client`, and returns the messages to send, with the clients to send them to.
+    pub fn handle(&mut self, client: ClientId, text: &str) -> Vec<(ClientId, String)> {
+        return match self.try_handle(client, text) {
+            Ok(sent) => sent,
+            Err(message) => vec![(client, format!("ERROR {}", message))],
+        };
+    }
+
+    /// Forgets `client`, e.g. when it disconnects, so that its seat in a game can be taken by another client.
+    pub fn disconnect(&mut self, client: ClientId) {
+        for session in self.sessions.values_mut() {
+            for player in session.players.iter_mut() {
+                if *player == Some(client) {
+                    *player = None;
+                }
+            }
+            session.spectators.retain(|spectator| *spectator != client);
+        }
+    }
+
+    fn try_handle(
+        &mut self,
+        client: ClientId,
+        text: &str,
+    ) -> Result<Vec<(ClientId, String)>, String> {
+        let words: Vec<&str> = text.split_whitespace().collect();
+        if words == ["NEW"] {
+            let id = self.next_id;
+            self.next_id += 1;
+            self.sessions.insert(
+                id,
+                Session {
+                    game: Game::new(),
+                    players: [None, None],
+                    spectators: vec![],
+                },
+            );
+            return Ok(vec![(client, format!("GAME {}", id))]);
+        }
+
+        let (command, id, argument) = match words.as_slice() {
+            [command, id] => (*command, *id, None),
+            [command, id, argument] => (*command, *id, Some(*argument)),
+            _ => return Err(format!("'{}' is not a message", text)),
+        };
+        let id: u64 = id
+            .parse()
+            .map_err(|_| format!("'{}' is not a game id", id))?;
+        let session = self
+            .sessions
+            .get_mut(&id)
+            .ok_or_else(|| format!("There is no game {}", id))?;
+
+        match (command, argument) {
+            ("JOIN", Some(colour)) => {
+                let colour = match colour {
+                    "white" => Colour::White,
+                    "black" => Colour::Black,
+                    _ => return Err(format!("'{}' is not a colour", colour)),
+                };
+                if let Some(seated) = session.colour_of(client) {
+                    if seated != colour {
+                        return Err(format!("You already play {} in game {}", seated, id));
+                    }
+                }
+                let seat = &mut session.players[if colour.is_white() { 0 } else { 1 }];
+                if seat.is_some_and(|player| player != client) {
+                    return Err(format!("{} is already taken in game {}", colour, id));
+                }
+                *seat = Some(client);
+                return Ok(vec![
+                    (
+                        client,
+                        format!("JOINED {} {}", id, colour.to_string().to_lowercase()),
+                    ),
+                    (client, session.state_message(id)),
+                ]);
+            }
+            ("WATCH", None) => {
+                if !session.spectators.contains(&client) {
+                    session.spectators.push(client);
+                }
+                return Ok(vec![(client, session.state_message(id))]);
+            }
+            ("MOVE", Some(uci)) => {
+                let colour = session
+                    .colour_of(client)
+                    .ok_or_else(|| format!("You do not play in game {}", id))?;
+                if colour != session.game.get_active_colour() {
+                    return Err(format!("It is not {}'s turn in game {}", colour, id));
+                }
+                let mv = Move::from_uci(uci).map_err(|err| err.to_string())?;
+                session.game.play_move(mv).map_err(|err| err.to_string())?;
+            }
+            ("RESIGN", None) => {
+                let colour = session
+                    .colour_of(client)
+                    .ok_or_else(|| format!("You do not play in game {}", id))?;
+                session.game.resign(colour).map_err(|err| err.to_string())?;
+            }
+            _ => return Err(format!("'{}' is not a message", text)),
+        }
+
+        let state = session.state_message(id);
+        return Ok(session
+            .clients()
+            .into_iter()
+            .map(|client| (client, state.clone()))
+            .collect());
+    }
+}
+
+/// A WebSocket server for the games of a `Lobby`, with a thread per connected client.
+#[derive(Debug)]
+pub struct GameServer {
+    listener: TcpListener,
+}
+
+impl GameServer {
+    /// Constructor for a server listening at `addr`, e.g. "0.0.0.0:8080".
+    pub fn bind(addr: impl ToSocketAddrs) -> io::Result<GameServer> {
+        return Ok(GameServer {
+            listener: TcpListener::bind(addr)?,
+        });
+    }
+
+    /// Returns the address the server listens at, e.g. to find the port chosen when binding to port 0.
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        return self.listener.local_addr();
+    }
+
+    /// Accepts clients until the listener fails, which is returned as the error.
+    pub fn run(self) -> io::Result<()> {
+        let lobby = Arc::new(Mutex::new(Lobby::new()));
+        let outboxes: Arc<Mutex<HashMap<ClientId, Sender<String>>>> =
+            Arc::new(Mutex::new(HashMap::new()));
+        let mut next_client = 1;
+        loop {
+            let (stream, _) = self.listener.accept()?;
+            let client = next_client;
+            next_client += 1;
+            let lobby = Arc::clone(&lobby);
+            let outboxes = Arc::clone(&outboxes);
+            thread::spawn(move || {
+                let (sender, receiver) = mpsc::channel();
+                outboxes
+                    .lock()
+                    .expect("not poisoned")
+                    .insert(client, sender);
+                if let Ok(socket) = tungstenite::accept(stream) {
+                    serve_client(client, socket, &lobby, &outboxes, receiver);
+                }
+                outboxes.lock().expect("not poisoned").remove(&client);
+                lobby.lock().expect("not poisoned").disconnect(client);
+            });
+        }
+    }
+}
+
+/// Reads the messages of `client` from `socket` and handles them, and sends it the messages in its outbox,
+/// until the client disconnects.
+fn serve_client(
+    client: ClientId,
+    mut socket: WebSocket<TcpStream>,
+    lobby: &Mutex<Lobby>,
+    outboxes: &Mutex<HashMap<ClientId, Sender<String>>>,
+    outbox: mpsc::Receiver<String>,
+) {
+    // Reading waits at most this long, so that messages to the client are not held up
+    if socket
+        .get_ref()
+        .set_read_timeout(Some(Duration::from_millis(20)))
+        .is_err()
+    {
+        return;
+    }
+    loop {
+        match socket.read() {
+            Ok(Message::Text(text)) => {
+                let sent = lobby.lock().expect("not poisoned").handle(client, &text);
+                let outboxes = outboxes.lock().expect("not poisoned");
+                for (receiver, message) in sent {
+                    if let Some(outbox) = outboxes.get(&receiver) {
+                        let _ = outbox.send(message);
+                    }
+                }
+            }
+            Ok(Message::Close(_)) => return,
+            Ok(_) => {}
+            Err(tungstenite::Error::Io(err))
+                if err.kind() == io::ErrorKind::WouldBlock
+                    || err.kind() == io::ErrorKind::TimedOut => {}
+            Err(_) => return,
+        }
+        while let Ok(message) = outbox.try_recv() {
+            if socket.send(Message::Text(message)).is_err() {
+                return;
+            }
+        }
+    }
+}
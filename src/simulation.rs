@@ -0,0 +1,242 @@
+// Author: Eskil Nyberg
+
+/*!
+ * Simulations of many games between two configured players, with the results added up, e.g. for statistics
+ * or to compare engine settings.
+ */
+
+use std::collections::HashMap;
+use std::io::{self, Write};
+
+use super::Game;
+use super::GameOverReason;
+use super::GameState;
+use super::Move;
+use super::Personality;
+
+/// A player of simulated games, see `Game::simulate`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Player {
+    /// Plays a possible move picked at random.
+    Random,
+    /// Plays the move that the engine prefers as `personality`, searching `depth` half-moves ahead,
+    /// see `Game::best_move_as`.
+    Engine {
+        personality: Personality,
+        depth: u32,
+    },
+}
+
+impl Player {
+    /// Returns the move that the player makes in `game`. Random picks are decided by `seed`,
+    /// so the same seed gives the same move.
+    ///
+    /// Is None if the game is over or waiting on a promotion choice.
+    pub fn choose_move(&self, game: &Game, seed: u64) -> Option<Move> {
+        return match self {
+            Player::Random => {
+                if game.is_gameover()
+                    || game.get_game_state() == GameState::WaitingOnPromotionChoice
+                {
+                    return None;
+                }
+                let moves = game.get_all_possible_moves();
+                if moves.is_empty() {
+                    return None;
+                }
+                Some(moves[(next_random(seed) >> 33) as usize % moves.len()])
+            }
+            Player::Engine { personality, depth } => game.best_move_as(*depth, *personality, seed),
+        };
+    }
+}
+
+/// Options for `Game::simulate`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct SimulationOptions {
+    /// The number of games to play.
+    pub games: usize,
+    /// The number of half-moves after which a game is cut off, unless it has ended before.
+    pub max_plies: usize,
+    /// The number of half-moves at the start of each game that are random, so that games between
+    /// engines differ.
+    pub random_plies: usize,
+}
+
+impl Default for SimulationOptions {
+    /// 100 games of at most 300 half-moves, with 4 random half-moves at the start of each.
+    fn default() -> SimulationOptions {
+        return SimulationOptions {
+            games: 100,
+            max_plies: 300,
+            random_plies: 4,
+        };
+    }
+}
+
+/// The results of the games of a simulation, see `Game::simulate`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SimulationStats {
+    /// The number of games played.
+    pub games: usize,
+    /// The number of games won by white.
+    pub white_wins: usize,
+    /// The number of games won by black.
+    pub black_wins: usize,
+    /// The number of games drawn.
+    pub draws: usize,
+    /// The number of games cut off after `SimulationOptions::max_plies` half-moves.
+    pub unfinished: usize,
+    /// The number of half-moves of all games together.
+    pub plies: usize,
+    /// The number of games that ended for each reason.
+    pub reasons: HashMap<GameOverReason, usize>,
+}
+
+impl SimulationStats {
+    /// Returns the average number of half-moves of a game. Is 0.0 if no games have been played.
+    pub fn average_plies(&self) -> f64 {
+        if self.games == 0 {
+            return 0.0;
+        }
+        return self.plies as f64 / self.games as f64;
+    }
+
+    /// Adds the result of the finished or cut off `game`.
+    fn add(&mut self, game: &Game) {
+        self.games += 1;
+        self.plies += game.plies();
+        match game.get_game_over_reason() {
+            Some(reason) => *self.reasons.entry(reason).or_insert(0) += 1,
+            None => {
+                self.unfinished += 1;
+                return;
+            }
+        }
+        match game.get_winner() {
+            Some(winner) if winner.is_white() => self.white_wins += 1,
+            Some(_) => self.black_wins += 1,
+            None => self.draws += 1,
+        }
+    }
+}
+
+/// Returns the next number of the random number generator, a step of a linear congruential generator.
+fn next_random(state: u64) -> u64 {
+    return state
+        .wrapping_mul(6364136223846793005)
+        .wrapping_add(1442695040888963407);
+}
+
+impl Game {
+    /// Plays `options.games` games from this position between `white` and `black`, and returns the results
+    /// added up. If `pgn` is given, each game is written to it with `to_pgn` as soon as it is over,
+    /// so that long simulations can be streamed to a file.
+    ///
+    /// The random moves are decided by `seed`, so the same seed gives the same games.
+    ///
+    /// Errors if writing to `pgn` fails.
+    ///
+    /// # Example code
+    ///
+    /// ```rust
+    /// use chess_engine::*;
+    ///
+    /// let options = SimulationOptions { games: 4, max_plies: 40, random_plies: 2 };
+    /// let engine = Player::Engine { personality: Personality::Balanced, depth: 1 };
+    /// let mut pgn = vec![];
+    /// let stats = Game::new().simulate(engine, Player::Random, options, 7, Some(&mut pgn)).unwrap();
+    /// assert_eq!(stats.games, 4);
+    /// assert_eq!(stats.white_wins + stats.black_wins + stats.draws + stats.unfinished, 4);
+    /// assert_eq!(String::from_utf8(pgn).unwrap().matches("[Event ").count(), 4);
+    /// ```
+    pub fn simulate(
+        &self,
+        white: Player,
+        black: Player,
+        options: SimulationOptions,
+        seed: u64,
+        mut pgn: Option<&mut dyn Write>,
+    ) -> io::Result<SimulationStats> {
+        let mut stats = SimulationStats::default();
+        for index in 0..options.games {
+            let game = self.simulate_game(white, black, &options, seed, index);
+            stats.add(&game);
+            if let Some(pgn) = pgn.as_mut() {
+                writeln!(pgn, "{}", game.to_pgn())?;
+            }
+        }
+        return Ok(stats);
+    }
+
+    /// Plays the same games as `simulate`, but on all cores with rayon, with the `parallel` feature.
+    ///
+    /// The results are the same as for `simulate`, but the games are written to `pgn` in the order they end.
+    ///
+    /// Errors if writing to `pgn` fails.
+    #[cfg(feature = "parallel")]
+    pub fn simulate_parallel(
+        &self,
+        white: Player,
+        black: Player,
+        options: SimulationOptions,
+        seed: u64,
+        mut pgn: Option<&mut dyn Write>,
+    ) -> io::Result<SimulationStats> {
+        use rayon::prelude::*;
+        use std::sync::mpsc;
+
+        let (sender, receiver) = mpsc::channel();
+        return std::thread::scope(|scope| {
+            scope.spawn(move || {
+                (0..options.games)
+                    .into_par_iter()
+                    .for_each_with(sender, |sender, index| {
+                        let game = self.simulate_game(white, black, &options, seed, index);
+                        // The receiver only hangs up if writing failed, and then the games are not needed
+                        let _ = sender.send(game);
+                    });
+            });
+            // The games are added up and written on this thread, as they end
+            let mut stats = SimulationStats::default();
+            for game in receiver {
+                stats.add(&game);
+                if let Some(pgn) = pgn.as_mut() {
+                    writeln!(pgn, "{}", game.to_pgn())?;
+                }
+            }
+            return Ok(stats);
+        });
+    }
+
+    /// Plays game number `index` of a simulation from this position, and returns it with its history.
+    fn simulate_game(
+        &self,
+        white: Player,
+        black: Player,
+        options: &SimulationOptions,
+        seed: u64,
+        index: usize,
+    ) -> Game {
+        let mut game = self.clone();
+        // Each game has its own generator, so that the games do not depend on the order they are played in
+        let mut random = next_random(seed ^ next_random(index as u64));
+        for ply in 0..options.max_plies {
+            random = next_random(random);
+            let player = if ply < options.random_plies {
+                Player::Random
+            } else if game.get_active_colour().is_white() {
+                white
+            } else {
+                black
+            };
+            match player.choose_move(&game, random) {
+                Some(mv) => {
+                    game.play_move(mv).expect("possible moves are legal");
+                }
+                None => break,
+            }
+        }
+        return game;
+    }
+}
@@ -0,0 +1,290 @@
+// Author: Eskil Nyberg
+
+/*!
+ * Named files, ranks and squares, as a readable alternative to the rank and file indices of `Position`.
+ *
+ * For example, `Position::from(Square::E1)` is the same position as `Position::new(0, 4).unwrap()`.
+ */
+
+use std::convert::TryFrom;
+use std::fmt;
+
+use super::ChessError;
+use super::Position;
+
+/// A file (column) of the chessboard, from the a-file to the h-file.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum File {
+    A,
+    B,
+    C,
+    D,
+    E,
+    F,
+    G,
+    H,
+}
+
+impl File {
+    /// Every file, from the a-file to the h-file.
+    pub const ALL: [File; 8] = [
+        File::A,
+        File::B,
+        File::C,
+        File::D,
+        File::E,
+        File::F,
+        File::G,
+        File::H,
+    ];
+
+    /// Constructor for the file with index `file` 0-7, where 0 is the a-file.
+    ///
+    /// Errors if `file` is not 0-7.
+    pub fn new(file: usize) -> Result<File, ChessError> {
+        return match File::ALL.get(file) {
+            Some(file) => Ok(*file),
+            None => Err(ChessError::InvalidPosition(format!(
+                "Invalid file: {}; input should be between 0-7",
+                file
+            ))),
+        };
+    }
+
+    /// Returns the index 0-7 of the file, where 0 is the a-file.
+    pub fn index(&self) -> usize {
+        return *self as usize;
+    }
+
+    /// Returns the file `offset` files to the right (towards the h-file), or None if it is outside the board.
+    pub fn offset(&self, offset: i32) -> Option<File> {
+        return File::new(usize::try_from(self.index() as i32 + offset).ok()?).ok();
+    }
+
+    /// Returns the lowercase character a-h of the file.
+    pub fn to_char(&self) -> char {
+        return (b'a' + *self as u8) as char;
+    }
+}
+
+/// A rank (row) of the chessboard, from the first rank (white's back rank) to the eighth rank.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Rank {
+    First,
+    Second,
+    Third,
+    Fourth,
+    Fifth,
+    Sixth,
+    Seventh,
+    Eighth,
+}
+
+impl Rank {
+    /// Every rank, from the first rank to the eighth rank.
+    pub const ALL: [Rank; 8] = [
+        Rank::First,
+        Rank::Second,
+        Rank::Third,
+        Rank::Fourth,
+        Rank::Fifth,
+        Rank::Sixth,
+        Rank::Seventh,
+        Rank::Eighth,
+    ];
+
+    /// Constructor for the rank with index `rank` 0-7, where 0 is the first rank.
+    ///
+    /// Errors if `rank` is not 0-7.
+    pub fn new(rank: usize) -> Result<Rank, ChessError> {
+        return match Rank::ALL.get(rank) {
+            Some(rank) => Ok(*rank),
+            None => Err(ChessError::InvalidPosition(format!(
+                "Invalid rank: {}; input should be between 0-7",
+                rank
+            ))),
+        };
+    }
+
+    /// Returns the index 0-7 of the rank, where 0 is the first rank.
+    pub fn index(&self) -> usize {
+        return *self as usize;
+    }
+
+    /// Returns the rank `offset` ranks up (towards the eighth rank), or None if it is outside the board.
+    pub fn offset(&self, offset: i32) -> Option<Rank> {
+        return Rank::new(usize::try_from(self.index() as i32 + offset).ok()?).ok();
+    }
+
+    /// Returns the character 1-8 of the rank.
+    pub fn to_char(&self) -> char {
+        return (b'1' + *self as u8) as char;
+    }
+}
+
+/// A square of the chessboard, named by its file and rank, e.g. `Square::E1`.
+///
+/// Converts to and from `Position` with `From` and `TryFrom`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Square {
+    A1,
+    B1,
+    C1,
+    D1,
+    E1,
+    F1,
+    G1,
+    H1,
+    A2,
+    B2,
+    C2,
+    D2,
+    E2,
+    F2,
+    G2,
+    H2,
+    A3,
+    B3,
+    C3,
+    D3,
+    E3,
+    F3,
+    G3,
+    H3,
+    A4,
+    B4,
+    C4,
+    D4,
+    E4,
+    F4,
+    G4,
+    H4,
+    A5,
+    B5,
+    C5,
+    D5,
+    E5,
+    F5,
+    G5,
+    H5,
+    A6,
+    B6,
+    C6,
+    D6,
+    E6,
+    F6,
+    G6,
+    H6,
+    A7,
+    B7,
+    C7,
+    D7,
+    E7,
+    F7,
+    G7,
+    H7,
+    A8,
+    B8,
+    C8,
+    D8,
+    E8,
+    F8,
+    G8,
+    H8,
+}
+
+impl Square {
+    /// Every square, in the order of their index 0-63: a1, b1, ..., h1, a2, ..., h8.
+    #[rustfmt::skip]
+    pub const ALL: [Square; 64] = [
+        Square::A1, Square::B1, Square::C1, Square::D1, Square::E1, Square::F1, Square::G1, Square::H1,
+        Square::A2, Square::B2, Square::C2, Square::D2, Square::E2, Square::F2, Square::G2, Square::H2,
+        Square::A3, Square::B3, Square::C3, Square::D3, Square::E3, Square::F3, Square::G3, Square::H3,
+        Square::A4, Square::B4, Square::C4, Square::D4, Square::E4, Square::F4, Square::G4, Square::H4,
+        Square::A5, Square::B5, Square::C5, Square::D5, Square::E5, Square::F5, Square::G5, Square::H5,
+        Square::A6, Square::B6, Square::C6, Square::D6, Square::E6, Square::F6, Square::G6, Square::H6,
+        Square::A7, Square::B7, Square::C7, Square::D7, Square::E7, Square::F7, Square::G7, Square::H7,
+        Square::A8, Square::B8, Square::C8, Square::D8, Square::E8, Square::F8, Square::G8, Square::H8,
+    ];
+
+    /// Constructor for the square at `file` and `rank`.
+    pub fn new(file: File, rank: Rank) -> Square {
+        return Square::ALL[rank.index() * 8 + file.index()];
+    }
+
+    /// Constructor for the square with index `idx` 0-63, see `Position::new_from_idx`.
+    ///
+    /// Errors if `idx` is not 0-63.
+    pub fn new_from_idx(idx: usize) -> Result<Square, ChessError> {
+        return match Square::ALL.get(idx) {
+            Some(square) => Ok(*square),
+            None => Err(ChessError::InvalidPosition(format!(
+                "Invalid idx: {}; input should be between 0-63",
+                idx
+            ))),
+        };
+    }
+
+    /// Returns the index 0-63 of the square, as in `Position::idx`.
+    pub fn idx(&self) -> usize {
+        return *self as usize;
+    }
+
+    /// Returns the file of the square.
+    pub fn file(&self) -> File {
+        return File::ALL[self.idx() % 8];
+    }
+
+    /// Returns the rank of the square.
+    pub fn rank(&self) -> Rank {
+        return Rank::ALL[self.idx() / 8];
+    }
+
+    /// Returns the square `rank_offset` ranks up and `file_offset` files to the right, or None if it is outside the board.
+    pub fn offset(&self, rank_offset: i32, file_offset: i32) -> Option<Square> {
+        return Some(Square::new(
+            self.file().offset(file_offset)?,
+            self.rank().offset(rank_offset)?,
+        ));
+    }
+}
+
+impl From<Square> for Position {
+    fn from(square: Square) -> Position {
+        return Position::new_from_idx(square.idx()).expect("squares are on the board");
+    }
+}
+
+/// Errors if the position is not valid, e.g. `Position::NULL`.
+impl TryFrom<Position> for Square {
+    type Error = ChessError;
+
+    fn try_from(pos: Position) -> Result<Square, ChessError> {
+        pos.valid()?;
+        return Square::new_from_idx(pos.idx);
+    }
+}
+
+/// Prints the file as a lowercase character a-h.
+impl fmt::Display for File {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.to_char())
+    }
+}
+
+/// Prints the rank as a character 1-8.
+impl fmt::Display for Rank {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.to_char())
+    }
+}
+
+/// Prints the square on the format `XF`, e.g. e1, like `Position`.
+impl fmt::Display for Square {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}{}", self.file(), self.rank())
+    }
+}
@@ -0,0 +1,170 @@
+// Author: Eskil Nyberg
+
+/*!
+ * Tournament standings with tiebreaks, computed from game results, and crosstables for printing them.
+ *
+ * # Example code
+ *
+ * ```rust
+ * use chess_engine::standings::*;
+ *
+ * let results = [
+ *     GameResult { white: 1, black: 2, white_score: 1.0 },
+ *     GameResult { white: 3, black: 1, white_score: 0.5 },
+ *     GameResult { white: 2, black: 3, white_score: 0.0 },
+ * ];
+ * let table = standings(&results, &[]);
+ * assert_eq!(table[0].id, 1);
+ * assert_eq!(table[0].score, 1.5);
+ * println!("{}", crosstable(&results, &[]));
+ * ```
+ */
+
+/// The result of a game, given as the ids of the players.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct GameResult {
+    pub white: usize,
+    pub black: usize,
+    /// The score of white: 1 for a win, 0.5 for a draw and 0 for a loss.
+    pub white_score: f32,
+}
+
+/// The standing of a player, see `standings`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Standing {
+    /// The place of the player, counted from 1.
+    pub rank: usize,
+    pub id: usize,
+    /// The score of the player, including byes.
+    pub score: f32,
+    /// The sum of the scores of the player's opponents.
+    pub buchholz: f32,
+    /// The sum of the scores of the opponents the player beat, plus half the scores of the opponents the player drew.
+    pub sonneborn_berger: f32,
+    /// The score of the player in the games against the players with the same score.
+    pub direct_encounter: f32,
+}
+
+/// Returns the standings of every player in `results`, and of every player in `byes` (who gets one point per bye).
+///
+/// Players are ranked by score, then Buchholz, then Sonneborn-Berger, then direct encounter, then id.
+pub fn standings(results: &[GameResult], byes: &[usize]) -> Vec<Standing> {
+    let mut ids: Vec<usize> = results
+        .iter()
+        .flat_map(|r| [r.white, r.black])
+        .chain(byes.iter().copied())
+        .collect();
+    ids.sort();
+    ids.dedup();
+
+    let score = |id: usize| -> f32 {
+        let byes = byes.iter().filter(|b| **b == id).count() as f32;
+        return byes + games_of(results, id).iter().map(|(_, s)| s).sum::<f32>();
+    };
+
+    let mut table: Vec<Standing> = ids
+        .iter()
+        .map(|id| {
+            let games = games_of(results, *id);
+            let own_score = score(*id);
+            let tied: Vec<usize> = ids
+                .iter()
+                .copied()
+                .filter(|other| *other != *id && score(*other) == own_score)
+                .collect();
+            return Standing {
+                rank: 0,
+                id: *id,
+                score: own_score,
+                buchholz: games.iter().map(|(opponent, _)| score(*opponent)).sum(),
+                sonneborn_berger: games.iter().map(|(opponent, s)| s * score(*opponent)).sum(),
+                direct_encounter: games
+                    .iter()
+                    .filter(|(opponent, _)| tied.contains(opponent))
+                    .map(|(_, s)| s)
+                    .sum(),
+            };
+        })
+        .collect();
+
+    table.sort_by(|a, b| {
+        b.score
+            .partial_cmp(&a.score)
+            .expect("scores are not NaN")
+            .then(b.buchholz.partial_cmp(&a.buchholz).expect("not NaN"))
+            .then(
+                b.sonneborn_berger
+                    .partial_cmp(&a.sonneborn_berger)
+                    .expect("not NaN"),
+            )
+            .then(
+                b.direct_encounter
+                    .partial_cmp(&a.direct_encounter)
+                    .expect("not NaN"),
+            )
+            .then(a.id.cmp(&b.id))
+    });
+    for (i, standing) in table.iter_mut().enumerate() {
+        standing.rank = i + 1;
+    }
+    return table;
+}
+
+/// Returns the standings (see `standings`) as a crosstable, with one row per player.
+///
+/// The results against each opponent are given in the columns of the opponents' ranks,
+/// as 1 for a win, = for a draw, 0 for a loss and . if the players have not met.
+/// When players have met several times, every result is listed.
+pub fn crosstable(results: &[GameResult], byes: &[usize]) -> String {
+    let table = standings(results, byes);
+
+    let mut text = String::from("Rk   Id");
+    for standing in &table {
+        text.push_str(&format!(" {:>3}", standing.rank));
+    }
+    text.push_str("  Score   Buch     SB     DE\n");
+
+    for standing in &table {
+        text.push_str(&format!("{:>2} {:>4}", standing.rank, standing.id));
+        let games = games_of(results, standing.id);
+        for opponent in &table {
+            let cell: String = if opponent.id == standing.id {
+                "X".to_owned()
+            } else {
+                let against: String = games
+                    .iter()
+                    .filter(|(id, _)| *id == opponent.id)
+                    .map(|(_, s)| match s {
+                        s if *s == 1.0 => '1',
+                        s if *s == 0.0 => '0',
+                        _ => '=',
+                    })
+                    .collect();
+                if against.is_empty() {
+                    ".".to_owned()
+                } else {
+                    against
+                }
+            };
+            text.push_str(&format!(" {:>3}", cell));
+        }
+        text.push_str(&format!(
+            " {:>6.1} {:>6.1} {:>6.2} {:>6.1}\n",
+            standing.score, standing.buchholz, standing.sonneborn_berger, standing.direct_encounter
+        ));
+    }
+    return text;
+}
+
+/// Returns the games of the player `id` in `results` as (opponent, score of the player).
+fn games_of(results: &[GameResult], id: usize) -> Vec<(usize, f32)> {
+    let mut games = vec![];
+    for result in results {
+        if result.white == id {
+            games.push((result.black, result.white_score));
+        } else if result.black == id {
+            games.push((result.white, 1.0 - result.white_score));
+        }
+    }
+    return games;
+}
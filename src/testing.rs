@@ -0,0 +1,222 @@
+// Author: Eskil Nyberg
+
+/*!
+ * A correctness check of the move generation: the perft counts of standard test positions,
+ * compared to the counts of `Game::perft`. Also a benchmark of the move generation, see `Game::bench_movegen`.
+ *
+ * # Example code
+ *
+ * ```rust
+ * use chess_engine::testing::*;
+ *
+ * for result in run_perft_suite(2) {
+ *     assert!(result.passed, "{} counted {} nodes", result.name, result.nodes);
+ * }
+ * ```
+ */
+
+use std::fmt;
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+use super::Game;
+use super::STARTING_FEN;
+
+/// A test position with its known perft counts.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct PerftPosition {
+    pub name: &'static str,
+    pub fen: &'static str,
+    /// The number of move sequences from the position, starting at depth 1.
+    pub counts: &'static [u64],
+}
+
+impl PerftPosition {
+    /// Returns the known perft count at `depth`, or None if it is not known. A depth of 0 gives 1.
+    pub fn count(&self, depth: u32) -> Option<u64> {
+        if depth == 0 {
+            return Some(1);
+        }
+        return self.counts.get(depth as usize - 1).copied();
+    }
+}
+
+/// The standard perft test positions, see <https://www.chessprogramming.org/Perft_Results>,
+/// and a position where a capture draws by insufficient material, where `Game::perft` does not continue
+/// the drawn lines. The counts of the last position beyond depth 2 are those of this engine.
+pub const PERFT_POSITIONS: [PerftPosition; 7] = [
+    PerftPosition {
+        name: "Start position",
+        fen: STARTING_FEN,
+        counts: &[20, 400, 8902, 197281, 4865609, 119060324],
+    },
+    PerftPosition {
+        name: "Kiwipete",
+        fen: "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+        counts: &[48, 2039, 97862, 4085603, 193690690],
+    },
+    PerftPosition {
+        name: "Position 3",
+        fen: "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+        counts: &[14, 191, 2812, 43238, 674624, 11030083],
+    },
+    PerftPosition {
+        name: "Position 4",
+        fen: "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+        counts: &[6, 264, 9467, 422333, 15833292],
+    },
+    PerftPosition {
+        name: "Position 5",
+        fen: "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8",
+        counts: &[44, 1486, 62379, 2103487, 89941194],
+    },
+    PerftPosition {
+        name: "Position 6",
+        fen: "r4rk1/1pp1qppp/p1np1n2/2b1p1B1/2B1P1b1/P1NP1N2/1PP1QPPP/R4RK1 w - - 0 10",
+        counts: &[46, 2079, 89890, 3894594, 164075551],
+    },
+    PerftPosition {
+        name: "Drawn capture",
+        fen: "4k3/8/8/8/8/3p4/8/2N1K3 w - - 0 1",
+        counts: &[8, 42, 349, 2530],
+    },
+];
+
+/// The outcome of the perft of a test position, see `run_perft_suite`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct PerftResult {
+    pub name: &'static str,
+    pub fen: &'static str,
+    pub depth: u32,
+    /// The known count.
+    pub expected: u64,
+    /// The count of `Game::perft`.
+    pub nodes: u64,
+    /// True if the counts are the same.
+    pub passed: bool,
+}
+
+/// Runs `Game::perft` to `depth` on each of the `PERFT_POSITIONS` and compares the counts to the known ones.
+///
+/// Positions whose count is not known at `depth` are left out. The counts grow quickly with the depth:
+/// depth 3 takes about a second in a debug build, and depth 5 several minutes in a release build.
+pub fn run_perft_suite(depth: u32) -> Vec<PerftResult> {
+    let mut results = vec![];
+    for position in PERFT_POSITIONS.iter() {
+        let expected = match position.count(depth) {
+            Some(count) => count,
+            None => continue,
+        };
+        let game = Game::from_fen(position.fen).expect("the test positions are valid");
+        let nodes = game.perft(depth);
+        results.push(PerftResult {
+            name: position.name,
+            fen: position.fen,
+            depth,
+            expected,
+            nodes,
+            passed: nodes == expected,
+        });
+    }
+    return results;
+}
+
+/// The speed of the move generation, see `Game::bench_movegen`.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct BenchReport {
+    /// The number of times the moves of every position were generated.
+    pub iterations: u32,
+    /// The number of moves generated in total.
+    pub moves_generated: u64,
+    pub movegen_time: Duration,
+    /// The number of moves made and taken back in total.
+    pub moves_made: u64,
+    pub make_unmake_time: Duration,
+}
+
+impl BenchReport {
+    /// Returns the number of moves generated per second.
+    pub fn movegen_per_second(&self) -> f64 {
+        return self.moves_generated as f64 / self.movegen_time.as_secs_f64();
+    }
+
+    /// Returns the number of moves made and taken back per second.
+    pub fn make_unmake_per_second(&self) -> f64 {
+        return self.moves_made as f64 / self.make_unmake_time.as_secs_f64();
+    }
+}
+
+impl fmt::Display for BenchReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        return write!(
+            f,
+            "Move generation: {} moves in {:?} ({:.0} moves/s)\nMake and unmake: {} moves in {:?} ({:.0} moves/s)",
+            self.moves_generated,
+            self.movegen_time,
+            self.movegen_per_second(),
+            self.moves_made,
+            self.make_unmake_time,
+            self.make_unmake_per_second()
+        );
+    }
+}
+
+impl Game {
+    /// Measures the speed of the move generation on the `PERFT_POSITIONS`, e.g. to catch performance regressions.
+    ///
+    /// The moves of every position are generated `iterations` times, and every move is made with `play_move`
+    /// and taken back with `undo_move` `iterations` times. Build in release mode for meaningful numbers.
+    ///
+    /// # Example code
+    ///
+    /// ```rust
+    /// use chess_engine::*;
+    ///
+    /// let report = Game::bench_movegen(10);
+    /// assert_eq!(report.moves_generated, 10 * 186);
+    /// println!("{}", report);
+    /// ```
+    pub fn bench_movegen(iterations: u32) -> BenchReport {
+        let games: Vec<Game> = PERFT_POSITIONS
+            .iter()
+            .map(|position| Game::from_fen(position.fen).expect("the test positions are valid"))
+            // a finished game has no moves to make
+            .filter(|game| !game.is_gameover())
+            .collect();
+        let mut moves = Vec::with_capacity(64);
+
+        let mut moves_generated = 0;
+        let start = Instant::now();
+        for _ in 0..iterations {
+            for game in &games {
+                game.generate_moves_into(&mut moves);
+                moves_generated += black_box(&moves).len() as u64;
+            }
+        }
+        let movegen_time = start.elapsed();
+
+        let mut moves_made = 0;
+        let mut make_unmake_time = Duration::ZERO;
+        for game in &games {
+            let mut game = game.clone();
+            game.generate_moves_into(&mut moves);
+            let start = Instant::now();
+            for _ in 0..iterations {
+                for mv in &moves {
+                    game.play_move(*mv).expect("generated moves are legal");
+                    game.undo_move().expect("a move was made");
+                    moves_made += 1;
+                }
+            }
+            make_unmake_time += start.elapsed();
+        }
+
+        return BenchReport {
+            iterations,
+            moves_generated,
+            movegen_time,
+            moves_made,
+            make_unmake_time,
+        };
+    }
+}
@@ -0,0 +1,212 @@
+// Author: Eskil Nyberg
+
+/*!
+ * Zobrist hashing of positions, and the transposition table that the search stores scored positions in.
+ */
+
+use super::castling;
+use super::Game;
+use super::Move;
+use super::Position;
+
+/// The random keys that are combined into a Zobrist hash, see `Game::zobrist_hash`.
+struct ZobristKeys {
+    /// Indexed by colour (white 0, black 1) times 6 plus piece type, and by position.
+    pieces: [[u64; 64]; 12],
+    /// Combined when black is to move.
+    black_to_move: u64,
+    /// Indexed like `PositionKey::castling`: white kingside, white queenside, black kingside, black queenside.
+    castling: [u64; 4],
+    /// Indexed by the file of the en passant target.
+    en_passant: [u64; 8],
+}
+
+static ZOBRIST: ZobristKeys = zobrist_keys();
+
+/// Returns the next state and value of the SplitMix64 generator, which is good enough for spreading hash keys.
+const fn split_mix(state: u64) -> (u64, u64) {
+    let state = state.wrapping_add(0x9e3779b97f4a7c15);
+    let mut z = state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+    return (state, z ^ (z >> 31));
+}
+
+/// Returns the table `ZOBRIST`, with the same keys every run.
+const fn zobrist_keys() -> ZobristKeys {
+    let mut keys = ZobristKeys {
+        pieces: [[0; 64]; 12],
+        black_to_move: 0,
+        castling: [0; 4],
+        en_passant: [0; 8],
+    };
+    let mut state = 0;
+    let mut value;
+    let mut piece = 0;
+    while piece < 12 {
+        let mut idx = 0;
+        while idx < 64 {
+            (state, value) = split_mix(state);
+            keys.pieces[piece][idx] = value;
+            idx += 1;
+        }
+        piece += 1;
+    }
+    (state, value) = split_mix(state);
+    keys.black_to_move = value;
+    let mut i = 0;
+    while i < 4 {
+        (state, value) = split_mix(state);
+        keys.castling[i] = value;
+        i += 1;
+    }
+    let mut file = 0;
+    while file < 8 {
+        (state, value) = split_mix(state);
+        keys.en_passant[file] = value;
+        file += 1;
+    }
+    return keys;
+}
+
+/// Whether the score of a table entry is exact, or only a bound because the search of the position was cut off.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub(crate) enum Bound {
+    /// The score is the score of the position.
+    Exact,
+    /// The score of the position is at least the score.
+    Lower,
+    /// The score of the position is at most the score.
+    Upper,
+}
+
+/// A position scored by the search.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub(crate) struct TableEntry {
+    /// The Zobrist hash of the position, since different positions share slots in the table.
+    pub(crate) hash: u64,
+    /// The number of half-moves the position was searched ahead.
+    pub(crate) depth: u32,
+    /// The score for the active colour, with mate scores counted from the position instead of from the root.
+    pub(crate) score: i32,
+    pub(crate) bound: Bound,
+    /// The best move found, which is searched first when the position is searched again. Is None if no move raised alpha.
+    pub(crate) best_move: Option<Move>,
+}
+
+/// A transposition table, which remembers the scores of positions the search has seen, see `Game::best_move_with_table`.
+///
+/// The same position is often reached by different move orders. With a table, it is only searched once,
+/// and the best move found earlier is searched first, which makes the alpha-beta pruning cut off more.
+///
+/// The table has a fixed number of slots. A new entry replaces the old entry in its slot, so a larger table
+/// keeps more positions at the cost of memory. Use `hit_rate()` to see how well the size fits the searches made.
+///
+/// # Example code
+///
+/// ```rust
+/// use chess_engine::*;
+///
+/// let game = Game::new();
+/// let mut table = TranspositionTable::new(1 << 16);
+/// assert!(game.best_move_with_table(4, &mut table).is_some());
+/// assert!(table.hits() > 0);
+/// ```
+#[derive(Clone, Debug)]
+pub struct TranspositionTable {
+    entries: Vec<Option<TableEntry>>,
+    probes: u64,
+    hits: u64,
+}
+
+impl TranspositionTable {
+    /// Constructor for an empty table with `size` slots. A size of 0 is treated as 1.
+    ///
+    /// Each slot takes a few dozen bytes, so a million slots take a few dozen megabytes.
+    pub fn new(size: usize) -> TranspositionTable {
+        return TranspositionTable {
+            entries: vec![None; size.max(1)],
+            probes: 0,
+            hits: 0,
+        };
+    }
+
+    /// Returns the number of slots.
+    pub fn size(&self) -> usize {
+        return self.entries.len();
+    }
+
+    /// Returns the number of times the search has looked up a position.
+    pub fn probes(&self) -> u64 {
+        return self.probes;
+    }
+
+    /// Returns the number of times the search has looked up a position and found it.
+    pub fn hits(&self) -> u64 {
+        return self.hits;
+    }
+
+    /// Returns the share of lookups that found the position, 0.0 to 1.0. Is 0.0 if no lookups have been made.
+    pub fn hit_rate(&self) -> f64 {
+        if self.probes == 0 {
+            return 0.0;
+        }
+        return self.hits as f64 / self.probes as f64;
+    }
+
+    /// Removes every entry and resets the statistics.
+    pub fn clear(&mut self) {
+        self.entries.iter_mut().for_each(|entry| *entry = None);
+        self.probes = 0;
+        self.hits = 0;
+    }
+
+    /// Returns the entry of the position with Zobrist hash `hash`, if it is in the table.
+    pub(crate) fn probe(&mut self, hash: u64) -> Option<TableEntry> {
+        self.probes += 1;
+        let entry = self.entries[self.slot(hash)].filter(|entry| entry.hash == hash);
+        if entry.is_some() {
+            self.hits += 1;
+        }
+        return entry;
+    }
+
+    /// Stores `entry`, replacing the entry in its slot.
+    pub(crate) fn store(&mut self, entry: TableEntry) {
+        let slot = self.slot(entry.hash);
+        self.entries[slot] = Some(entry);
+    }
+
+    fn slot(&self, hash: u64) -> usize {
+        return (hash % self.entries.len() as u64) as usize;
+    }
+}
+
+impl Game {
+    /// Returns the Zobrist hash of the position: the pieces, the active colour, the castling rights and the en passant target.
+    ///
+    /// Equal positions have equal hashes, and different positions almost always have different hashes.
+    /// Unlike the repetition rules (see `is_threefold_repetition`), castling rights and en passant targets
+    /// make positions different even when the castling or capture cannot be made right now.
+    pub fn zobrist_hash(&self) -> u64 {
+        let mut hash = 0;
+        for (idx, piece) in self.board.iter().enumerate() {
+            if let Some(piece) = piece {
+                let colour = if piece.is_white() { 0 } else { 1 };
+                hash ^= ZOBRIST.pieces[colour * 6 + piece.piece_type as usize][idx];
+            }
+        }
+        if self.active_colour.is_black() {
+            hash ^= ZOBRIST.black_to_move;
+        }
+        for (i, (colour, side)) in castling::RIGHTS.iter().enumerate() {
+            if self.castling_rights.can_castle(*colour, *side) {
+                hash ^= ZOBRIST.castling[i];
+            }
+        }
+        if self.en_passant_target != Position::NULL {
+            hash ^= ZOBRIST.en_passant[self.en_passant_target.file];
+        }
+        return hash;
+    }
+}
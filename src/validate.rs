@@ -0,0 +1,316 @@
+// Author: Eskil Nyberg
+
+/*!
+ * Checks of the game: `validate_position` audits a position set up by hand for illegal configurations,
+ * and the internal consistency checks are run after every move and undo with the `debug-validate` feature.
+ */
+
+use std::fmt;
+
+use super::castling;
+use super::CastlingSide;
+use super::Colour;
+use super::Game;
+use super::GameState;
+use super::Piece;
+use super::PieceType;
+use super::Position;
+
+/// Something that makes a position illegal, see `Game::validate_position`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum PositionIssue {
+    /// The colour has no king.
+    MissingKing(Colour),
+    /// The colour has more than one king, the number of kings is given.
+    TooManyKings(Colour, usize),
+    /// The kings stand next to each other.
+    AdjacentKings,
+    /// The colour has more than 8 pawns, the number of pawns is given.
+    TooManyPawns(Colour, usize),
+    /// The colour has more than 16 pieces, the number of pieces is given.
+    TooManyPieces(Colour, usize),
+    /// A pawn stands on the first or eighth rank (other than a pawn waiting on its promotion choice).
+    PawnOnBackRank(Position),
+    /// The king of the colour that just moved is in check, i.e. the colour to move could capture it.
+    OpponentInCheck(Colour),
+    /// The colour has the right to castle to the given side (kingside if true) without its king and rook in place.
+    CastlingRightWithoutPieces(Colour, bool),
+    /// The en passant target is not behind a pawn that could just have moved two squares.
+    InvalidEnPassantTarget(Position),
+}
+
+impl Game {
+    /// Returns every reason the position on the board is illegal. An empty vector means the position is legal.
+    ///
+    /// Useful after setting up a position with `put`, `remove` or `from_fen`, since these do not check that
+    /// the position could occur in a game. The checks are: one king per colour, kings not adjacent,
+    /// at most 8 pawns and 16 pieces per colour, no pawns on the first or eighth rank,
+    /// the colour that just moved not in check, castling rights with the king and rook in place,
+    /// and an en passant target behind a pawn that could just have moved two squares.
+    ///
+    /// # Example code
+    ///
+    /// ```rust
+    /// use chess_engine::*;
+    ///
+    /// let mut game = Game::new();
+    /// game.remove(Position::parse_str("a1").unwrap()).unwrap();
+    /// assert_eq!(
+    ///     game.validate_position(),
+    ///     vec![PositionIssue::CastlingRightWithoutPieces(Colour::White, false)]
+    /// );
+    /// ```
+    pub fn validate_position(&self) -> Vec<PositionIssue> {
+        let mut issues = vec![];
+
+        let mut kings = vec![];
+        for colour in [Colour::White, Colour::Black] {
+            let pieces: Vec<&Piece> = self
+                .board
+                .iter()
+                .flatten()
+                .filter(|p| p.colour == colour)
+                .collect();
+            let king_count = pieces.iter().filter(|p| p.is_king()).count();
+            match king_count {
+                0 => issues.push(PositionIssue::MissingKing(colour)),
+                1 => kings.push(self.find_king(colour).expect("has a king")),
+                _ => issues.push(PositionIssue::TooManyKings(colour, king_count)),
+            }
+            let pawn_count = pieces.iter().filter(|p| p.is_pawn()).count();
+            if pawn_count > 8 {
+                issues.push(PositionIssue::TooManyPawns(colour, pawn_count));
+            }
+            if pieces.len() > 16 {
+                issues.push(PositionIssue::TooManyPieces(colour, pieces.len()));
+            }
+        }
+
+        if kings.len() == 2
+            && kings[0].rank.abs_diff(kings[1].rank) <= 1
+            && kings[0].file.abs_diff(kings[1].file) <= 1
+        {
+            issues.push(PositionIssue::AdjacentKings);
+        }
+
+        let promoting = match self.state {
+            GameState::WaitingOnPromotionChoice => self.find_pawn_to_promote().ok(),
+            _ => None,
+        };
+        for (i, piece) in self.board.iter().enumerate() {
+            let pos = Position::new_from_idx(i).expect("enumerated");
+            if piece.is_some_and(|p| p.is_pawn())
+                && (pos.rank == 0 || pos.rank == 7)
+                && promoting != Some(pos)
+            {
+                issues.push(PositionIssue::PawnOnBackRank(pos));
+            }
+        }
+
+        // The turn passes once the promotion is chosen
+        let just_moved = match self.state {
+            GameState::WaitingOnPromotionChoice => self.active_colour,
+            _ => self.active_colour.invert(),
+        };
+        if self.is_in_check(just_moved) {
+            issues.push(PositionIssue::OpponentInCheck(just_moved));
+        }
+
+        for (colour, side) in self.castling_rights_without_pieces() {
+            issues.push(PositionIssue::CastlingRightWithoutPieces(
+                colour,
+                side == CastlingSide::Kingside,
+            ));
+        }
+
+        if self.en_passant_target != Position::NULL {
+            let target = self.en_passant_target;
+            let dir = self.active_colour.pawn_dir();
+            let expected_rank = match self.active_colour {
+                Colour::White => 5,
+                Colour::Black => 2,
+            };
+            // The pawn stands in front of the target, and came from behind it
+            if target.rank != expected_rank
+                || self.board[target.idx].is_some()
+                || target
+                    .offset(dir, 0)
+                    .is_ok_and(|pos| self.board[pos.idx].is_some())
+                || target.offset(-dir, 0).is_ok_and(|pos| {
+                    self.board[pos.idx]
+                        != Some(Piece {
+                            piece_type: PieceType::Pawn,
+                            colour: self.active_colour.invert(),
+                        })
+                })
+            {
+                issues.push(PositionIssue::InvalidEnPassantTarget(target));
+            }
+        }
+
+        return issues;
+    }
+
+    /// Returns the castling rights whose king or rook is not in place.
+    pub(crate) fn castling_rights_without_pieces(&self) -> Vec<(Colour, CastlingSide)> {
+        let piece = |piece_type, colour| Some(Piece { piece_type, colour });
+        let mut rights = vec![];
+        for (colour, side) in castling::RIGHTS {
+            let rank = castling::home_rank(colour);
+            if self.castling_rights.can_castle(colour, side)
+                && (self.board[Position::idx(rank, 4)] != piece(PieceType::King, colour)
+                    || self.board[Position::idx(rank, side.rook_file())]
+                        != piece(PieceType::Rook, colour))
+            {
+                rights.push((colour, side));
+            }
+        }
+        return rights;
+    }
+
+    /// Returns true if there is an en passant target without a pawn of the inactive colour in front of it,
+    /// i.e. where the pawn that just moved two squares would stand.
+    pub(crate) fn en_passant_target_without_pawn(&self) -> bool {
+        if self.en_passant_target == Position::NULL {
+            return false;
+        }
+        let pawn = Some(Piece {
+            piece_type: PieceType::Pawn,
+            colour: self.active_colour.invert(),
+        });
+        return self
+            .en_passant_target
+            .offset(-self.active_colour.pawn_dir(), 0)
+            .map_or(true, |pos| self.board[pos.idx] != pawn);
+    }
+
+    /// Returns a description of every internal invariant that the game breaks. An empty vector means the game is consistent.
+    ///
+    /// The invariants are:
+    /// * each colour has exactly one king,
+    /// * every castling right has its king and rook in place,
+    /// * the en passant target is behind a pawn of the inactive colour that just moved two squares,
+    /// * the last position key matches the key computed for the current position.
+    #[cfg(any(test, feature = "debug-validate"))]
+    pub(crate) fn invariant_violations(&self) -> Vec<String> {
+        let mut violations = vec![];
+
+        for colour in [Colour::White, Colour::Black] {
+            let kings = self
+                .board
+                .iter()
+                .flatten()
+                .filter(|p| p.is_king() && p.colour == colour)
+                .count();
+            if kings != 1 {
+                violations.push(format!("{:?} has {} kings", colour, kings));
+            }
+        }
+
+        for (colour, side) in self.castling_rights_without_pieces() {
+            violations.push(format!(
+                "The {} {} castling right remains without the king and rook in place",
+                colour.to_string().to_lowercase(),
+                match side {
+                    CastlingSide::Kingside => "kingside",
+                    CastlingSide::Queenside => "queenside",
+                }
+            ));
+        }
+
+        if self.en_passant_target != Position::NULL {
+            let target = self.en_passant_target;
+            let pawn_pos = target.offset(-self.active_colour.pawn_dir(), 0);
+            let expected_rank = match self.active_colour {
+                Colour::White => 5,
+                Colour::Black => 2,
+            };
+            let just_moved = self.last_move_squares().is_none_or(|(from, to)| {
+                pawn_pos == Ok(to) && from.file == to.file && from.rank.abs_diff(to.rank) == 2
+            });
+            if target.rank != expected_rank
+                || self.board[target.idx].is_some()
+                || pawn_pos.is_ok_and(|pos| {
+                    self.board[pos.idx]
+                        != Some(Piece {
+                            piece_type: PieceType::Pawn,
+                            colour: self.active_colour.invert(),
+                        })
+                })
+                || !just_moved
+            {
+                violations.push(format!(
+                    "The en passant target {} does not follow a pawn's double step",
+                    target
+                ));
+            }
+        }
+
+        // The key is pushed once the turn passes, which waits on the promotion choice
+        if self.state != GameState::WaitingOnPromotionChoice
+            && self.position_keys.last() != Some(&self.position_key())
+        {
+            violations.push("The last position key does not match the position".to_owned());
+        }
+
+        return violations;
+    }
+
+    /// Panics with a dump of the game if it breaks some internal invariant, see `invariant_violations`.
+    #[cfg(feature = "debug-validate")]
+    pub(crate) fn assert_valid(&self) {
+        let violations = self.invariant_violations();
+        if !violations.is_empty() {
+            panic!(
+                "The game broke its invariants:\n{}\n\nFEN: {}\nState: {:?}\nEn passant target: {}\nBoard:\n{}\nHistory: {:?}",
+                violations.join("\n"),
+                self.fen(),
+                self.state,
+                self.en_passant_target,
+                self,
+                self.history
+            );
+        }
+    }
+}
+
+impl fmt::Display for PositionIssue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        return match self {
+            PositionIssue::MissingKing(colour) => write!(f, "{:?} has no king", colour),
+            PositionIssue::TooManyKings(colour, count) => {
+                write!(f, "{:?} has {} kings", colour, count)
+            }
+            PositionIssue::AdjacentKings => write!(f, "The kings stand next to each other"),
+            PositionIssue::TooManyPawns(colour, count) => {
+                write!(f, "{:?} has {} pawns, at most 8 are allowed", colour, count)
+            }
+            PositionIssue::TooManyPieces(colour, count) => {
+                write!(
+                    f,
+                    "{:?} has {} pieces, at most 16 are allowed",
+                    colour, count
+                )
+            }
+            PositionIssue::PawnOnBackRank(pos) => {
+                write!(f, "The pawn on {} stands on the first or eighth rank", pos)
+            }
+            PositionIssue::OpponentInCheck(colour) => write!(
+                f,
+                "The {:?} king is in check although it is not {:?}'s turn",
+                colour, colour
+            ),
+            PositionIssue::CastlingRightWithoutPieces(colour, kingside) => write!(
+                f,
+                "{:?} has the right to castle {} without the king and rook in place",
+                colour,
+                if *kingside { "kingside" } else { "queenside" }
+            ),
+            PositionIssue::InvalidEnPassantTarget(pos) => write!(
+                f,
+                "The en passant target {} is not behind a pawn that just moved two squares",
+                pos
+            ),
+        };
+    }
+}
@@ -0,0 +1,150 @@
+// Author: Eskil Nyberg
+
+/*!
+ * Chess variants, as rules that are added on top of the standard rules of a `Game`.
+ *
+ * A variant implements `Rules` and is set with `Game::set_rules`. It can forbid moves, declare a winner,
+ * turn off the insufficient material draw and change the piece types that pawns are promoted to, while the rest of the game (check, checkmate, stalemate,
+ * the repetition and move rules, history and undo) works as for standard chess.
+ *
+ * # Example code
+ *
+ * ```rust
+ * use chess_engine::variant::*;
+ * use chess_engine::*;
+ * use std::sync::Arc;
+ *
+ * let mut game = Game::new();
+ * game.set_rules(Arc::new(KingOfTheHill));
+ * for (from, to) in [("e2", "e4"), ("a7", "a6"), ("e1", "e2"), ("a6", "a5"), ("e2", "d3"), ("a5", "a4"), ("d3", "d4")] {
+ *     game.make_move(from, to).unwrap();
+ * }
+ * assert_eq!(game.get_game_over_reason(), Some(GameOverReason::VariantWin(Colour::White)));
+ * ```
+ */
+
+use std::fmt;
+use std::panic::RefUnwindSafe;
+
+use super::Colour;
+use super::Game;
+use super::PieceType;
+use super::Position;
+
+/// The rules of a chess variant, on top of the standard rules. Every method defaults to standard chess.
+///
+/// The methods are called while the game is being updated, so they must not make moves or list
+/// the possible moves of `game`, which would call them again.
+///
+/// The rules are shared between games, and must be `RefUnwindSafe` so that a `Game` stays unwind safe.
+pub trait Rules: fmt::Debug + Send + Sync + RefUnwindSafe {
+    /// Returns the name of the variant, e.g. for a PGN Variant tag.
+    fn name(&self) -> &str;
+
+    /// Returns true if the piece at `from` may move to `to`, where the move is legal in standard chess.
+    ///
+    /// Moves that are not allowed are left out of every list of possible moves, and are rejected by the move methods.
+    fn allows_move(&self, _game: &Game, _from: Position, _to: Position) -> bool {
+        return true;
+    }
+
+    /// Returns the colour that has won by a rule of the variant, if any.
+    ///
+    /// Is checked after every move, before checkmate and the draw rules, see `GameOverReason::VariantWin`.
+    fn winner(&self, _game: &Game) -> Option<Colour> {
+        return None;
+    }
+
+    /// Returns true if a position where no checkmate is possible ends the game as a draw,
+    /// see `GameOverReason::InsufficientMaterial` and `GameOverReason::DeadPosition`.
+    fn insufficient_material_is_draw(&self) -> bool {
+        return true;
+    }
+
+    /// Returns the piece types that a pawn may be promoted to, see `Game::promotion_pending`.
+    ///
+    /// A pawn is never promoted to a pawn, and a second king is not placed, so those are never accepted.
+    fn promotion_piece_types(&self) -> Vec<PieceType> {
+        return vec![
+            PieceType::Queen,
+            PieceType::Rook,
+            PieceType::Bishop,
+            PieceType::Knight,
+        ];
+    }
+}
+
+/// Returns the rules of a deserialized `Game`, since rules are not serialized.
+#[cfg(feature = "serde")]
+pub(crate) fn standard_rules() -> std::sync::Arc<dyn Rules> {
+    return std::sync::Arc::new(Standard);
+}
+
+/// Standard chess, the rules of a `Game` unless others are set.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct Standard;
+
+impl Rules for Standard {
+    fn name(&self) -> &str {
+        return "Standard";
+    }
+}
+
+/// King of the Hill: a colour also wins by moving its king to one of the centre squares d4, e4, d5 and e5.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct KingOfTheHill;
+
+impl Rules for KingOfTheHill {
+    fn name(&self) -> &str {
+        return "King of the Hill";
+    }
+
+    fn winner(&self, game: &Game) -> Option<Colour> {
+        for colour in [Colour::White, Colour::Black] {
+            if let Ok(king) = game.find_king(colour) {
+                if (3..=4).contains(&king.rank) && (3..=4).contains(&king.file) {
+                    return Some(colour);
+                }
+            }
+        }
+        return None;
+    }
+
+    fn insufficient_material_is_draw(&self) -> bool {
+        // A lone king can still walk to the centre
+        return false;
+    }
+}
+
+/// Three-check: a colour also wins by checking the opponent's king for the third time.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct ThreeCheck;
+
+impl ThreeCheck {
+    /// Returns the number of checks that `colour` has given in `game`, counted from its history.
+    pub fn checks_given(game: &Game, colour: Colour) -> usize {
+        return game
+            .history
+            .iter()
+            .filter(|entry| entry.is_check && entry.piece_moved.colour == colour)
+            .count();
+    }
+}
+
+impl Rules for ThreeCheck {
+    fn name(&self) -> &str {
+        return "Three-check";
+    }
+
+    fn winner(&self, game: &Game) -> Option<Colour> {
+        return [Colour::White, Colour::Black]
+            .iter()
+            .copied()
+            .find(|colour| ThreeCheck::checks_given(game, *colour) >= 3);
+    }
+
+    fn insufficient_material_is_draw(&self) -> bool {
+        // Any piece but a king can still give check. Two bare kings are left to the move rules.
+        return false;
+    }
+}
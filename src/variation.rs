@@ -0,0 +1,254 @@
+// Author: Eskil Nyberg
+
+/*!
+ * The variation tree of a game: every line of moves played from the start, also those that were taken back,
+ * so that an analysis board can return to them.
+ */
+
+use super::ChessError;
+use super::Game;
+use super::Move;
+
+/// A move in the variation tree, linked to its parent, its first continuation and the next move played instead of it.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+struct VariationNode {
+    /// None for the root, which is the position before the first move.
+    mv: Option<Move>,
+    parent: usize,
+    /// The main continuation.
+    first_child: Option<usize>,
+    next_sibling: Option<usize>,
+}
+
+/// The tree of the moves played in a game, where the continuations of a position are ordered with the main line first.
+///
+/// The nodes are stored in a vector and refer to each other by index, so that cloning the tree, which happens
+/// with every clone of the game, is a single copy.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub(crate) struct VariationTree {
+    nodes: Vec<VariationNode>,
+    /// The node of the last move made, i.e. the current position.
+    current: usize,
+}
+
+/// Returns true if the moves are the same, ignoring their flags.
+fn same_move(a: Move, b: Move) -> bool {
+    return a.from == b.from && a.to == b.to && a.promotion == b.promotion;
+}
+
+impl VariationTree {
+    /// Constructor for a tree without moves.
+    pub(crate) fn new() -> VariationTree {
+        return VariationTree {
+            nodes: vec![VariationNode {
+                mv: None,
+                parent: 0,
+                first_child: None,
+                next_sibling: None,
+            }],
+            current: 0,
+        };
+    }
+
+    /// Returns the nodes of the continuations of `node`, the main line first.
+    fn children(&self, node: usize) -> Vec<usize> {
+        let mut children = vec![];
+        let mut child = self.nodes[node].first_child;
+        while let Some(idx) = child {
+            children.push(idx);
+            child = self.nodes[idx].next_sibling;
+        }
+        return children;
+    }
+
+    /// Returns the continuation of the current position that is `mv`, if any.
+    fn find(&self, mv: Move) -> Option<usize> {
+        return self
+            .children(self.current)
+            .into_iter()
+            .find(|idx| self.nodes[*idx].mv.is_some_and(|m| same_move(m, mv)));
+    }
+
+    /// Records that `mv` was made from the current position. A move not played there before becomes a new variation,
+    /// after the existing ones.
+    pub(crate) fn record(&mut self, mv: Move) {
+        if let Some(idx) = self.find(mv) {
+            self.current = idx;
+            return;
+        }
+        let idx = self.nodes.len();
+        self.nodes.push(VariationNode {
+            mv: Some(mv),
+            parent: self.current,
+            first_child: None,
+            next_sibling: None,
+        });
+        match self.children(self.current).last() {
+            Some(last) => self.nodes[*last].next_sibling = Some(idx),
+            None => self.nodes[self.current].first_child = Some(idx),
+        }
+        self.current = idx;
+    }
+
+    /// Steps back to the position before the last move, which is kept in the tree.
+    pub(crate) fn back(&mut self) {
+        self.current = self.nodes[self.current].parent;
+    }
+
+    /// Drops the newest move of the tree if it has no continuations and is not the current position,
+    /// e.g. a move of a search that was just taken back, see `Game::unmake_move`.
+    pub(crate) fn forget_last(&mut self) {
+        let idx = self.nodes.len() - 1;
+        if idx == 0 || idx == self.current || self.nodes[idx].first_child.is_some() {
+            return;
+        }
+        self.unlink(idx);
+        self.nodes.pop();
+    }
+
+    /// Returns the moves played from the current position, the main line first.
+    pub(crate) fn continuations(&self) -> Vec<Move> {
+        return self
+            .children(self.current)
+            .into_iter()
+            .filter_map(|idx| self.nodes[idx].mv)
+            .collect();
+    }
+
+    /// Returns the moves from the start that follow the main continuation of every position.
+    pub(crate) fn main_line(&self) -> Vec<Move> {
+        let mut moves = vec![];
+        let mut node = self.nodes[0].first_child;
+        while let Some(idx) = node {
+            moves.extend(self.nodes[idx].mv);
+            node = self.nodes[idx].first_child;
+        }
+        return moves;
+    }
+
+    /// Makes the continuation `mv` of the current position the main one. Returns false if `mv` was not played there.
+    pub(crate) fn promote(&mut self, mv: Move) -> bool {
+        let idx = match self.find(mv) {
+            Some(idx) => idx,
+            None => return false,
+        };
+        self.unlink(idx);
+        self.nodes[idx].next_sibling = self.nodes[self.current].first_child;
+        self.nodes[self.current].first_child = Some(idx);
+        return true;
+    }
+
+    /// Removes the continuation `mv` of the current position with every move after it.
+    /// Returns false if `mv` was not played there.
+    pub(crate) fn delete(&mut self, mv: Move) -> bool {
+        let idx = match self.find(mv) {
+            Some(idx) => idx,
+            None => return false,
+        };
+        self.unlink(idx);
+        self.compact();
+        return true;
+    }
+
+    /// Removes `idx` from the continuations of its parent.
+    fn unlink(&mut self, idx: usize) {
+        let parent = self.nodes[idx].parent;
+        let next = self.nodes[idx].next_sibling;
+        if self.nodes[parent].first_child == Some(idx) {
+            self.nodes[parent].first_child = next;
+        } else {
+            let before = self
+                .children(parent)
+                .into_iter()
+                .find(|child| self.nodes[*child].next_sibling == Some(idx))
+                .expect("idx is a child of its parent");
+            self.nodes[before].next_sibling = next;
+        }
+        self.nodes[idx].next_sibling = None;
+    }
+
+    /// Drops the nodes that can no longer be reached from the root, renumbering the rest.
+    fn compact(&mut self) {
+        let mut new_idx = vec![None; self.nodes.len()];
+        let mut order = vec![0];
+        new_idx[0] = Some(0);
+        let mut i = 0;
+        while i < order.len() {
+            for child in self.children(order[i]) {
+                new_idx[child] = Some(order.len());
+                order.push(child);
+            }
+            i += 1;
+        }
+        let renumber = |idx: Option<usize>| idx.map(|idx| new_idx[idx].expect("reachable"));
+        self.nodes = order
+            .iter()
+            .map(|idx| {
+                let node = self.nodes[*idx];
+                return VariationNode {
+                    mv: node.mv,
+                    parent: new_idx[node.parent].expect("reachable"),
+                    first_child: renumber(node.first_child),
+                    next_sibling: renumber(node.next_sibling),
+                };
+            })
+            .collect();
+        self.current = new_idx[self.current].expect("the current position is not deleted");
+    }
+}
+
+impl Game {
+    /// Returns the moves that have been played from the current position, also those that were taken back,
+    /// with the main line first. Make one of them with `play_move` to enter its variation.
+    ///
+    /// After taking back a move, making another move does not forget the first one: it becomes a variation.
+    /// The moves have no flags set, see `Move`.
+    ///
+    /// # Example code
+    ///
+    /// ```rust
+    /// use chess_engine::*;
+    ///
+    /// let mut game = Game::new();
+    /// game.make_move_uci("e2e4").unwrap();
+    /// game.undo_move().unwrap();
+    /// game.make_move_uci("d2d4").unwrap();
+    /// game.undo_move().unwrap();
+    /// assert_eq!(game.variations(), vec![Move::from_uci("e2e4").unwrap(), Move::from_uci("d2d4").unwrap()]);
+    ///
+    /// game.promote_variation(Move::from_uci("d2d4").unwrap()).unwrap();
+    /// game.delete_variation(Move::from_uci("e2e4").unwrap()).unwrap();
+    /// assert_eq!(game.main_line(), vec![Move::from_uci("d2d4").unwrap()]);
+    /// ```
+    pub fn variations(&self) -> Vec<Move> {
+        return self.variations.continuations();
+    }
+
+    /// Returns the main line of the game: the moves from the start that follow the first variation of every position,
+    /// see `variations`. The moves have no flags set.
+    pub fn main_line(&self) -> Vec<Move> {
+        return self.variations.main_line();
+    }
+
+    /// Makes the variation starting with `mv` the main line from the current position, see `variations`.
+    ///
+    /// Errors if `mv` has not been played from the current position.
+    pub fn promote_variation(&mut self, mv: Move) -> Result<(), ChessError> {
+        if !self.variations.promote(mv) {
+            return Err(ChessError::NoSuchVariation(mv.to_string()));
+        }
+        return Ok(());
+    }
+
+    /// Deletes the variation starting with `mv` from the current position, with every move after it, see `variations`.
+    ///
+    /// Errors if `mv` has not been played from the current position.
+    pub fn delete_variation(&mut self, mv: Move) -> Result<(), ChessError> {
+        if !self.variations.delete(mv) {
+            return Err(ChessError::NoSuchVariation(mv.to_string()));
+        }
+        return Ok(());
+    }
+}